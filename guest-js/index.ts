@@ -6,9 +6,378 @@ import { invoke } from "@tauri-apps/api/tauri";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
 
 interface ChangePayload<T> {
+  /** The version of the payload, missing from payloads emitted in the legacy shape. */
+  schema?: number;
   path: string;
   key: string;
   value: T | null;
+  window?: string | null;
+  txnId?: string | null;
+}
+
+/**
+ * Identifies the write that caused a change.
+ */
+export interface ChangeContext {
+  /** The label of the window that made the change, `null` if it was made by the backend. */
+  window: string | null;
+  /** The `txnId` passed to the write, if any. */
+  txnId: string | null;
+}
+
+/**
+ * Options accepted by the methods modifying a store.
+ */
+export interface WriteOptions {
+  /**
+   * An id included in the resulting change events,
+   * so listeners can recognize the events caused by their own writes.
+   */
+  txnId?: string;
+}
+
+export interface ScopeOptions {
+  /** Only include keys starting with this prefix, which is stripped from returned keys. */
+  scope?: string;
+}
+
+/**
+ * A single JSON Patch (RFC 6902) operation on a top-level store key.
+ */
+export type PatchOperation<T = unknown> =
+  | { op: "add"; path: string; value: T }
+  | { op: "remove"; path: string }
+  | { op: "replace"; path: string; value: T };
+
+interface ResetPayload<T> {
+  schema: number;
+  path: string;
+  patch: Array<PatchOperation<T>>;
+}
+
+/**
+ * Decodes a top-level JSON Pointer (RFC 6901) back into a store key.
+ */
+function pointerToKey(pointer: string): string {
+  return pointer.slice(1).replace(/~1/g, "/").replace(/~0/g, "~");
+}
+
+/**
+ * The order in which `keysSorted` returns keys.
+ *
+ * - `lexicographic`: plain string ordering.
+ * - `numeric`: compares runs of digits by their numeric value, so `item2` sorts before `item10`.
+ * - `reverse`: reverse string ordering.
+ */
+export type KeyOrder = "lexicographic" | "numeric" | "reverse";
+
+/**
+ * The JSON type of a stored value, as returned by `typeOf`.
+ * `missing` means the key does not exist.
+ */
+export type ValueType =
+  | "string"
+  | "number"
+  | "bool"
+  | "object"
+  | "array"
+  | "null"
+  | "missing";
+
+/**
+ * The differences between two stores, as returned by `diff`.
+ */
+export interface StoreDiff<T = unknown> {
+  /** Keys only present in the other store. */
+  added: Record<string, T>;
+  /** Keys only present in this store. */
+  removed: Record<string, T>;
+  /** Keys present in both stores with different values. */
+  changed: Record<string, { old: T; new: T }>;
+}
+
+/**
+ * How `importMap` handles keys that already exist in the store.
+ *
+ * - `skip`: keep the existing value.
+ * - `overwrite`: replace the existing value with the imported one.
+ * - `fail`: abort the import without changing anything.
+ */
+export type ConflictStrategy = "skip" | "overwrite" | "fail";
+
+/**
+ * The context allowed to modify a store in single-writer mode.
+ */
+export type Writer = { kind: "backend" } | { kind: "window"; label: string };
+
+export interface ImportOptions {
+  /** Defaults to `skip`. */
+  strategy?: ConflictStrategy;
+  /** Don't emit change events for the imported keys. */
+  silent?: boolean;
+  /**
+   * A key marking the import as done. If it already exists the import is skipped,
+   * otherwise it is set to `true` once the import succeeded.
+   */
+  marker?: string;
+}
+
+/**
+ * Saves all stores of a group registered on the backend as a unit.
+ *
+ * If one of the stores fails to save, the others are restored to their previous on-disk state.
+ *
+ * @param group
+ * @returns
+ */
+export async function saveGroup(group: string): Promise<void> {
+  return await invoke("plugin:store|save_group", { group });
+}
+
+/**
+ * Reloads all stores of a group registered on the backend from disk.
+ *
+ * If one of the stores fails to load, none of them are changed.
+ *
+ * @param group
+ * @returns
+ */
+export async function reloadGroup(group: string): Promise<void> {
+  return await invoke("plugin:store|reload_group", { group });
+}
+
+/**
+ * Selects keys by glob patterns, where `*` matches any sequence of characters and `?` matches a single one.
+ *
+ * A key is selected if it matches at least one `include` pattern (or `include` is empty) and no `exclude` pattern.
+ */
+export interface KeyFilter {
+  include?: string[];
+  exclude?: string[];
+}
+
+/**
+ * An aggregation computed by {@linkcode Store.aggregate}.
+ */
+export type Aggregate = "count" | "sum" | "min" | "max";
+
+/**
+ * An item of a queue stored in a `Store`.
+ */
+export interface QueueItem<T> {
+  id: number;
+  value: T;
+}
+
+/**
+ * A mutation recorded by a store with history enabled on the backend.
+ */
+export interface HistoryEntry<T> {
+  key: string;
+  /** The new value, or `null` if the key was removed. */
+  value: T | null;
+  /** Milliseconds since the Unix epoch. */
+  timestamp: number;
+  /** The label of the window that caused the mutation, if it came from the frontend. */
+  window: string | null;
+}
+
+/**
+ * When a store was last loaded and saved. Times are milliseconds since the Unix epoch.
+ */
+export interface PersistenceStatus {
+  /** When the store was last loaded from disk successfully. */
+  loadedAt: number | null;
+  /** When the store was last written to disk successfully. */
+  savedAt: number | null;
+  /** When the store was last attempted to be written to disk. */
+  saveAttemptedAt: number | null;
+  /** The error of the last attempt to write the store to disk, `null` if it succeeded. */
+  saveError: string | null;
+  /** How long the last attempt to write the store to disk took, in milliseconds. */
+  saveDurationMs: number | null;
+}
+
+/**
+ * An anonymized report about a single store, without any keys or values.
+ */
+export interface StoreDiagnostics extends PersistenceStatus {
+  /** The path of the store as given by the app. */
+  path: string;
+  /** The number of keys in the store. */
+  keys: number;
+  /** The size of the store on disk in bytes, `null` if it was never saved. */
+  fileSize: number | null;
+  format: "json" | "envelope" | "sharded" | "custom";
+  lazyLoad: boolean;
+  changeLog: boolean;
+  journal: boolean;
+  saveStrategy: string;
+  unsavedChanges: boolean;
+}
+
+/**
+ * An anonymized report about the stores of the app that users can attach to bug reports.
+ */
+export interface Diagnostics {
+  pluginVersion: string;
+  /** Milliseconds since the Unix epoch at which the report was created. */
+  generatedAt: number;
+  /** Whether the stores are read-only because another instance of the app owns them. */
+  readOnly: boolean;
+  stores: StoreDiagnostics[];
+}
+
+/**
+ * What `Store.repair` salvaged from a corrupt store file.
+ */
+export interface RepairReport {
+  /** Whether the file was corrupt. If not, it was left untouched. */
+  corrupt: boolean;
+  /** The keys that were recovered. */
+  recovered: string[];
+  /** The keys whose values couldn't be recovered. Nested keys are joined with `.`. */
+  lost: string[];
+  /** The number of bytes that couldn't be attributed to any key. */
+  skippedBytes: number;
+  /** Where the corrupt file was moved to. */
+  backup: string | null;
+}
+
+/**
+ * Creates an anonymized report about the loaded stores, e.g. their sizes, formats and the outcome of
+ * their last save, without any keys or values.
+ *
+ * @returns A JSON-serializable report to attach to bug reports.
+ */
+export async function diagnostics(): Promise<Diagnostics> {
+  return await invoke("plugin:store|diagnostics");
+}
+
+/**
+ * Returns a stable client id, a UUID generated and persisted on first use.
+ *
+ * Windows asking for the id at the same time get the same one.
+ *
+ * @param options.path The store the id is kept in, defaults to `client.json`.
+ * @param options.scope `user` for a random id per OS user, the default, or `machine` for an id derived from
+ * the OS machine id, which is the same for every user of the machine.
+ * @returns The client id.
+ */
+export async function clientId(
+  options: { path?: string; scope?: "user" | "machine" } = {},
+): Promise<string> {
+  return await invoke("plugin:store|client_id", {
+    path: options.path ?? null,
+    scope: options.scope ?? null,
+  });
+}
+
+/**
+ * A problem found by `verifyAll`.
+ */
+export interface StoreIssue {
+  path: string;
+  /** Why the store file couldn't be loaded, if it couldn't. */
+  error: string | null;
+  /** Where the broken store file was moved to. */
+  quarantined: string | null;
+  /** The keys whose values don't match the store's schema. */
+  invalidKeys: string[];
+}
+
+export interface VerifyReport {
+  schema: number;
+  /** The number of stores that were checked. */
+  checked: number;
+  issues: StoreIssue[];
+}
+
+/**
+ * Reads the file of every store registered on the backend and checks its values against the schema of the store,
+ * without changing the stores themselves.
+ * Store files that can't be read are moved next to the store file with a `.quarantined` extension.
+ *
+ * @returns The problems that were found.
+ */
+export async function verifyAll(): Promise<VerifyReport> {
+  return await invoke("plugin:store|verify_all");
+}
+
+/**
+ * Listen to saves failing because the disk is full, including saves made by the backend, e.g. when the app exits.
+ * The stores keep their unsaved changes, so the app can ask the user to free up space and save again.
+ *
+ * @param cb Called with the path of the store that couldn't be saved.
+ * @returns A promise resolving to a function to unlisten to the event.
+ */
+export async function onDiskFull(
+  cb: (path: string) => void,
+): Promise<UnlistenFn> {
+  return await listen<{ path: string }>("store://disk-full", (event) => {
+    cb(event.payload.path);
+  });
+}
+
+/**
+ * A failed background operation of a store built with `ErrorPolicy::PropagateToFrontend`.
+ */
+export interface StoreError {
+  schema: number;
+  path: string;
+  /** The operation that failed, e.g. `"save"` or `"load"`. */
+  operation: string;
+  error: string;
+}
+
+/**
+ * Listen to failures of background operations, e.g. autosaves, of stores built with `ErrorPolicy::PropagateToFrontend`.
+ *
+ * @param cb
+ * @returns A promise resolving to a function to unlisten to the event.
+ */
+export async function onStoreError(
+  cb: (error: StoreError) => void,
+): Promise<UnlistenFn> {
+  return await listen<StoreError>("store://error", (event) => {
+    cb(event.payload);
+  });
+}
+
+/**
+ * A lease of a store that is currently held, as returned by `Store.lockStatus`.
+ */
+export interface LockStatus {
+  name: string;
+  /** The label of the window holding the lease. */
+  owner: string;
+  /** Milliseconds until the lease expires unless it is renewed. */
+  expiresInMs: number;
+}
+
+interface LockPayload {
+  schema?: number;
+  path: string;
+  name: string;
+  owner?: string;
+  ttlMs?: number;
+}
+
+/**
+ * Whether two paths refer to the same store, following the backend in treating `/` and `\` as separators.
+ *
+ * Paths are compared case-insensitively because the frontend can't tell whether the backend does,
+ * which it does on Windows and macOS.
+ */
+function isSameStore(a: string, b: string): boolean {
+  const normalize = (path: string): string =>
+    path
+      .replace(/\\/g, "/")
+      .split("/")
+      .filter((part, i) => (part !== "" || i === 0) && part !== ".")
+      .join("/")
+      .toLowerCase();
+  return normalize(a) === normalize(b);
 }
 
 /**
@@ -16,8 +385,33 @@ interface ChangePayload<T> {
  */
 export class Store {
   path: string;
-  constructor(path: string) {
+  changeEvent: string;
+
+  /**
+   * @param path
+   * @param options.changeEvent The change event name configured for this store on the backend, defaults to `store://change`.
+   */
+  constructor(path: string, options: { changeEvent?: string } = {}) {
     this.path = path;
+    this.changeEvent = options.changeEvent ?? "store://change";
+  }
+
+  /**
+   * Opens the store at `path` on behalf of the current webview.
+   *
+   * If the backend was built with `Builder::unload_unreferenced`, the store is unloaded once every webview
+   * that opened it was destroyed, e.g. when the app opens a window per document.
+   *
+   * @param path
+   * @param options.changeEvent The change event name configured for this store on the backend, defaults to `store://change`.
+   * @returns
+   */
+  static async open(
+    path: string,
+    options: { changeEvent?: string } = {},
+  ): Promise<Store> {
+    await invoke("plugin:store|open", { path });
+    return new Store(path, options);
   }
 
   /**
@@ -25,19 +419,108 @@ export class Store {
    *
    * @param key
    * @param value
+   * @param options
    * @returns
    */
-  async set(key: string, value: unknown): Promise<void> {
+  async set(
+    key: string,
+    value: unknown,
+    options: WriteOptions = {},
+  ): Promise<void> {
     return await invoke("plugin:store|set", {
       path: this.path,
       key,
       value,
+      txnId: options.txnId ?? null,
+    });
+  }
+
+  /**
+   * Inserts a key-value pair into the store that expires after `ttlMs` milliseconds.
+   *
+   * Expired keys are removed by the backend's background sweeper, if one is configured.
+   *
+   * @param key
+   * @param value
+   * @param ttlMs
+   * @param options
+   * @returns
+   */
+  async setWithTtl(
+    key: string,
+    value: unknown,
+    ttlMs: number,
+    options: WriteOptions = {},
+  ): Promise<void> {
+    return await invoke("plugin:store|set_with_ttl", {
+      path: this.path,
+      key,
+      value,
+      ttlMs,
+      txnId: options.txnId ?? null,
+    });
+  }
+
+  /**
+   * Inserts `value` only if `key` currently holds `expected`.
+   *
+   * A missing key compares equal to `null`.
+   *
+   * @param key
+   * @param expected
+   * @param value
+   * @param options
+   * @returns Whether the value was inserted.
+   */
+  async compareAndSet(
+    key: string,
+    expected: unknown,
+    value: unknown,
+    options: WriteOptions = {},
+  ): Promise<boolean> {
+    return await invoke("plugin:store|compare_and_set", {
+      path: this.path,
+      key,
+      expected,
+      value,
+      txnId: options.txnId ?? null,
     });
   }
 
+  /**
+   * Replaces the value of `key` with the result of `updater`, which receives the current value.
+   *
+   * If another write to `key` happens in between, `updater` is called again with the new value,
+   * so concurrent updates from several windows are never lost:
+   *
+   * ```typescript
+   * await store.update<number>("launches", (launches) => (launches ?? 0) + 1);
+   * ```
+   *
+   * @param key
+   * @param updater Must not have side effects, as it may be called multiple times.
+   * @param options
+   * @returns The new value.
+   */
+  async update<T>(
+    key: string,
+    updater: (value: T | null) => T,
+    options: WriteOptions = {},
+  ): Promise<T> {
+    for (;;) {
+      const current = await this.get<T>(key);
+      const value = updater(current);
+      if (await this.compareAndSet(key, current, value, options)) {
+        return value;
+      }
+    }
+  }
+
   /**
    * Returns the value for the given `key` or `null` the key does not exist.
    *
+   * If the store has a schema registered on the backend, values of the wrong type are rejected.
+   *
    * @param key
    * @returns
    */
@@ -48,6 +531,68 @@ export class Store {
     });
   }
 
+  /**
+   * Stores `date` under `key` as an RFC 3339 string in UTC with millisecond precision,
+   * the same encoding the backend uses for `Store::set_datetime`.
+   *
+   * @param key
+   * @param date
+   * @param options
+   * @returns
+   */
+  async setDatetime(
+    key: string,
+    date: Date,
+    options: WriteOptions = {},
+  ): Promise<void> {
+    return await this.set(key, date.toISOString(), options);
+  }
+
+  /**
+   * Returns the timestamp stored under `key` or `null` if the key does not exist.
+   *
+   * @param key
+   * @returns
+   */
+  async getDatetime(key: string): Promise<Date | null> {
+    const value = await this.get<string>(key);
+    if (value === null) {
+      return null;
+    }
+    const date = new Date(value);
+    if (typeof value !== "string" || isNaN(date.getTime())) {
+      throw new Error(`Invalid datetime ${JSON.stringify(value)}`);
+    }
+    return date;
+  }
+
+  /**
+   * Waits until `key` exists in the store, or holds `options.value` if given, and returns its value.
+   *
+   * Useful for startup handshakes with keys written by the backend, without polling:
+   *
+   * ```typescript
+   * await store.waitFor("backend-ready", { value: true, timeoutMs: 5000 });
+   * ```
+   *
+   * @param key
+   * @param options.value The value to wait for. Waiting for `null` is the same as waiting for the key to exist.
+   * @param options.timeoutMs Rejects if the key doesn't reach the awaited state within this many milliseconds.
+   * Defaults to 30 seconds.
+   * @returns
+   */
+  async waitFor<T>(
+    key: string,
+    options: { value?: T; timeoutMs?: number } = {},
+  ): Promise<T> {
+    return await invoke("plugin:store|wait_for", {
+      path: this.path,
+      key,
+      value: options.value ?? null,
+      timeoutMs: options.timeoutMs ?? null,
+    });
+  }
+
   /**
    * Returns `true` if the given `key` exists in the store.
    *
@@ -62,142 +607,1266 @@ export class Store {
   }
 
   /**
-   * Removes a key-value pair from the store.
+   * Returns the JSON type of the value for the given `key`, or `missing` if the key does not exist.
    *
    * @param key
    * @returns
    */
-  async delete(key: string): Promise<boolean> {
-    return await invoke("plugin:store|delete", {
+  async typeOf(key: string): Promise<ValueType> {
+    return await invoke("plugin:store|type_of", {
       path: this.path,
       key,
     });
   }
 
   /**
-   * Clears the store, removing all key-value pairs.
+   * Returns the context allowed to modify the store if the backend built it with `StoreBuilder::single_writer`,
+   * otherwise `null`.
    *
-   * Note: To clear the storage and reset it to it's `default` value, use `reset` instead.
    * @returns
    */
-  async clear(): Promise<void> {
-    return await invoke("plugin:store|clear", {
+  async writer(): Promise<Writer | null> {
+    return await invoke("plugin:store|writer", {
       path: this.path,
     });
   }
 
   /**
-   * Resets the store to it's `default` value.
+   * Hands the writer token of a single-writer store to another window, or to the backend with `null`.
+   * Only the window holding the token may do so.
    *
-   * If no default value has been set, this method behaves identical to `clear`.
+   * @param to The label of the window to hand the token to.
    * @returns
    */
-  async reset(): Promise<void> {
-    return await invoke("plugin:store|reset", {
+  async transferWriter(to: string | null): Promise<void> {
+    return await invoke("plugin:store|transfer_writer", {
       path: this.path,
+      to,
     });
   }
 
   /**
-   * Returns a list of all key in the store.
+   * Removes a key-value pair from the store.
    *
+   * @param key
+   * @param options
    * @returns
    */
-  async keys(): Promise<string[]> {
-    return await invoke("plugin:store|keys", {
+  async delete(key: string, options: WriteOptions = {}): Promise<boolean> {
+    return await invoke("plugin:store|delete", {
       path: this.path,
+      key,
+      txnId: options.txnId ?? null,
     });
   }
 
   /**
-   * Returns a list of all values in the store.
+   * Removes the keys whose values are `null`, empty objects or empty arrays.
    *
-   * @returns
+   * @param options
+   * @returns The removed keys.
    */
-  async values<T>(): Promise<T[]> {
-    return await invoke("plugin:store|values", {
+  async prune(options: WriteOptions = {}): Promise<string[]> {
+    return await invoke("plugin:store|prune", {
       path: this.path,
+      txnId: options.txnId ?? null,
     });
   }
 
   /**
-   * Returns a list of all entries in the store.
+   * Clears the store, removing all key-value pairs.
    *
+   * Note: To clear the storage and reset it to it's `default` value, use `reset` instead.
+   * @param options.scope Only remove the keys starting with this prefix.
    * @returns
    */
-  async entries<T>(): Promise<Array<[key: string, value: T]>> {
-    return await invoke("plugin:store|entries", {
+  async clear(options: WriteOptions & ScopeOptions = {}): Promise<void> {
+    return await invoke("plugin:store|clear", {
       path: this.path,
+      scope: options.scope ?? null,
+      txnId: options.txnId ?? null,
     });
   }
 
   /**
-   * Returns the number of key-value pairs in the store.
+   * Resets the store to it's `default` value.
    *
+   * If no default value has been set, this method behaves identical to `clear`.
+   * Listeners registered with `onReset` receive a single JSON Patch describing all changes.
+   * @param options.silent Don't emit any change events.
    * @returns
    */
-  async length(): Promise<number> {
-    return await invoke("plugin:store|length", {
+  async reset(options: { silent?: boolean } = {}): Promise<void> {
+    return await invoke("plugin:store|reset", {
       path: this.path,
+      silent: options.silent ?? false,
     });
   }
 
   /**
-   * Attempts to load the on-disk state at the stores `path` into memory.
+   * Registers default values, inserting those whose key is missing from the store.
    *
-   * This method is useful if the on-disk state was edited by the user and you want to synchronize the changes.
+   * The defaults are restored by `reset` and `resetKey` like defaults declared on the backend.
    *
-   * Note: This method does not emit change events.
+   * @param defaults
    * @returns
    */
-  async load(): Promise<void> {
-    return await invoke("plugin:store|load", {
+  async addDefaults(defaults: Record<string, unknown>): Promise<void> {
+    return await invoke("plugin:store|add_defaults", {
       path: this.path,
+      defaults,
     });
   }
 
   /**
-   * Saves the store to disk at the stores `path`.
+   * Restores the default value of `key`, or removes it if it has none.
    *
-   * As the store is only persisted to disk before the apps exit, changes might be lost in a crash.
-   * This method lets you persist the store to disk whenever you deem necessary.
+   * @param key
+   * @param options
    * @returns
    */
-  async save(): Promise<void> {
-    return await invoke("plugin:store|save", {
+  async resetKey(key: string, options: WriteOptions = {}): Promise<void> {
+    return await invoke("plugin:store|reset_key", {
       path: this.path,
+      key,
+      txnId: options.txnId ?? null,
     });
   }
 
   /**
-   * Listen to changes on a store key.
-   * @param key
-   * @param cb
-   * @returns A promise resolving to a function to unlisten to the event.
+   * Returns the keys with a default value whose current value differs from it, sorted.
+   *
+   * @returns
    */
-  async onKeyChange<T>(
-    key: string,
-    cb: (value: T | null) => void,
-  ): Promise<UnlistenFn> {
-    return await listen<ChangePayload<T>>("store://change", (event) => {
-      if (event.payload.path === this.path && event.payload.key === key) {
-        cb(event.payload.value);
-      }
+  async overriddenKeys(): Promise<string[]> {
+    return await invoke("plugin:store|overridden_keys", {
+      path: this.path,
+    });
+  }
+
+  /**
+   * Returns a list of all key in the store.
+   *
+   * @returns
+   */
+  async keys(options: ScopeOptions = {}): Promise<string[]> {
+    return await invoke("plugin:store|keys", {
+      path: this.path,
+      scope: options.scope ?? null,
+    });
+  }
+
+  /**
+   * Returns a list of all keys in the store, sorted by the backend.
+   *
+   * @param order
+   * @returns
+   */
+  async keysSorted(order: KeyOrder = "lexicographic"): Promise<string[]> {
+    return await invoke("plugin:store|keys_sorted", {
+      path: this.path,
+      order,
+    });
+  }
+
+  /**
+   * Returns a list of all values in the store.
+   *
+   * @returns
+   */
+  async values<T>(options: ScopeOptions = {}): Promise<T[]> {
+    return await invoke("plugin:store|values", {
+      path: this.path,
+      scope: options.scope ?? null,
+    });
+  }
+
+  /**
+   * Returns a list of all entries in the store.
+   *
+   * @returns
+   */
+  async entries<T>(
+    options: ScopeOptions = {},
+  ): Promise<Array<[key: string, value: T]>> {
+    return await invoke("plugin:store|entries", {
+      path: this.path,
+      scope: options.scope ?? null,
+    });
+  }
+
+  /**
+   * Returns the number of key-value pairs in the store.
+   *
+   * @returns
+   */
+  async length(options: ScopeOptions = {}): Promise<number> {
+    return await invoke("plugin:store|length", {
+      path: this.path,
+      scope: options.scope ?? null,
+    });
+  }
+
+  /**
+   * Returns a view of the keys starting with `prefix`, which is added to and stripped from keys automatically.
+   *
+   * ```typescript
+   * const editor = store.scoped("editor.");
+   * await editor.set("fontSize", 14); // stored as `editor.fontSize`
+   * ```
+   *
+   * @param prefix
+   * @returns
+   */
+  scoped(prefix: string): ScopedStore {
+    return new ScopedStore(this, prefix);
+  }
+
+  /**
+   * Returns the entries whose indexed field equals `value`, using an index declared on the backend
+   * with `StoreBuilder::index`. Entries are sorted by key.
+   *
+   * @param index The name of the index.
+   * @param value
+   * @returns
+   */
+  async findBy<T>(
+    index: string,
+    value: unknown,
+  ): Promise<Array<[key: string, value: T]>> {
+    return await invoke("plugin:store|find_by", {
+      path: this.path,
+      index,
+      value,
+    });
+  }
+
+  /**
+   * Returns the entries whose field at the JSON pointer `field` (e.g. `/updatedAt`) is a number
+   * within `min` and `max` inclusive, ordered by that number. The range is evaluated on the backend.
+   *
+   * @param field A JSON pointer into the values, or `""` for stores holding plain numbers.
+   * @param min The lower bound, unbounded if omitted.
+   * @param max The upper bound, unbounded if omitted.
+   * @returns
+   */
+  async findRange<T>(
+    field: string,
+    min?: number,
+    max?: number,
+  ): Promise<Array<[key: string, value: T]>> {
+    return await invoke("plugin:store|find_range", {
+      path: this.path,
+      field,
+      min,
+      max,
+    });
+  }
+
+  /**
+   * Computes `op` over the numbers at the JSON pointer `field` of the values of the keys selected by `filter`.
+   * Values where the field is missing or not a number are skipped.
+   *
+   * ```typescript
+   * const total = await store.aggregate("/score", "sum", { include: ["games.*"] });
+   * ```
+   *
+   * @param field A JSON pointer into the values, or `""` for stores holding plain numbers.
+   * @param op
+   * @param filter
+   * @returns The result, or `null` for `min` and `max` if no number was found.
+   */
+  async aggregate(
+    field: string,
+    op: Aggregate,
+    filter: KeyFilter = {},
+  ): Promise<number | null> {
+    return await invoke("plugin:store|aggregate", {
+      path: this.path,
+      field,
+      op,
+      filter,
+    });
+  }
+
+  /**
+   * Returns up to `limit` entries whose string values contain every word of `query`, best matches first.
+   * Requires the `search` feature and a store built with `StoreBuilder::full_text_search`.
+   *
+   * @param query
+   * @param limit
+   * @returns
+   */
+  async search<T>(
+    query: string,
+    limit?: number,
+  ): Promise<Array<[key: string, value: T]>> {
+    return await invoke("plugin:store|search", {
+      path: this.path,
+      query,
+      limit,
+    });
+  }
+
+  /**
+   * Walks the entries of the store in lexicographic order of their keys, fetching `pageSize` entries at a time.
+   *
+   * The backend only remembers the last key returned, so even enormous stores are never copied as a whole:
+   *
+   * ```typescript
+   * for await (const [key, value] of store.iterate()) {
+   *   console.log(key, value);
+   * }
+   * ```
+   *
+   * @param pageSize
+   */
+  async *iterate<T>(
+    pageSize = 100,
+  ): AsyncGenerator<[key: string, value: T], void, undefined> {
+    const cursor = await invoke<number>("plugin:store|cursor_open", {
+      path: this.path,
+    });
+    try {
+      for (;;) {
+        const page = await invoke<Array<[key: string, value: T]>>(
+          "plugin:store|cursor_next",
+          { cursor, limit: pageSize },
+        );
+        if (page.length === 0) {
+          return;
+        }
+        yield* page;
+      }
+    } finally {
+      await invoke("plugin:store|cursor_close", { cursor });
+    }
+  }
+
+  /**
+   * Returns a hash of the store's contents.
+   *
+   * The hash only changes when the contents do, so it can be used to cheaply check whether a cached copy is stale.
+   * @returns
+   */
+  async checksum(): Promise<string> {
+    return await invoke("plugin:store|checksum", {
+      path: this.path,
+    });
+  }
+
+  /**
+   * Compares this store with the store at `other`.
+   *
+   * @param other The path of the store to compare against.
+   * @returns The keys that were added, removed or changed in `other`.
+   */
+  async diff<T>(other: string): Promise<StoreDiff<T>> {
+    return await invoke("plugin:store|diff", {
+      pathA: this.path,
+      pathB: other,
+    });
+  }
+
+  /**
+   * Imports a set of entries into the store.
+   *
+   * This is intended for migrating existing users' `localStorage` data into a store on first run:
+   *
+   * ```typescript
+   * const entries = Object.fromEntries(
+   *   Object.entries(localStorage).map(([key, value]) => {
+   *     try {
+   *       return [key, JSON.parse(value)];
+   *     } catch {
+   *       return [key, value];
+   *     }
+   *   }),
+   * );
+   * await store.importMap(entries, { marker: "migratedFromLocalStorage" });
+   * ```
+   *
+   * @param entries
+   * @param options
+   * @returns `true` if the import ran, `false` if it was skipped because the marker already existed.
+   */
+  async importMap(
+    entries: Record<string, unknown>,
+    options: ImportOptions = {},
+  ): Promise<boolean> {
+    return await invoke("plugin:store|import_map", {
+      path: this.path,
+      entries,
+      strategy: options.strategy ?? "skip",
+      marker: options.marker ?? null,
+      silent: options.silent ?? false,
+    });
+  }
+
+  /**
+   * Parses and schema-checks an import payload in the store's on-disk format without applying it.
+   *
+   * @param bytes The contents of the file to import.
+   * @param strategy How keys that already exist would be handled, defaults to `skip`.
+   * @returns The changes the import would make, e.g. to show a confirmation diff.
+   */
+  async validateImport<T>(
+    bytes: Uint8Array | number[],
+    strategy: ConflictStrategy = "skip",
+  ): Promise<StoreDiff<T>> {
+    return await invoke("plugin:store|validate_import", {
+      path: this.path,
+      bytes: Array.from(bytes),
+      strategy,
+    });
+  }
+
+  /**
+   * Applies a preset registered on the backend, e.g. a theme or the recommended settings.
+   *
+   * All keys of the preset are written before any change event is emitted.
+   *
+   * @param name
+   * @returns
+   */
+  async applyPreset(name: string): Promise<void> {
+    return await invoke("plugin:store|apply_preset", {
+      path: this.path,
+      name,
+    });
+  }
+
+  /**
+   * Evaluates the feature flag stored under `name`.
+   *
+   * A flag is either a boolean or an object with the optional rules `enabled`, `rollout` (percentage of clients),
+   * `platforms` (e.g. `["macos", "windows"]`), `minVersion` (inclusive) and `maxVersion` (exclusive).
+   * Missing flags are disabled. Rollouts are bucketed by the app's client id, see {@link clientId}.
+   *
+   * @param name
+   * @returns
+   */
+  async flag(name: string): Promise<boolean> {
+    return await invoke("plugin:store|flag", {
+      path: this.path,
+      name,
+    });
+  }
+
+  /**
+   * Appends `value` to the durable FIFO queue stored under the key `queue`.
+   *
+   * @param queue
+   * @param value
+   * @returns The id of the new item.
+   */
+  async queuePush(queue: string, value: unknown): Promise<number> {
+    return await invoke("plugin:store|queue_push", {
+      path: this.path,
+      queue,
+      value,
+    });
+  }
+
+  /**
+   * Takes up to `max` items from the front of the queue.
+   *
+   * The items stay in the queue's in-flight set until they are acknowledged with `queueAck`,
+   * and can be delivered again with `queueRequeue`, e.g. after a restart.
+   *
+   * @param queue
+   * @param max
+   * @returns
+   */
+  async queuePopBatch<T>(
+    queue: string,
+    max: number,
+  ): Promise<Array<QueueItem<T>>> {
+    return await invoke("plugin:store|queue_pop_batch", {
+      path: this.path,
+      queue,
+      max,
+    });
+  }
+
+  /**
+   * Removes processed items from the queue's in-flight set.
+   *
+   * @param queue
+   * @param ids
+   * @returns The number of removed items.
+   */
+  async queueAck(queue: string, ids: number[]): Promise<number> {
+    return await invoke("plugin:store|queue_ack", {
+      path: this.path,
+      queue,
+      ids,
+    });
+  }
+
+  /**
+   * Moves all unacknowledged in-flight items back to the front of the queue.
+   *
+   * @param queue
+   * @returns The number of moved items.
+   */
+  async queueRequeue(queue: string): Promise<number> {
+    return await invoke("plugin:store|queue_requeue", {
+      path: this.path,
+      queue,
+    });
+  }
+
+  /**
+   * Returns the number of items waiting in the queue, not counting in-flight ones.
+   *
+   * @param queue
+   * @returns
+   */
+  async queueLen(queue: string): Promise<number> {
+    return await invoke("plugin:store|queue_len", {
+      path: this.path,
+      queue,
+    });
+  }
+
+  /**
+   * Opens an edit session whose writes are staged until they are committed at once or discarded.
+   *
+   * @returns The edit session.
+   */
+  async beginEdit(): Promise<EditSession> {
+    const id = await invoke<number>("plugin:store|begin_edit", {
+      path: this.path,
+    });
+    return new EditSession(id);
+  }
+
+  /**
+   * Caches `value` under `key`, e.g. an API response, for stale-while-revalidate caching with `cacheGet`.
+   *
+   * @param key
+   * @param value
+   * @param options.freshForMs How long the value is fresh.
+   * @param options.maxAgeMs Removes the entry once this has passed. Stale values are kept until replaced otherwise.
+   */
+  async cachePut(
+    key: string,
+    value: unknown,
+    options: { freshForMs: number; maxAgeMs?: number },
+  ): Promise<void> {
+    await invoke("plugin:store|cache_put", {
+      path: this.path,
+      key,
+      value,
+      freshForMs: options.freshForMs,
+      maxAgeMs: options.maxAgeMs ?? null,
+    });
+  }
+
+  /**
+   * Returns the value cached under `key` with `cachePut` and whether it is stale.
+   * A stale value can be used right away while it is refreshed in the background.
+   *
+   * @param key
+   * @returns The cached value, or `null` if there is none.
+   */
+  async cacheGet<T>(
+    key: string,
+  ): Promise<{ value: T; stale: boolean } | null> {
+    return await invoke("plugin:store|cache_get", {
+      path: this.path,
+      key,
+    });
+  }
+
+  /**
+   * Adds `n` to the counter stored under the key `name`, e.g. to count launches or feature usage.
+   *
+   * The backend keeps increments in memory and writes them to the store periodically and when the app exits.
+   *
+   * @param name
+   * @param n
+   */
+  async counterAdd(name: string, n = 1): Promise<void> {
+    await invoke("plugin:store|counter_add", { path: this.path, name, n });
+  }
+
+  /**
+   * Returns the value of the counter stored under the key `name`, including increments that weren't written yet.
+   *
+   * @param name
+   * @returns The counter's value.
+   */
+  async counterGet(name: string): Promise<number> {
+    return await invoke("plugin:store|counter_get", { path: this.path, name });
+  }
+
+  /**
+   * Returns `true` exactly once per store: the first time this is called, e.g. to show an onboarding flow.
+   *
+   * The backend saves the store before answering, so a crash never repeats the first run.
+   *
+   * @returns Whether this is the first run.
+   */
+  async firstRun(): Promise<boolean> {
+    return await invoke("plugin:store|first_run", { path: this.path });
+  }
+
+  /**
+   * Marks an onboarding step as done. The backend saves the store before answering.
+   *
+   * @param step
+   * @returns `false` if the step already was done.
+   */
+  async markStepDone(step: string): Promise<boolean> {
+    return await invoke("plugin:store|mark_step_done", {
+      path: this.path,
+      step,
+    });
+  }
+
+  /**
+   * Returns whether an onboarding step was marked as done.
+   *
+   * @param step
+   * @returns Whether the step is done.
+   */
+  async isStepDone(step: string): Promise<boolean> {
+    return await invoke("plugin:store|is_step_done", {
+      path: this.path,
+      step,
+    });
+  }
+
+  /**
+   * Forgets the first run and all completed onboarding steps, e.g. to replay the onboarding.
+   */
+  async resetOnboarding(): Promise<void> {
+    await invoke("plugin:store|reset_onboarding", { path: this.path });
+  }
+
+  /**
+   * Returns the shortcuts of the keymap stored under the key `keymap`, by action.
+   *
+   * @param keymap
+   * @returns The normalized shortcuts, e.g. `Ctrl+Shift+P`.
+   */
+  async keymap(keymap: string): Promise<Record<string, string>> {
+    return await invoke("plugin:store|keymap", {
+      path: this.path,
+      keymap,
+    });
+  }
+
+  /**
+   * Binds `action` to `shortcut` in the keymap stored under the key `keymap`.
+   *
+   * Modifier aliases are unified and `CmdOrCtrl` is resolved for the current platform, so the same shortcut
+   * always has the same spelling. Fails if another action is bound to the same shortcut.
+   *
+   * @param keymap
+   * @param action
+   * @param shortcut A shortcut like `CmdOrCtrl+Shift+P`.
+   * @returns The normalized shortcut.
+   */
+  async keymapSet(
+    keymap: string,
+    action: string,
+    shortcut: string,
+  ): Promise<string> {
+    return await invoke("plugin:store|keymap_set", {
+      path: this.path,
+      keymap,
+      action,
+      shortcut,
+    });
+  }
+
+  /**
+   * Unbinds `action` in the keymap stored under the key `keymap`.
+   *
+   * @param keymap
+   * @param action
+   * @returns Whether the action was bound.
+   */
+  async keymapRemove(keymap: string, action: string): Promise<boolean> {
+    return await invoke("plugin:store|keymap_remove", {
+      path: this.path,
+      keymap,
+      action,
+    });
+  }
+
+  /**
+   * Acquires the named lease for this window, so multiple windows can coordinate exclusive access
+   * to a section of the store. Acquiring a lease this window already holds renews it.
+   *
+   * Leases are only kept in memory and don't prevent writes by other windows.
+   *
+   * @param name
+   * @param ttlMs The lease expires after this many milliseconds unless renewed.
+   * @returns `false` if another window holds the lease.
+   */
+  async acquireLock(name: string, ttlMs: number): Promise<boolean> {
+    return await invoke("plugin:store|acquire_lock", {
+      path: this.path,
+      name,
+      ttlMs,
+    });
+  }
+
+  /**
+   * Releases the named lease if this window holds it.
+   *
+   * @param name
+   * @returns Whether the lease was released.
+   */
+  async releaseLock(name: string): Promise<boolean> {
+    return await invoke("plugin:store|release_lock", {
+      path: this.path,
+      name,
+    });
+  }
+
+  /**
+   * Returns the leases of the store that are currently held, sorted by name.
+   *
+   * @returns The held leases.
+   */
+  async lockStatus(): Promise<LockStatus[]> {
+    return await invoke("plugin:store|lock_status", {
+      path: this.path,
+    });
+  }
+
+  /**
+   * Listen to leases of the store being acquired or released, e.g. to disable a settings panel
+   * while another window holds its lease.
+   *
+   * Leases that expire without being released aren't reported; use `ttlMs` to tell when they lapse.
+   * @param cb Called with the lease's name and, when it was acquired, its owner and TTL.
+   * @returns A promise resolving to a function to unlisten to the events.
+   */
+  async onLockChange(
+    cb: (name: string, lock: { owner: string; ttlMs: number } | null) => void,
+  ): Promise<UnlistenFn> {
+    const unlistenLocked = await listen<LockPayload>(
+      "store://locked",
+      (event) => {
+        if (isSameStore(event.payload.path, this.path)) {
+          cb(event.payload.name, {
+            owner: event.payload.owner ?? "",
+            ttlMs: event.payload.ttlMs ?? 0,
+          });
+        }
+      },
+    );
+    const unlistenUnlocked = await listen<LockPayload>(
+      "store://unlocked",
+      (event) => {
+        if (isSameStore(event.payload.path, this.path)) {
+          cb(event.payload.name, null);
+        }
+      },
+    );
+    return () => {
+      unlistenLocked();
+      unlistenUnlocked();
+    };
+  }
+
+  /**
+   * Returns the recorded mutations of the store, oldest first.
+   *
+   * Empty unless history was enabled for the store on the backend.
+   *
+   * @param key Only return the mutations of this key.
+   * @returns
+   */
+  async history<T>(key?: string): Promise<Array<HistoryEntry<T>>> {
+    return await invoke("plugin:store|history", {
+      path: this.path,
+      key: key ?? null,
+    });
+  }
+
+  /**
+   * Returns when the store was last loaded and saved, and whether the last save succeeded.
+   *
+   * @returns
+   */
+  async persistenceStatus(): Promise<PersistenceStatus> {
+    return await invoke("plugin:store|persistence_status", {
+      path: this.path,
+    });
+  }
+
+  /**
+   * Returns the key-value pairs selected by `filter`.
+   *
+   * This lets users share their settings without leaking e.g. credentials stored in the same file:
+   *
+   * ```typescript
+   * const shareable = await store.export({ exclude: ["auth.*"] });
+   * ```
+   *
+   * @param filter
+   * @returns
+   */
+  async export<T>(filter: KeyFilter = {}): Promise<Record<string, T>> {
+    return await invoke("plugin:store|export", {
+      path: this.path,
+      filter,
+    });
+  }
+
+  /**
+   * Returns the contents of the store as pretty-printed JSON with the values of sensitive keys masked,
+   * e.g. for users to paste their settings into a support ticket.
+   *
+   * Keys are sensitive if the backend marked them with `StoreBuilder::sensitive_keys` or encrypts them.
+   *
+   * @returns The redacted JSON.
+   */
+  async exportRedacted(): Promise<string> {
+    return await invoke("plugin:store|export_redacted", { path: this.path });
+  }
+
+  /**
+   * Attempts to load the on-disk state at the stores `path` into memory.
+   *
+   * This method is useful if the on-disk state was edited by the user and you want to synchronize the changes.
+   *
+   * Note: This method does not emit change events, except for keys inserted from `defaults`.
+   * @param defaults Values inserted for keys missing from the store, also restored by `reset`.
+   * They are applied even if the store file doesn't exist yet.
+   * @returns
+   */
+  async load(defaults?: Record<string, unknown>): Promise<void> {
+    return await invoke("plugin:store|load", {
+      path: this.path,
+      defaults: defaults ?? null,
+    });
+  }
+
+  /**
+   * Like `load`, but only parses the values of `keys` right away.
+   *
+   * The other values are parsed when they are first accessed, so apps with one large store can boot fast
+   * by loading just what the first screen needs. Stores saved in a format other than JSON are loaded completely.
+   *
+   * @param keys
+   * @returns
+   */
+  async loadKeys(keys: string[]): Promise<void> {
+    return await invoke("plugin:store|load_keys", {
+      path: this.path,
+      keys,
+    });
+  }
+
+  /**
+   * Attempts to recover the contents of a corrupt store file.
+   *
+   * Values that aren't valid JSON are skipped and a truncated file is read up to the last complete value.
+   * The recovered values are merged into the store, the corrupt file is kept next to the store file
+   * with a `.corrupt` extension and the store is saved.
+   *
+   * @returns What was recovered and what was lost.
+   */
+  async repair(): Promise<RepairReport> {
+    return await invoke("plugin:store|repair", {
+      path: this.path,
+    });
+  }
+
+  /**
+   * Rewrites the store file and empties the change log or journal of the store, if it has one.
+   *
+   * @returns
+   */
+  async compact(): Promise<void> {
+    return await invoke("plugin:store|compact", {
+      path: this.path,
+    });
+  }
+
+  /**
+   * Saves the store to disk at the stores `path`.
+   *
+   * As the store is only persisted to disk before the apps exit, changes might be lost in a crash.
+   * This method lets you persist the store to disk whenever you deem necessary.
+   *
+   * The store is written in the background and this returns once the save is queued.
+   * It still rejects right away if the store is read-only or the save is cancelled by the app,
+   * failures of the write itself are reported through `onStoreError`.
+   * @param options.wait Wait until the store is written and reject if saving fails.
+   * @returns
+   */
+  async save(options: { wait?: boolean } = {}): Promise<void> {
+    return await invoke("plugin:store|save", {
+      path: this.path,
+      wait: options.wait ?? null,
+    });
+  }
+
+  /**
+   * Listen to changes on a store key.
+   * @param key
+   * @param cb
+   * @returns A promise resolving to a function to unlisten to the event.
+   */
+  async onKeyChange<T>(
+    key: string,
+    cb: (value: T | null, context: ChangeContext) => void,
+  ): Promise<UnlistenFn> {
+    return await this.onChange<T>((changedKey, value, context) => {
+      if (changedKey === key) {
+        cb(value, context);
+      }
     });
   }
 
   /**
    * Listen to changes on the store.
+   *
+   * A `reset` or a committed edit session is reported as one change per affected key, without a context.
+   * @param cb
+   * @returns A promise resolving to a function to unlisten to the event.
+   */
+  async onChange<T>(
+    cb: (key: string, value: T | null, context: ChangeContext) => void,
+  ): Promise<UnlistenFn> {
+    const unlistenChange = await listen<ChangePayload<T>>(
+      this.changeEvent,
+      (event) => {
+        if (isSameStore(event.payload.path, this.path)) {
+          cb(event.payload.key, event.payload.value, {
+            window: event.payload.window ?? null,
+            txnId: event.payload.txnId ?? null,
+          });
+        }
+      },
+    );
+    const onPatch = (patch: Array<PatchOperation<T>>): void => {
+      for (const operation of patch) {
+        cb(
+          pointerToKey(operation.path),
+          operation.op === "remove" ? null : operation.value,
+          { window: null, txnId: null },
+        );
+      }
+    };
+    const unlistenReset = await this.onReset<T>(onPatch);
+    const unlistenBatch = await this.onBatch<T>(onPatch);
+    return () => {
+      unlistenChange();
+      unlistenReset();
+      unlistenBatch();
+    };
+  }
+
+  /**
+   * Listen to edit sessions of the store being committed.
+   * @param cb Called with a JSON Patch describing every change made by the edit session.
+   * @returns A promise resolving to a function to unlisten to the event.
+   */
+  async onBatch<T>(
+    cb: (patch: Array<PatchOperation<T>>) => void,
+  ): Promise<UnlistenFn> {
+    return await listen<ResetPayload<T>>("store://batch", (event) => {
+      if (isSameStore(event.payload.path, this.path)) {
+        cb(event.payload.patch);
+      }
+    });
+  }
+
+  /**
+   * Listen to resets of the store.
+   * @param cb Called with a JSON Patch describing every change made by the reset.
+   * @returns A promise resolving to a function to unlisten to the event.
+   */
+  async onReset<T>(
+    cb: (patch: Array<PatchOperation<T>>) => void,
+  ): Promise<UnlistenFn> {
+    return await listen<ResetPayload<T>>("store://reset", (event) => {
+      if (isSameStore(event.payload.path, this.path)) {
+        cb(event.payload.patch);
+      }
+    });
+  }
+}
+
+/**
+ * A draft layer over a `Store`, created with `Store.beginEdit`, whose writes are staged until they are
+ * committed at once or discarded, e.g. by a settings dialog with Apply and Cancel buttons.
+ */
+export class EditSession {
+  id: number;
+
+  constructor(id: number) {
+    this.id = id;
+  }
+
+  /**
+   * Stages `value` under `key`.
+   *
+   * @param key
+   * @param value
+   */
+  async set(key: string, value: unknown): Promise<void> {
+    await invoke("plugin:store|edit_set", { edit: this.id, key, value });
+  }
+
+  /**
+   * Stages the deletion of `key`.
+   *
+   * @param key
+   */
+  async delete(key: string): Promise<void> {
+    await invoke("plugin:store|edit_set", {
+      edit: this.id,
+      key,
+      value: null,
+    });
+  }
+
+  /**
+   * Returns the staged value of `key`, or the one in the store if none was staged.
+   *
+   * @param key
+   * @returns The value, or `null` if the key doesn't exist.
+   */
+  async get<T>(key: string): Promise<T | null> {
+    return await invoke("plugin:store|edit_get", { edit: this.id, key });
+  }
+
+  /**
+   * Applies the staged changes to the store at once, emitting a single batch event, and closes the session.
+   */
+  async commit(): Promise<void> {
+    await invoke("plugin:store|commit_edit", { edit: this.id });
+  }
+
+  /**
+   * Drops the staged changes and closes the session.
+   *
+   * @returns Whether the session was still open.
+   */
+  async discard(): Promise<boolean> {
+    return await invoke("plugin:store|discard_edit", { edit: this.id });
+  }
+}
+
+/**
+ * A view of the keys of a `Store` that start with a prefix, created with `Store.scoped`.
+ */
+export class ScopedStore {
+  store: Store;
+  prefix: string;
+
+  constructor(store: Store, prefix: string) {
+    this.store = store;
+    this.prefix = prefix;
+  }
+
+  /**
+   * Returns a view of the keys of this view that start with `prefix`.
+   *
+   * @param prefix
+   * @returns
+   */
+  scoped(prefix: string): ScopedStore {
+    return new ScopedStore(this.store, this.prefix + prefix);
+  }
+
+  async set(
+    key: string,
+    value: unknown,
+    options: WriteOptions = {},
+  ): Promise<void> {
+    await this.store.set(this.prefix + key, value, options);
+  }
+
+  async get<T>(key: string): Promise<T | null> {
+    return await this.store.get<T>(this.prefix + key);
+  }
+
+  async has(key: string): Promise<boolean> {
+    return await this.store.has(this.prefix + key);
+  }
+
+  async delete(key: string, options: WriteOptions = {}): Promise<boolean> {
+    return await this.store.delete(this.prefix + key, options);
+  }
+
+  /**
+   * Removes all keys of this view from the store.
+   *
+   * @param options
+   * @returns
+   */
+  async clear(options: WriteOptions = {}): Promise<void> {
+    await this.store.clear({ ...options, scope: this.prefix });
+  }
+
+  async keys(): Promise<string[]> {
+    return await this.store.keys({ scope: this.prefix });
+  }
+
+  async values<T>(): Promise<T[]> {
+    return await this.store.values<T>({ scope: this.prefix });
+  }
+
+  async entries<T>(): Promise<Array<[key: string, value: T]>> {
+    return await this.store.entries<T>({ scope: this.prefix });
+  }
+
+  async length(): Promise<number> {
+    return await this.store.length({ scope: this.prefix });
+  }
+
+  /**
+   * Listen to changes of the keys of this view, which are passed to `cb` without the prefix.
+   *
    * @param cb
    * @returns A promise resolving to a function to unlisten to the event.
    */
   async onChange<T>(
-    cb: (key: string, value: T | null) => void,
+    cb: (key: string, value: T | null, context: ChangeContext) => void,
+  ): Promise<UnlistenFn> {
+    return await this.store.onChange<T>((key, value, context) => {
+      if (key.startsWith(this.prefix)) {
+        cb(key.slice(this.prefix.length), value, context);
+      }
+    });
+  }
+
+  async onKeyChange<T>(
+    key: string,
+    cb: (value: T | null, context: ChangeContext) => void,
   ): Promise<UnlistenFn> {
-    return await listen<ChangePayload<T>>("store://change", (event) => {
-      if (event.payload.path === this.path) {
-        cb(event.payload.key, event.payload.value);
+    return await this.store.onKeyChange<T>(this.prefix + key, cb);
+  }
+}
+
+/**
+ * Converts a store value into the string representation `localStorage` would return.
+ */
+function toItem(value: unknown): string {
+  return typeof value === "string" ? value : JSON.stringify(value);
+}
+
+/**
+ * A `localStorage`-like view of a store, easing ports of existing web apps.
+ *
+ * Reads are synchronous and served from a snapshot that is pushed by the backend on every change.
+ * Writes update the snapshot immediately and are written through to the store asynchronously;
+ * use `flush` to wait for them to complete.
+ *
+ * ```typescript
+ * const storage = await LocalStorageCompat.open("local-storage.json");
+ *
+ * storage.setItem("theme", "dark");
+ * storage.getItem("theme"); // "dark"
+ *
+ * await storage.flush();
+ * ```
+ */
+export class LocalStorageCompat {
+  readonly store: Store;
+  private snapshot = new Map<string, string>();
+  private pending: Promise<void> = Promise.resolve();
+  private error: unknown = null;
+  private unlisten: UnlistenFn | null = null;
+
+  private constructor(path: string, options: { changeEvent?: string }) {
+    this.store = new Store(path, options);
+  }
+
+  /**
+   * Opens the store at `path` and fetches its initial snapshot.
+   *
+   * @param path
+   * @param options Passed on to the `Store` constructor.
+   * @returns
+   */
+  static async open(
+    path: string,
+    options: { changeEvent?: string } = {},
+  ): Promise<LocalStorageCompat> {
+    const storage = new LocalStorageCompat(path, options);
+    storage.unlisten = await storage.store.onChange((key, value) => {
+      if (value === null) {
+        storage.snapshot.delete(key);
+      } else {
+        storage.snapshot.set(key, toItem(value));
       }
     });
+    for (const [key, value] of await storage.store.entries()) {
+      storage.snapshot.set(key, toItem(value));
+    }
+    return storage;
+  }
+
+  get length(): number {
+    return this.snapshot.size;
+  }
+
+  key(index: number): string | null {
+    return Array.from(this.snapshot.keys())[index] ?? null;
+  }
+
+  getItem(key: string): string | null {
+    return this.snapshot.get(key) ?? null;
+  }
+
+  setItem(key: string, value: string): void {
+    this.snapshot.set(key, value);
+    this.writeThrough(async () => await this.store.set(key, value));
+  }
+
+  removeItem(key: string): void {
+    this.snapshot.delete(key);
+    this.writeThrough(async () => await this.store.delete(key));
+  }
+
+  clear(): void {
+    this.snapshot.clear();
+    this.writeThrough(async () => await this.store.clear());
+  }
+
+  /**
+   * Waits for all pending writes, rejecting with the first write error since the last flush.
+   *
+   * @returns
+   */
+  async flush(): Promise<void> {
+    await this.pending;
+    const error = this.error;
+    this.error = null;
+    if (error !== null) {
+      throw error;
+    }
+  }
+
+  /**
+   * Stops following changes of the store.
+   */
+  close(): void {
+    this.unlisten?.();
+    this.unlisten = null;
+  }
+
+  private writeThrough(write: () => Promise<unknown>): void {
+    this.pending = this.pending.then(write).then(
+      () => undefined,
+      (error) => {
+        if (this.error === null) {
+          this.error = error;
+        }
+      },
+    );
   }
 }
@@ -0,0 +1,91 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Returns the path of the temporary file `path` is written to before it's renamed into place.
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    temp.into()
+}
+
+/// Returns the file that replacing `path` should replace, following symlinks.
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+    let is_link =
+        fs::symlink_metadata(path).map_or(false, |metadata| metadata.file_type().is_symlink());
+    if !is_link {
+        return Ok(path.to_path_buf());
+    }
+    match fs::canonicalize(path) {
+        Ok(target) => Ok(target),
+        // a dangling link, relative link targets are relative to the directory containing the link
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let target = fs::read_link(path)?;
+            Ok(match path.parent() {
+                Some(parent) => parent.join(target),
+                None => target,
+            })
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// A file that was written and synced next to its destination, waiting to be renamed into place.
+pub(crate) struct StagedFile {
+    temp: PathBuf,
+    path: PathBuf,
+}
+
+impl StagedFile {
+    /// Renames the file into place.
+    pub(crate) fn commit(self) -> io::Result<()> {
+        if let Err(err) = fs::rename(&self.temp, &self.path) {
+            let _ = fs::remove_file(&self.temp);
+            return Err(err);
+        }
+
+        // make the rename itself durable, directories can't be opened for syncing on Windows
+        #[cfg(unix)]
+        if let Some(parent) = self
+            .path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            File::open(parent)?.sync_all()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes `bytes` to a temporary file next to `path` and syncs it to disk, see [`StagedFile::commit`].
+///
+/// If `path` is a symlink, the file it points to is replaced instead of the link.
+pub(crate) fn stage(path: &Path, bytes: &[u8]) -> io::Result<StagedFile> {
+    let path = resolve(path)?;
+    let temp = temp_path(&path);
+
+    let result = File::create(&temp).and_then(|mut f| {
+        f.write_all(bytes)?;
+        f.sync_all()
+    });
+    if let Err(err) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    Ok(StagedFile { temp, path })
+}
+
+/// Writes `bytes` to `path` so that a crash leaves either the old or the new contents, never a mix.
+///
+/// The bytes are written to a temporary file next to `path`, synced to disk and renamed over `path`.
+/// If `path` is a symlink, the file it points to is replaced instead of the link.
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    stage(path, bytes)?.commit()
+}
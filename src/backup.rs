@@ -0,0 +1,156 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    atomic::write_atomic, collection_key, ensure_store, ConflictStrategy, Error, KeyFilter,
+    StoreCollection,
+};
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::{AppHandle, Runtime};
+
+const MAGIC: &[u8; 4] = b"TSBK";
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Describes the contents of a backup archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupManifest {
+    /// Seconds since the Unix epoch at which the backup was created.
+    pub created_at: u64,
+    /// The paths of the stores in the archive.
+    pub stores: Vec<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Archive {
+    manifest: BackupManifest,
+    stores: HashMap<PathBuf, HashMap<String, JsonValue>>,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, Error> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| Error::Backup(err.to_string()))?;
+    Ok(key)
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], Error> {
+    let mut bytes = [0; N];
+    getrandom::getrandom(&mut bytes).map_err(|err| Error::Backup(err.to_string()))?;
+    Ok(bytes)
+}
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Writes all stores of the collection into a single archive at `target`, encrypted with `passphrase`.
+    ///
+    /// Only keys selected by `filter` are included.
+    pub fn backup_all(
+        &self,
+        target: &Path,
+        passphrase: &str,
+        filter: &KeyFilter,
+    ) -> Result<BackupManifest, Error> {
+        let stores: HashMap<PathBuf, HashMap<String, JsonValue>> = self
+            .stores
+            .lock()
+            .expect("mutex poisoned")
+            .iter()
+            .map(|(path, store)| (path.clone(), store.export(filter)))
+            .collect();
+
+        let manifest = BackupManifest {
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+            stores: stores.keys().cloned().collect(),
+        };
+        let plaintext = serde_json::to_vec(&Archive {
+            manifest: manifest.clone(),
+            stores,
+        })?;
+
+        let salt = random_bytes::<SALT_LEN>()?;
+        let nonce = random_bytes::<NONCE_LEN>()?;
+        let ciphertext = XChaCha20Poly1305::new(&derive_key(passphrase, &salt)?)
+            .encrypt(XNonce::from_slice(&nonce), plaintext.as_ref())
+            .map_err(|err| Error::Backup(err.to_string()))?;
+
+        let mut bytes =
+            Vec::with_capacity(MAGIC.len() + 1 + SALT_LEN + NONCE_LEN + ciphertext.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&salt);
+        bytes.extend_from_slice(&nonce);
+        bytes.extend_from_slice(&ciphertext);
+        write_atomic(target, &bytes)?;
+
+        Ok(manifest)
+    }
+
+    /// Restores the stores of an archive created by [`Self::backup_all`].
+    ///
+    /// Keys of stores that already exist are merged according to the strategy in `strategies`,
+    /// falling back to `default_strategy`. Every store of the archive is checked before any is changed,
+    /// so nothing is restored if any key conflicts under [`ConflictStrategy::Fail`] or is rejected by its store.
+    pub fn restore_all(
+        &self,
+        app: &AppHandle<R>,
+        archive: &Path,
+        passphrase: &str,
+        default_strategy: ConflictStrategy,
+        strategies: &HashMap<PathBuf, ConflictStrategy>,
+    ) -> Result<BackupManifest, Error> {
+        let bytes = std::fs::read(archive)?;
+        let header_len = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+        if bytes.len() < header_len || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(Error::Backup("not a store backup archive".into()));
+        }
+        if bytes[MAGIC.len()] != FORMAT_VERSION {
+            return Err(Error::Backup(format!(
+                "unsupported archive version {}",
+                bytes[MAGIC.len()]
+            )));
+        }
+        let (salt, rest) = bytes[MAGIC.len() + 1..].split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        let plaintext = XChaCha20Poly1305::new(&derive_key(passphrase, salt)?)
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| Error::Backup("wrong passphrase or corrupted archive".into()))?;
+        let archive: Archive = serde_json::from_slice(&plaintext)?;
+
+        let strategy_for =
+            |path: &PathBuf| strategies.get(path).copied().unwrap_or(default_strategy);
+
+        let mut stores = self.stores.lock().expect("mutex poisoned");
+        for (path, entries) in &archive.stores {
+            let key = ensure_store(app, self, &mut stores, path)?;
+            stores[&key].check_import(entries, strategy_for(path))?;
+        }
+        for (path, entries) in archive.stores {
+            let strategy = strategy_for(&path);
+            stores
+                .get_mut(&collection_key(&path))
+                .expect("store was loaded above")
+                .import_map(entries, strategy, None)?;
+        }
+
+        Ok(archive.manifest)
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{Error, JsonValue, Store};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::Runtime;
+
+/// A value cached with [`Store::cache_put`], as returned by [`Store::cache_get`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CachedValue {
+    pub value: JsonValue,
+    /// Whether the value outlived its freshness and should be revalidated.
+    pub stale: bool,
+}
+
+/// A cached value as stored under its key.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntry {
+    value: JsonValue,
+    /// Milliseconds since the Unix epoch until which the value is fresh.
+    fresh_until: u64,
+}
+
+impl<R: Runtime> Store<R> {
+    /// Caches `value` under `key`, e.g. an API response, fresh for `fresh_for`.
+    ///
+    /// With `max_age`, the entry is removed by [`Self::sweep_expired`] once it has passed;
+    /// otherwise stale values are kept until they are replaced.
+    pub fn cache_put(
+        &mut self,
+        key: String,
+        value: JsonValue,
+        fresh_for: Duration,
+        max_age: Option<Duration>,
+    ) -> Result<(), Error> {
+        let entry = serde_json::to_value(CacheEntry {
+            value,
+            fresh_until: self
+                .clock
+                .now_millis()
+                .saturating_add(fresh_for.as_millis() as u64),
+        })?;
+        match max_age {
+            Some(max_age) => self.insert_with_ttl(key, entry, max_age),
+            None => self.insert(key, entry),
+        }
+    }
+
+    /// Returns the value cached under `key` with [`Self::cache_put`] and whether it is stale,
+    /// for stale-while-revalidate caching: use a stale value right away and refresh it in the background.
+    pub fn cache_get(&self, key: &str) -> Result<Option<CachedValue>, Error> {
+        let Some(entry) = self.get(key) else {
+            return Ok(None);
+        };
+        let entry = CacheEntry::deserialize(entry)?;
+        Ok(Some(CachedValue {
+            value: entry.value,
+            stale: entry.fresh_until <= self.clock.now_millis(),
+        }))
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Stable client ids, generated on first use and persisted in a store.
+
+use crate::{ensure_store, Error, JsonValue, Store, StoreCollection};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tauri::{AppHandle, Runtime};
+
+/// The key under which a stable, random client id is stored, see [`Store::client_id`].
+pub const CLIENT_ID_KEY: &str = "__client_id";
+/// The key under which the machine-scoped client id is stored, see [`ClientIdScope::Machine`].
+pub const MACHINE_ID_KEY: &str = "__machine_id";
+/// The store [`StoreCollection::client_id`] keeps the ids in unless told otherwise.
+pub const DEFAULT_CLIENT_ID_PATH: &str = "client.json";
+
+/// Which installations share a client id, see [`StoreCollection::client_id`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClientIdScope {
+    /// A random id per OS user, kept in the user's app data directory.
+    #[default]
+    User,
+    /// An id derived from the OS machine id and the app identifier, the same for every user of the machine.
+    ///
+    /// The OS machine id itself isn't exposed, and other apps derive different ids from it.
+    Machine,
+}
+
+/// Formats 16 bytes as a UUID of the given version with the RFC 4122 variant.
+fn format_uuid(mut bytes: [u8; 16], version: u8) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | (version << 4);
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Returns a random version 4 UUID.
+pub(crate) fn random_uuid() -> Result<String, Error> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))?;
+    Ok(format_uuid(bytes, 4))
+}
+
+/// Reads the id the OS assigns to the machine.
+#[cfg(target_os = "macos")]
+fn read_os_machine_id() -> Result<String, Error> {
+    // not resolved through `PATH`, which the user controls
+    let output = std::process::Command::new("/usr/sbin/ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::MachineId(format!(
+            "ioreg failed with {}",
+            output.status
+        )));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("\"IOPlatformUUID\""))
+        .and_then(|line| line.split('"').nth(3))
+        .map(str::to_string)
+        .ok_or_else(|| Error::MachineId("IOPlatformUUID not found".into()))
+}
+
+/// Reads the id the OS assigns to the machine.
+#[cfg(windows)]
+fn read_os_machine_id() -> Result<String, Error> {
+    use std::os::windows::process::CommandExt;
+
+    /// Keeps `reg.exe` from opening a console window in GUI apps.
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    // not resolved through `PATH`, which the user controls
+    let system_root =
+        std::env::var_os("SystemRoot").unwrap_or_else(|| std::ffi::OsString::from(r"C:\Windows"));
+    let reg = std::path::Path::new(&system_root)
+        .join("System32")
+        .join("reg.exe");
+    let output = std::process::Command::new(reg)
+        .args([
+            "query",
+            r"HKLM\SOFTWARE\Microsoft\Cryptography",
+            "/v",
+            "MachineGuid",
+        ])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::MachineId(format!(
+            "reg failed with {}",
+            output.status
+        )));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("MachineGuid"))
+        .and_then(|line| line.split_whitespace().last())
+        .map(str::to_string)
+        .ok_or_else(|| Error::MachineId("MachineGuid not found".into()))
+}
+
+/// Reads the id the OS assigns to the machine.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn read_os_machine_id() -> Result<String, Error> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|path| {
+            std::fs::read_to_string(path)
+                .ok()
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty())
+        })
+        .ok_or_else(|| Error::MachineId("machine-id not found".into()))
+}
+
+/// Returns the id the OS assigns to the machine, read once per process.
+///
+/// A failure is remembered as well, so machines without an id don't spawn a process on every request.
+fn os_machine_id() -> Result<String, Error> {
+    static MACHINE_ID: OnceCell<Result<String, String>> = OnceCell::new();
+    MACHINE_ID
+        .get_or_init(|| {
+            read_os_machine_id().map_err(|err| match err {
+                Error::MachineId(reason) => reason,
+                err => err.to_string(),
+            })
+        })
+        .clone()
+        .map_err(Error::MachineId)
+}
+
+/// Derives a version 8 UUID from the OS machine id, specific to the app `identifier`.
+fn machine_uuid(identifier: &str) -> Result<String, Error> {
+    let digest = Sha256::new()
+        .chain_update(os_machine_id()?)
+        .chain_update(":")
+        .chain_update(identifier)
+        .finalize();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    Ok(format_uuid(bytes, 8))
+}
+
+impl<R: Runtime> Store<R> {
+    /// Returns the id stored under `key`, storing the one returned by `generate` if there is none.
+    ///
+    /// A new id is saved right away, so it survives a crash.
+    fn provision_id(
+        &mut self,
+        key: &str,
+        generate: impl FnOnce() -> Result<String, Error>,
+    ) -> Result<String, Error> {
+        if let Some(JsonValue::String(id)) = self.get(key) {
+            return Ok(id.clone());
+        }
+        let id = generate()?;
+        self.insert(key.to_string(), JsonValue::String(id.clone()))?;
+        self.save_if_dirty()?;
+        Ok(id)
+    }
+
+    /// Returns the stable client id of this store, a random UUID generated and saved on first use.
+    ///
+    /// The id is stored under [`CLIENT_ID_KEY`]. Ids generated by earlier versions, which are hex strings
+    /// without dashes, are kept as they are.
+    pub fn client_id(&mut self) -> Result<String, Error> {
+        self.provision_id(CLIENT_ID_KEY, random_uuid)
+    }
+}
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Returns the stable client id of the given `scope`, generating and persisting it on first use.
+    ///
+    /// The id is kept in the store at `path`, or [`DEFAULT_CLIENT_ID_PATH`]. The store stays locked while the id
+    /// is generated, so windows asking for it at the same time get the same id.
+    pub fn client_id(
+        &self,
+        app: &AppHandle<R>,
+        path: Option<&Path>,
+        scope: ClientIdScope,
+    ) -> Result<String, Error> {
+        let path = path.unwrap_or_else(|| Path::new(DEFAULT_CLIENT_ID_PATH));
+        let mut stores = self.stores.lock().expect("mutex poisoned");
+        let key = ensure_store(app, self, &mut stores, path)?;
+        let store = stores.get_mut(&key).expect("store was loaded above");
+        match scope {
+            ClientIdScope::User => store.client_id(),
+            ClientIdScope::Machine => {
+                let identifier = app.config().tauri.bundle.identifier.clone();
+                store.provision_id(MACHINE_ID_KEY, || machine_uuid(&identifier))
+            }
+        }
+    }
+}
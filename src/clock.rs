@@ -0,0 +1,27 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The source of the current time used for TTLs, change timestamps, history entries and the persistence status
+/// of a store, see [`StoreBuilder::clock`](crate::StoreBuilder::clock).
+///
+/// Implement it to control time in tests, or to use a source that doesn't jump with the system clock.
+pub trait Clock: Send + Sync {
+    /// Returns the current time in milliseconds since the Unix epoch.
+    fn now_millis(&self) -> u64;
+}
+
+/// The system's wall clock, used by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default()
+    }
+}
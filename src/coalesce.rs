@@ -0,0 +1,114 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Write coalescing for keys updated many times per second, see [`StoreBuilder::coalesce`](crate::StoreBuilder::coalesce).
+
+use crate::{Error, JsonValue, KeyFilter, Store};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tauri::Runtime;
+
+#[derive(Debug, Clone)]
+pub(crate) struct Coalescing {
+    filter: KeyFilter,
+    pub(crate) interval: Duration,
+    /// The latest values of keys written since they were last committed.
+    staged: HashMap<String, JsonValue>,
+    /// When each key was last committed.
+    committed_at: HashMap<String, Instant>,
+}
+
+impl Coalescing {
+    pub(crate) fn new(filter: KeyFilter, interval: Duration) -> Self {
+        Self {
+            filter,
+            interval,
+            staged: HashMap::new(),
+            committed_at: HashMap::new(),
+        }
+    }
+
+    /// Stages `value` if `key` was committed less than the interval ago, otherwise returns it to be committed now.
+    pub(crate) fn stage(&mut self, key: &str, value: JsonValue) -> Option<JsonValue> {
+        if !self.filter.matches(key) {
+            return Some(value);
+        }
+        let now = Instant::now();
+        match self.committed_at.get(key) {
+            Some(committed_at) if now.duration_since(*committed_at) < self.interval => {
+                self.staged.insert(key.to_string(), value);
+                None
+            }
+            _ => {
+                self.staged.remove(key);
+                self.committed_at.insert(key.to_string(), now);
+                Some(value)
+            }
+        }
+    }
+
+    /// Drops the staged value of `key`, e.g. because the key was deleted.
+    pub(crate) fn discard(&mut self, key: &str) {
+        self.staged.remove(key);
+    }
+
+    pub(crate) fn discard_all(&mut self) {
+        self.staged.clear();
+    }
+
+    /// Removes the staged values that are due, or all of them if `all` is set, marking them as committed.
+    fn take(&mut self, all: bool) -> Vec<(String, JsonValue)> {
+        let now = Instant::now();
+        let due: Vec<String> = self
+            .staged
+            .keys()
+            .filter(|key| {
+                all || self.committed_at.get(*key).map_or(true, |committed_at| {
+                    now.duration_since(*committed_at) >= self.interval
+                })
+            })
+            .cloned()
+            .collect();
+        due.into_iter()
+            .map(|key| {
+                self.committed_at.insert(key.clone(), now);
+                let value = self.staged.remove(&key).expect("key was staged");
+                (key, value)
+            })
+            .collect()
+    }
+}
+
+impl<R: Runtime> Store<R> {
+    /// Commits all values staged by [`StoreBuilder::coalesce`](crate::StoreBuilder::coalesce) right away,
+    /// emitting their change events.
+    pub fn commit_staged(&mut self) -> Result<(), Error> {
+        self.commit(true)
+    }
+
+    /// Commits the staged values whose interval elapsed. Called periodically in the background.
+    pub(crate) fn commit_due(&mut self) -> Result<(), Error> {
+        self.commit(false)
+    }
+
+    fn commit(&mut self, all: bool) -> Result<(), Error> {
+        let entries = match &mut self.coalescing {
+            Some(coalescing) => coalescing.take(all),
+            None => return Ok(()),
+        };
+        for (key, value) in entries {
+            self.commit_value(key, value)?;
+        }
+        Ok(())
+    }
+
+    /// The interval at which staged values are committed, if the store coalesces writes.
+    pub(crate) fn coalesce_interval(&self) -> Option<Duration> {
+        self.coalescing
+            .as_ref()
+            .map(|coalescing| coalescing.interval)
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Compression of individual values, set up with
+//! [`StoreBuilder::compress_values_above`](crate::StoreBuilder::compress_values_above).
+
+use crate::{Error, JsonValue};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use std::io::{Read, Write};
+
+/// The field of the object a compressed value is replaced with in the store file.
+const COMPRESSED_FIELD: &str = "$deflate";
+
+/// Returns the deflated value wrapped into an object, or `None` if its JSON encoding isn't larger than
+/// `threshold` bytes or doesn't get smaller.
+pub(crate) fn compress(value: &JsonValue, threshold: usize) -> Result<Option<JsonValue>, Error> {
+    let plain = serde_json::to_vec(value)?;
+    if plain.len() <= threshold {
+        return Ok(None);
+    }
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&plain)?;
+    let encoded = STANDARD.encode(encoder.finish()?);
+    if encoded.len() >= plain.len() {
+        return Ok(None);
+    }
+    Ok(Some(JsonValue::Object(
+        [(COMPRESSED_FIELD.to_string(), encoded.into())]
+            .into_iter()
+            .collect(),
+    )))
+}
+
+/// Whether `value` was produced by [`compress`].
+pub(crate) fn is_compressed(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Object(map) => {
+            map.len() == 1
+                && map
+                    .get(COMPRESSED_FIELD)
+                    .map_or(false, JsonValue::is_string)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the unparsed JSON `raw` may hold a compressed value, without parsing it.
+pub(crate) fn may_be_compressed(raw: &str) -> bool {
+    raw.trim_start().strip_prefix('{').map_or(false, |rest| {
+        rest.trim_start()
+            .starts_with(&format!("\"{}\"", COMPRESSED_FIELD))
+    })
+}
+
+/// Unwraps a value produced by [`compress`], passing other values through.
+pub(crate) fn decompress(value: JsonValue) -> Result<JsonValue, Error> {
+    if !is_compressed(&value) {
+        return Ok(value);
+    }
+    let encoded = value[COMPRESSED_FIELD].as_str().unwrap_or_default();
+    let deflated = STANDARD
+        .decode(encoded)
+        .map_err(|err| Error::Compression(err.to_string()))?;
+    let mut plain = Vec::new();
+    DeflateDecoder::new(deflated.as_slice())
+        .read_to_end(&mut plain)
+        .map_err(|err| Error::Compression(err.to_string()))?;
+    Ok(serde_json::from_slice(&plain)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn round_trip() {
+        let value = json!({ "log": "line\n".repeat(1000) });
+        let compressed = compress(&value, 64).unwrap().unwrap();
+        assert!(is_compressed(&compressed));
+        assert!(may_be_compressed(&compressed.to_string()));
+        assert_eq!(decompress(compressed).unwrap(), value);
+    }
+
+    #[test]
+    fn skips_small_and_incompressible_values() {
+        assert_eq!(compress(&json!("short"), 64).unwrap(), None);
+        // deflate and base64 make a few random-looking bytes larger
+        assert_eq!(compress(&json!("q8Zr2xT0vLw9"), 0).unwrap(), None);
+    }
+
+    #[test]
+    fn passes_other_values_through() {
+        for value in [
+            json!("plain"),
+            json!({ COMPRESSED_FIELD: 1 }),
+            json!({ COMPRESSED_FIELD: "", "other": 1 }),
+        ] {
+            assert!(!is_compressed(&value));
+            assert_eq!(decompress(value.clone()).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn corrupt_values_fail() {
+        assert!(matches!(
+            decompress(json!({ COMPRESSED_FIELD: "not base64!" })),
+            Err(Error::Compression(_))
+        ));
+        assert!(matches!(
+            decompress(json!({ COMPRESSED_FIELD: STANDARD.encode("not deflated") })),
+            Err(Error::Compression(_))
+        ));
+    }
+
+    #[test]
+    fn detects_compressed_raw_json() {
+        assert!(may_be_compressed(r#" { "$deflate": "eJw=" }"#));
+        assert!(!may_be_compressed(r#"{"name": "$deflate"}"#));
+        assert!(!may_be_compressed(r#""$deflate""#));
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{Error, JsonValue, Store};
+use arc_swap::ArcSwap;
+use log::warn;
+use serde::de::DeserializeOwned;
+use std::{collections::HashMap, sync::Arc};
+use tauri::Runtime;
+
+/// A cheap, cloneable handle to the contents of a [`Store`] deserialized into `T`, created with [`Store::bind`].
+///
+/// The whole store is deserialized again every time it changes, so readers always see a coherent snapshot.
+/// Changes that don't deserialize into `T` are logged and the previous snapshot is kept.
+///
+/// # Examples
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use serde::Deserialize;
+/// use tauri_plugin_store::StoreBuilder;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///   theme: String,
+///   #[serde(default)]
+///   telemetry: bool,
+/// }
+///
+/// let mut store = StoreBuilder::new("settings.json".parse()?).build();
+/// let config = store.bind::<Config>()?;
+///
+/// std::thread::spawn(move || {
+///   let telemetry = config.get().telemetry;
+/// });
+///
+/// # Ok(())
+/// # }
+/// ```
+pub struct TypedConfig<T> {
+    snapshot: Arc<ArcSwap<T>>,
+}
+
+impl<T> Clone for TypedConfig<T> {
+    fn clone(&self) -> Self {
+        Self {
+            snapshot: self.snapshot.clone(),
+        }
+    }
+}
+
+impl<T> TypedConfig<T> {
+    /// Returns the current snapshot.
+    pub fn get(&self) -> Arc<T> {
+        self.snapshot.load_full()
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for TypedConfig<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedConfig")
+            .field("snapshot", &self.snapshot.load())
+            .finish()
+    }
+}
+
+fn deserialize<T: DeserializeOwned>(contents: &HashMap<String, JsonValue>) -> Result<T, Error> {
+    let object = contents
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    Ok(serde_json::from_value(JsonValue::Object(object))?)
+}
+
+impl<R: Runtime> Store<R> {
+    /// Returns a handle to the contents of the store deserialized into `T`, kept up to date as the store changes.
+    ///
+    /// Fails if the current contents don't deserialize into `T`.
+    pub fn bind<T: DeserializeOwned + Send + Sync + 'static>(
+        &mut self,
+    ) -> Result<TypedConfig<T>, Error> {
+        let snapshot = Arc::new(ArcSwap::from_pointee(deserialize::<T>(&self.contents())?));
+
+        let path = self.path.clone();
+        let target = snapshot.clone();
+        self.bindings
+            .push(Arc::new(move |contents| match deserialize::<T>(contents) {
+                Ok(config) => target.store(Arc::new(config)),
+                Err(err) => warn!(
+                    "Contents of store {:?} don't match the bound {}: {}",
+                    path,
+                    std::any::type_name::<T>(),
+                    err
+                ),
+            }));
+
+        Ok(TypedConfig { snapshot })
+    }
+}
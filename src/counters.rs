@@ -0,0 +1,104 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Usage counters that are maintained in memory and flushed into stores periodically.
+
+use crate::{collection_key, ensure_store, Error, JsonValue, StoreCollection};
+use log::warn;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use tauri::{AppHandle, Manager, Runtime};
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Adds `n` to the counter stored under the key `name` in the store at `path`, e.g. to count launches
+    /// or feature usage.
+    ///
+    /// The increment is kept in memory and written to the store periodically, see
+    /// [`Builder::counter_flush_interval`](crate::Builder::counter_flush_interval), and when the app exits,
+    /// so frequent increments don't hammer the disk. Increments from all windows add up.
+    pub fn counter_add(&self, app: &AppHandle<R>, path: &Path, name: &str, n: i64) {
+        self.start_counter_timer(app);
+        *self
+            .counters
+            .lock()
+            .expect("mutex poisoned")
+            .entry((collection_key(path), name.to_string()))
+            .or_default() += n;
+    }
+
+    /// Starts flushing the counters in the background, unless it was started already.
+    ///
+    /// Apps that never count anything don't need the thread.
+    fn start_counter_timer(&self, app: &AppHandle<R>) {
+        if self.read_only {
+            return;
+        }
+        self.counter_timer.call_once(|| {
+            let app_handle = app.clone();
+            let interval = self.counter_flush_interval;
+            std::thread::spawn(move || loop {
+                std::thread::sleep(interval);
+                app_handle
+                    .state::<StoreCollection<R>>()
+                    .flush_counters(&app_handle);
+            });
+        });
+    }
+
+    /// Returns the value of the counter stored under the key `name` in the store at `path`,
+    /// including increments that weren't flushed yet.
+    pub fn counter_get(&self, app: &AppHandle<R>, path: &Path, name: &str) -> Result<i64, Error> {
+        let pending = self
+            .counters
+            .lock()
+            .expect("mutex poisoned")
+            .get(&(collection_key(path), name.to_string()))
+            .copied()
+            .unwrap_or_default();
+        let mut stores = self.stores.lock().expect("mutex poisoned");
+        let key = ensure_store(app, self, &mut stores, path)?;
+        let stored = stores[&key]
+            .get(name)
+            .and_then(JsonValue::as_i64)
+            .unwrap_or_default();
+        Ok(stored.saturating_add(pending))
+    }
+
+    /// Writes the pending increments of all counters into their stores.
+    pub fn flush_counters(&self, app: &AppHandle<R>) {
+        let pending: HashMap<(PathBuf, String), i64> =
+            std::mem::take(&mut *self.counters.lock().expect("mutex poisoned"));
+        if pending.is_empty() {
+            return;
+        }
+
+        let mut stores = self.stores.lock().expect("mutex poisoned");
+        for ((path, name), n) in pending {
+            let key = match ensure_store(app, self, &mut stores, &path) {
+                Ok(key) => key,
+                Err(err) => {
+                    warn!(
+                        "Failed to flush counter {:?} of store {:?}: {}",
+                        name, path, err
+                    );
+                    continue;
+                }
+            };
+            let store = stores.get_mut(&key).expect("store was loaded above");
+            let value = store
+                .get(&name)
+                .and_then(JsonValue::as_i64)
+                .unwrap_or_default()
+                .saturating_add(n);
+            if let Err(err) = store.insert(name, value.into()) {
+                store.report_error("flush counters into", err);
+            }
+            if let Err(err) = store.save_if_due() {
+                store.report_error("save", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Cursors walking the keys of a store page by page, in lexicographic order.
+//!
+//! A cursor only remembers the last key it returned, so walking a store never copies all of its keys.
+
+use crate::{collection_key, ensure_store, Error, JsonValue, Store, StoreCollection};
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+};
+use tauri::{AppHandle, Runtime};
+
+/// The position of a cursor opened with [`StoreCollection::cursor_open`].
+pub(crate) struct Cursor {
+    path: PathBuf,
+    /// The last key returned, `None` before the first page.
+    after: Option<String>,
+}
+
+impl<R: Runtime> Store<R> {
+    /// Returns the first `limit` keys that sort after `after` lexicographically, or the first keys if `after` is `None`.
+    ///
+    /// Only `limit` keys are held at a time, so large stores can be walked without copying all of their keys.
+    pub fn keys_after(&self, after: Option<&str>, limit: usize) -> Vec<&String> {
+        let mut page = BTreeSet::new();
+        for key in self.keys() {
+            if after.map_or(false, |after| key.as_str() <= after) {
+                continue;
+            }
+            page.insert(key);
+            if page.len() > limit {
+                page.pop_last();
+            }
+        }
+        page.into_iter().collect()
+    }
+}
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Opens a cursor over the keys of the store at `path`, loading the store if needed.
+    pub fn cursor_open(&self, app: &AppHandle<R>, path: &Path) -> Result<u64, Error> {
+        let mut stores = self.stores.lock().expect("mutex poisoned");
+        let key = ensure_store(app, self, &mut stores, path)?;
+
+        let id = self.next_cursor.fetch_add(1, Ordering::Relaxed);
+        self.cursors.lock().expect("mutex poisoned").insert(
+            id,
+            Cursor {
+                path: key,
+                after: None,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Returns the next `limit` entries of a cursor, in lexicographic order of their keys.
+    ///
+    /// Keys inserted behind the cursor's position are skipped. An empty page means the cursor is exhausted.
+    pub fn cursor_next(
+        &self,
+        cursor: u64,
+        limit: usize,
+    ) -> Result<Vec<(String, JsonValue)>, Error> {
+        let mut cursors = self.cursors.lock().expect("mutex poisoned");
+        let cursor = cursors
+            .get_mut(&cursor)
+            .ok_or(Error::CursorNotFound(cursor))?;
+
+        let stores = self.stores.lock().expect("mutex poisoned");
+        let store = stores
+            .get(&collection_key(&cursor.path))
+            .ok_or_else(|| Error::NotFound(cursor.path.clone()))?;
+        let page: Vec<(String, JsonValue)> = store
+            .keys_after(cursor.after.as_deref(), limit)
+            .into_iter()
+            .filter_map(|key| store.get(key).map(|value| (key.clone(), value.clone())))
+            .collect();
+
+        if let Some((last, _)) = page.last() {
+            cursor.after = Some(last.clone());
+        }
+        Ok(page)
+    }
+
+    /// Closes a cursor, returning `true` if it was open.
+    pub fn cursor_close(&self, cursor: u64) -> bool {
+        self.cursors
+            .lock()
+            .expect("mutex poisoned")
+            .remove(&cursor)
+            .is_some()
+    }
+}
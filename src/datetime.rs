@@ -0,0 +1,91 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The canonical encoding of timestamps in stores.
+//!
+//! Timestamps are stored as RFC 3339 strings in UTC with exactly three fractional digits, e.g.
+//! `2024-05-01T09:30:00.000Z`, which is what JavaScript's `Date.prototype.toISOString` produces.
+//! Values written from Rust and from the frontend therefore agree and compare correctly as strings.
+//!
+//! Use this module with `#[serde(with = "tauri_plugin_store::datetime")]` on [`OffsetDateTime`] fields
+//! of types stored with [`Store::insert`] or read with [`Store::bind`].
+//!
+//! # Examples
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use tauri_plugin_store::OffsetDateTime;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Session {
+//!   #[serde(with = "tauri_plugin_store::datetime")]
+//!   started_at: OffsetDateTime,
+//! }
+//! ```
+
+use crate::{Error, JsonValue, Store};
+use serde::{de, ser, Deserialize, Deserializer, Serializer};
+use tauri::Runtime;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime, UtcOffset};
+
+/// Formats `value` in the canonical encoding, converting it to UTC and truncating it to milliseconds.
+///
+/// Fails for years before 0 or after 9999, which RFC 3339 can't represent.
+pub fn format_datetime(value: OffsetDateTime) -> Result<String, Error> {
+    let value = value.to_offset(UtcOffset::UTC);
+    if !(0..=9999).contains(&value.year()) {
+        return Err(Error::InvalidDatetime(value.to_string()));
+    }
+    Ok(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        value.year(),
+        value.month() as u8,
+        value.day(),
+        value.hour(),
+        value.minute(),
+        value.second(),
+        value.millisecond()
+    ))
+}
+
+/// Parses an RFC 3339 timestamp with any offset, returning it in UTC.
+pub fn parse_datetime(value: &str) -> Result<OffsetDateTime, Error> {
+    OffsetDateTime::parse(value, &Rfc3339)
+        .map(|value| value.to_offset(UtcOffset::UTC))
+        .map_err(|_| Error::InvalidDatetime(value.to_string()))
+}
+
+/// Serializes an [`OffsetDateTime`] in the canonical encoding.
+pub fn serialize<S: Serializer>(value: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error> {
+    let value = format_datetime(*value).map_err(ser::Error::custom)?;
+    serializer.serialize_str(&value)
+}
+
+/// Deserializes an [`OffsetDateTime`] from an RFC 3339 string with any offset.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<OffsetDateTime, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_datetime(&value).map_err(de::Error::custom)
+}
+
+impl<R: Runtime> Store<R> {
+    /// Stores `value` under `key` in the canonical encoding, see [`crate::datetime`].
+    pub fn set_datetime(
+        &mut self,
+        key: impl Into<String>,
+        value: OffsetDateTime,
+    ) -> Result<(), Error> {
+        let value = format_datetime(value)?;
+        self.insert(key.into(), JsonValue::String(value))
+    }
+
+    /// Returns the timestamp stored under `key` in UTC, or `None` if the key doesn't exist.
+    ///
+    /// Any RFC 3339 string is accepted, so values written before the canonical encoding was adopted still parse.
+    pub fn get_datetime(&self, key: impl AsRef<str>) -> Result<Option<OffsetDateTime>, Error> {
+        match self.get(key) {
+            Some(JsonValue::String(value)) => parse_datetime(value).map(Some),
+            Some(value) => Err(Error::InvalidDatetime(value.to_string())),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Store writes requested through deep links like `myapp://store/set?path=settings.json&theme=dark`.
+
+use crate::{collection_key, with_store, Error, JsonValue, KeyFilter, StoreCollection};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use tauri::{AppHandle, Manager, Runtime};
+
+pub(crate) type ConfirmFn = Arc<dyn Fn(&DeepLinkUpdate) -> bool + Send + Sync>;
+
+/// The store write requested by a deep link, passed to the hook of [`Builder::confirm_deep_link_updates`](crate::Builder::confirm_deep_link_updates).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeepLinkUpdate {
+    /// The path of the store to write to.
+    pub path: PathBuf,
+    /// The key-value pairs to write.
+    pub entries: HashMap<String, JsonValue>,
+}
+
+/// The stores and keys deep links may write to, and the hook confirming their writes.
+#[derive(Default)]
+pub(crate) struct DeepLinks {
+    pub(crate) allowed: HashMap<PathBuf, KeyFilter>,
+    pub(crate) confirm: Option<ConfirmFn>,
+}
+
+fn percent_decode(text: &str) -> Result<String, Error> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let byte = text
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| Error::DeepLink(format!("invalid escape in {:?}", text)))?;
+                decoded.push(byte);
+                i += 2;
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8(decoded).map_err(|err| Error::DeepLink(err.to_string()))
+}
+
+/// Parses a `<scheme>://store/set?path=<store>&<key>=<value>...` link.
+///
+/// Values are parsed as JSON, falling back to a string if they aren't valid JSON.
+fn parse(url: &str) -> Result<DeepLinkUpdate, Error> {
+    let (_, rest) = url
+        .split_once("://")
+        .ok_or_else(|| Error::DeepLink(format!("{:?} is not a URL", url)))?;
+    let (route, query) = rest.split_once('?').unwrap_or((rest, ""));
+    if route.trim_end_matches('/') != "store/set" {
+        return Err(Error::DeepLink(format!("unknown route {:?}", route)));
+    }
+
+    let mut path = None;
+    let mut entries = HashMap::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let (name, value) = (percent_decode(name)?, percent_decode(value)?);
+        if name == "path" {
+            path = Some(PathBuf::from(value));
+        } else {
+            let value = serde_json::from_str(&value).unwrap_or(JsonValue::String(value));
+            entries.insert(name, value);
+        }
+    }
+
+    Ok(DeepLinkUpdate {
+        path: path.ok_or_else(|| Error::DeepLink("missing the `path` parameter".into()))?,
+        entries,
+    })
+}
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Writes the key-value pairs of a `<scheme>://store/set?path=<store>&<key>=<value>...` deep link into the store,
+    /// returning `false` if the hook of [`Builder::confirm_deep_link_updates`](crate::Builder::confirm_deep_link_updates)
+    /// declined it.
+    ///
+    /// Only stores and keys allowed with [`Builder::allow_deep_link_updates`](crate::Builder::allow_deep_link_updates)
+    /// can be written; links with any other key are rejected as a whole. Values are parsed as JSON,
+    /// falling back to a string, so `theme=dark` and `theme=%22dark%22` are equivalent.
+    pub fn handle_deep_link(&self, app: &AppHandle<R>, url: &str) -> Result<bool, Error> {
+        let update = parse(url)?;
+        let filter = self
+            .deep_links
+            .allowed
+            .get(&collection_key(&update.path))
+            .ok_or_else(|| {
+                Error::DeepLink(format!(
+                    "store {:?} can't be updated through deep links",
+                    update.path
+                ))
+            })?;
+        if let Some(key) = update.entries.keys().find(|key| !filter.matches(key)) {
+            return Err(Error::DeepLink(format!(
+                "key {:?} of store {:?} can't be updated through deep links",
+                key, update.path
+            )));
+        }
+        if let Some(confirm) = &self.deep_links.confirm {
+            if !confirm(&update) {
+                return Ok(false);
+            }
+        }
+
+        let DeepLinkUpdate { path, entries } = update;
+        with_store(app.clone(), app.state(), path, |store| {
+            store.insert_many(entries)
+        })?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{PersistenceStatus, Store, StoreCollection};
+use serde::Serialize;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::Runtime;
+
+/// An anonymized report about the stores of the app that users can attach to bug reports.
+///
+/// It never contains keys or values.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostics {
+    /// The version of this plugin.
+    pub plugin_version: &'static str,
+    /// Milliseconds since the Unix epoch at which the report was created.
+    pub generated_at: u64,
+    /// Whether the stores are read-only because another instance of the app owns them.
+    pub read_only: bool,
+    pub stores: Vec<StoreDiagnostics>,
+}
+
+/// The part of [`Diagnostics`] about a single store.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreDiagnostics {
+    /// The path of the store as given by the app, relative to the app data directory unless it is absolute.
+    pub path: PathBuf,
+    /// The number of keys in the store.
+    pub keys: usize,
+    /// The size of the store on disk in bytes, `None` if it was never saved.
+    pub file_size: Option<u64>,
+    /// One of `json`, `envelope`, `sharded` or `custom`.
+    pub format: &'static str,
+    pub lazy_load: bool,
+    pub change_log: bool,
+    pub journal: bool,
+    /// The store's [`SaveStrategy`](crate::SaveStrategy) in its debug representation.
+    pub save_strategy: String,
+    pub unsaved_changes: bool,
+    #[serde(flatten)]
+    pub status: PersistenceStatus,
+}
+
+/// Returns the size of a store file, or the total size of the files of a sharded store.
+fn disk_size(path: &Path) -> io::Result<u64> {
+    let metadata = fs::metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        size += entry?.metadata()?.len();
+    }
+    Ok(size)
+}
+
+impl<R: Runtime> Store<R> {
+    /// Returns the part of [`StoreCollection::diagnostics`] about this store.
+    pub fn diagnostics(&self) -> StoreDiagnostics {
+        let format = if self.shard_separator.is_some() {
+            "sharded"
+        } else if self.custom_format {
+            "custom"
+        } else if self.envelope {
+            "envelope"
+        } else {
+            "json"
+        };
+        StoreDiagnostics {
+            path: self.path.clone(),
+            keys: self.len(),
+            file_size: disk_size(&self.store_path()).ok(),
+            format,
+            lazy_load: self.lazy_load,
+            change_log: self.change_log,
+            journal: self.journal,
+            save_strategy: format!("{:?}", self.save_strategy()),
+            unsaved_changes: self.has_unsaved_changes(),
+            status: self.persistence_status(),
+        }
+    }
+}
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Creates an anonymized report about the loaded stores, e.g. their sizes, formats and the outcome of
+    /// their last save, without any keys or values.
+    pub fn diagnostics(&self) -> Diagnostics {
+        let mut stores: Vec<StoreDiagnostics> = self
+            .stores
+            .lock()
+            .expect("mutex poisoned")
+            .values()
+            .map(Store::diagnostics)
+            .collect();
+        stores.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Diagnostics {
+            plugin_version: env!("CARGO_PKG_VERSION"),
+            generated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or_default(),
+            read_only: self.read_only,
+            stores,
+        }
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Draft layers over a store whose changes are applied at once or dropped, e.g. by settings dialogs with Apply/Cancel.
+
+use crate::{collection_key, ensure_store, Error, JsonValue, StoreCollection};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+};
+use tauri::{AppHandle, Runtime};
+
+/// The staged changes of an edit session opened with [`StoreCollection::begin_edit`].
+pub(crate) struct Edit {
+    path: PathBuf,
+    /// The staged values by key, `None` for deleted keys.
+    changes: BTreeMap<String, Option<JsonValue>>,
+}
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Opens an edit session on the store at `path`, loading the store if needed.
+    ///
+    /// Writes to the session are staged until [`Self::commit_edit`] applies them at once,
+    /// or [`Self::discard_edit`] drops them.
+    pub fn begin_edit(&self, app: &AppHandle<R>, path: &Path) -> Result<u64, Error> {
+        let key = {
+            let mut stores = self.stores.lock().expect("mutex poisoned");
+            ensure_store(app, self, &mut stores, path)?
+        };
+
+        let id = self.next_edit.fetch_add(1, Ordering::Relaxed);
+        self.edits.lock().expect("mutex poisoned").insert(
+            id,
+            Edit {
+                path: key,
+                changes: BTreeMap::new(),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Stages `value` under `key` in an edit session, or the deletion of `key` if `value` is `None`.
+    pub fn edit_set(&self, edit: u64, key: String, value: Option<JsonValue>) -> Result<(), Error> {
+        self.edits
+            .lock()
+            .expect("mutex poisoned")
+            .get_mut(&edit)
+            .ok_or(Error::EditNotFound(edit))?
+            .changes
+            .insert(key, value);
+        Ok(())
+    }
+
+    /// Returns the value of `key` as seen by an edit session: the staged value if there is one,
+    /// otherwise the one in the store.
+    pub fn edit_get(&self, edit: u64, key: &str) -> Result<Option<JsonValue>, Error> {
+        let edits = self.edits.lock().expect("mutex poisoned");
+        let edit = edits.get(&edit).ok_or(Error::EditNotFound(edit))?;
+        if let Some(staged) = edit.changes.get(key) {
+            return Ok(staged.clone());
+        }
+
+        let stores = self.stores.lock().expect("mutex poisoned");
+        let store = stores
+            .get(&collection_key(&edit.path))
+            .ok_or_else(|| Error::NotFound(edit.path.clone()))?;
+        Ok(store.get(key).cloned())
+    }
+
+    /// Applies the staged changes of an edit session to its store at once and closes the session.
+    ///
+    /// Listeners receive a single `store://batch` event instead of one change event per key. `window` is
+    /// reported as the origin of the changes, like for writes made by the frontend.
+    pub fn commit_edit(&self, edit: u64, window: Option<&str>) -> Result<(), Error> {
+        let edit = self
+            .edits
+            .lock()
+            .expect("mutex poisoned")
+            .remove(&edit)
+            .ok_or(Error::EditNotFound(edit))?;
+
+        let mut stores = self.stores.lock().expect("mutex poisoned");
+        let store = stores
+            .get_mut(&collection_key(&edit.path))
+            .ok_or_else(|| Error::NotFound(edit.path.clone()))?;
+        match window {
+            Some(window) => {
+                store.with_origin(window, None, |store| store.apply_batch(edit.changes))
+            }
+            None => store.apply_batch(edit.changes),
+        }?;
+        if let Err(err) = store.save_if_due() {
+            store.report_error("save", err);
+        }
+        Ok(())
+    }
+
+    /// Drops the staged changes of an edit session and closes it, returning `true` if it was open.
+    pub fn discard_edit(&self, edit: u64) -> bool {
+        self.edits
+            .lock()
+            .expect("mutex poisoned")
+            .remove(&edit)
+            .is_some()
+    }
+}
@@ -0,0 +1,328 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Encryption of individual values, set up with [`StoreBuilder::encrypt_keys`](crate::StoreBuilder::encrypt_keys).
+
+use crate::{
+    atomic::write_atomic,
+    envelope,
+    hex::{from_hex, to_hex},
+    repair::backup_path,
+    signature::signature_path,
+    verify::quarantine_path,
+    Error, JsonValue, KeyFilter, Store,
+};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use log::warn;
+use std::{collections::HashMap, fs, io, path::Path};
+use subtle::ConstantTimeEq;
+use tauri::Runtime;
+
+/// The field of the object an encrypted value is replaced with in the store file.
+const ENCRYPTED_FIELD: &str = "$encrypted";
+const NONCE_LEN: usize = 24;
+
+/// Encrypts the values of the keys selected by a filter with a data key supplied by the app.
+#[derive(Clone)]
+pub(crate) struct KeyEncryption {
+    filter: KeyFilter,
+    data_key: Key,
+}
+
+/// Whether `value` was produced by [`KeyEncryption::encrypt`].
+fn is_sealed(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Object(map) => {
+            map.len() == 1 && map.get(ENCRYPTED_FIELD).map_or(false, JsonValue::is_string)
+        }
+        _ => false,
+    }
+}
+
+/// Reads the file at `path`, returning `None` if it doesn't exist.
+fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>, Error> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Puts back the file at `path` read with [`read_if_exists`], removing it if it didn't exist.
+fn restore(path: &Path, previous: Option<Vec<u8>>) -> Result<(), Error> {
+    match previous {
+        Some(bytes) => Ok(write_atomic(path, &bytes)?),
+        None => match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        },
+    }
+}
+
+impl KeyEncryption {
+    pub(crate) fn new(filter: KeyFilter, data_key: [u8; 32]) -> Self {
+        Self {
+            filter,
+            data_key: *Key::from_slice(&data_key),
+        }
+    }
+
+    /// Whether the value of `key` is stored encrypted.
+    pub(crate) fn applies(&self, key: &str) -> bool {
+        self.filter.matches(key)
+    }
+
+    /// Wraps the value of `key` into an object holding its ciphertext.
+    ///
+    /// The key is authenticated along with the value, so encrypted values can't be moved to another key.
+    pub(crate) fn encrypt(&self, key: &str, value: &JsonValue) -> Result<JsonValue, Error> {
+        let mut nonce = [0; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).map_err(|err| Error::Encryption(err.to_string()))?;
+        let plaintext = serde_json::to_vec(value)?;
+        let ciphertext = XChaCha20Poly1305::new(&self.data_key)
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: key.as_bytes(),
+                },
+            )
+            .map_err(|err| Error::Encryption(err.to_string()))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(JsonValue::Object(
+            [(ENCRYPTED_FIELD.to_string(), to_hex(&sealed).into())]
+                .into_iter()
+                .collect(),
+        ))
+    }
+
+    /// Re-encrypts a value encrypted with this data key with the one of `new`, passing other values through.
+    fn rotate(&self, new: &KeyEncryption, key: &str, value: JsonValue) -> Result<JsonValue, Error> {
+        if !is_sealed(&value) {
+            return Ok(value);
+        }
+        new.encrypt(key, &self.decrypt(key, value)?)
+    }
+
+    /// Unwraps a value produced by [`Self::encrypt`], passing plaintext values through, e.g. a secret
+    /// that was stored before its key was marked as encrypted.
+    pub(crate) fn decrypt(&self, key: &str, value: JsonValue) -> Result<JsonValue, Error> {
+        let sealed = match &value {
+            JsonValue::Object(map) if map.len() == 1 => match map.get(ENCRYPTED_FIELD) {
+                Some(JsonValue::String(sealed)) => sealed,
+                _ => return Ok(value),
+            },
+            _ => return Ok(value),
+        };
+        let invalid = || Error::Encryption(format!("invalid encrypted value for key \"{key}\""));
+        let sealed = from_hex(sealed).ok_or_else(invalid)?;
+        if sealed.len() < NONCE_LEN {
+            return Err(invalid());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = XChaCha20Poly1305::new(&self.data_key)
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: key.as_bytes(),
+                },
+            )
+            .map_err(|_| {
+                Error::Encryption(format!(
+                    "wrong data key or tampered value for key \"{key}\""
+                ))
+            })?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+}
+
+impl<R: Runtime> Store<R> {
+    /// Re-encrypts the keys selected by [`StoreBuilder::encrypt_keys`](crate::StoreBuilder::encrypt_keys) with
+    /// `new_key` and saves the store, which also empties its change log.
+    ///
+    /// Fails if `old_key` isn't the store's current data key, compared in constant time. If saving fails,
+    /// the previous store file and its signature are restored and the store keeps using `old_key`.
+    ///
+    /// The copies of the store file kept by [`Self::repair`] and
+    /// [`StoreCollection::verify_all`](crate::StoreCollection::verify_all) are re-encrypted as well if they can
+    /// be parsed. Archives written by [`StoreCollection::backup_all`](crate::StoreCollection::backup_all) hold
+    /// decrypted values protected by their passphrase and aren't affected.
+    pub fn rotate_key(&mut self, old_key: [u8; 32], new_key: [u8; 32]) -> Result<(), Error> {
+        self.check_writable()?;
+        let old = self
+            .encryption
+            .clone()
+            .ok_or_else(|| Error::Encryption("the store has no encrypted keys".into()))?;
+        if !bool::from(old.data_key.as_slice().ct_eq(&old_key)) {
+            return Err(Error::Encryption("wrong data key".into()));
+        }
+
+        let store_path = self.store_path();
+        let signature_path = signature_path(&store_path);
+        // the shards of a sharded store are written one by one and can't be restored
+        let previous = match self.shard_separator {
+            Some(_) => None,
+            None => Some((
+                read_if_exists(&store_path)?,
+                read_if_exists(&signature_path)?,
+            )),
+        };
+        let new = KeyEncryption {
+            data_key: *Key::from_slice(&new_key),
+            ..old.clone()
+        };
+        self.encryption = Some(new.clone());
+        if let Err(err) = self.save() {
+            if let Some((file, signature)) = previous {
+                let restored =
+                    restore(&store_path, file).and_then(|()| restore(&signature_path, signature));
+                if let Err(err) = restored {
+                    warn!("Failed to restore store {:?}: {}", store_path, err);
+                }
+            }
+            self.encryption = Some(old);
+            return Err(err);
+        }
+
+        for backup in [backup_path(&store_path), quarantine_path(&store_path)] {
+            if let Err(err) = self.rotate_backup(&backup, &old, &new) {
+                warn!(
+                    "Failed to re-encrypt {:?}, it stays encrypted with the previous data key: {}",
+                    backup, err
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-encrypts the values of a copy of the store file encrypted with `old` for `new`.
+    fn rotate_backup(
+        &self,
+        path: &Path,
+        old: &KeyEncryption,
+        new: &KeyEncryption,
+    ) -> Result<(), Error> {
+        let Some(bytes) = read_if_exists(path)? else {
+            return Ok(());
+        };
+        let (values, meta) = envelope::unwrap(self.deserialize_file(&bytes)?)?;
+        let values: HashMap<String, JsonValue> = values
+            .into_iter()
+            .map(|(key, value)| {
+                let value = if old.applies(&key) {
+                    old.rotate(new, &key, value)?
+                } else {
+                    value
+                };
+                Ok((key, value))
+            })
+            .collect::<Result<_, Error>>()?;
+        let contents = match meta {
+            Some(meta) => envelope::wrap(&values, meta.keys)?,
+            None => values,
+        };
+        let bytes = (self.serialize)(&contents).map_err(Error::Serialize)?;
+        Ok(write_atomic(path, &bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn encryption(data_key: [u8; 32]) -> KeyEncryption {
+        KeyEncryption::new(KeyFilter::default().include("auth.*"), data_key)
+    }
+
+    #[test]
+    fn round_trip() {
+        let encryption = encryption([7; 32]);
+        let value = json!({ "token": "secret", "expires": 3600 });
+        let sealed = encryption.encrypt("auth.session", &value).unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.to_string().contains("secret"));
+        assert_eq!(encryption.decrypt("auth.session", sealed).unwrap(), value);
+    }
+
+    #[test]
+    fn nonces_differ() {
+        let encryption = encryption([7; 32]);
+        let a = encryption.encrypt("auth.token", &json!("secret")).unwrap();
+        let b = encryption.encrypt("auth.token", &json!("secret")).unwrap();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn value_is_bound_to_its_key() {
+        let encryption = encryption([7; 32]);
+        let sealed = encryption.encrypt("auth.token", &json!("secret")).unwrap();
+        assert!(matches!(
+            encryption.decrypt("auth.refresh", sealed),
+            Err(Error::Encryption(_))
+        ));
+    }
+
+    #[test]
+    fn wrong_data_key_fails() {
+        let sealed = encryption([7; 32])
+            .encrypt("auth.token", &json!("secret"))
+            .unwrap();
+        assert!(matches!(
+            encryption([8; 32]).decrypt("auth.token", sealed),
+            Err(Error::Encryption(_))
+        ));
+    }
+
+    #[test]
+    fn tampered_value_fails() {
+        let encryption = encryption([7; 32]);
+        let sealed = encryption.encrypt("auth.token", &json!("secret")).unwrap();
+        let hex = sealed[ENCRYPTED_FIELD].as_str().unwrap();
+        let last = if hex.ends_with('0') { "1" } else { "0" };
+        let tampered = json!({ ENCRYPTED_FIELD: format!("{}{last}", &hex[..hex.len() - 1]) });
+        assert!(matches!(
+            encryption.decrypt("auth.token", tampered),
+            Err(Error::Encryption(_))
+        ));
+        let truncated = json!({ ENCRYPTED_FIELD: &hex[..NONCE_LEN] });
+        assert!(matches!(
+            encryption.decrypt("auth.token", truncated),
+            Err(Error::Encryption(_))
+        ));
+    }
+
+    #[test]
+    fn plaintext_passes_through() {
+        let encryption = encryption([7; 32]);
+        for value in [
+            json!("plain"),
+            json!({ "token": "secret" }),
+            json!({ ENCRYPTED_FIELD: 1 }),
+        ] {
+            assert_eq!(
+                encryption.decrypt("auth.token", value.clone()).unwrap(),
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn rotate_re_encrypts() {
+        let (old, new) = (encryption([7; 32]), encryption([8; 32]));
+        let sealed = old.encrypt("auth.token", &json!("secret")).unwrap();
+        let rotated = old.rotate(&new, "auth.token", sealed).unwrap();
+        assert_eq!(new.decrypt("auth.token", rotated).unwrap(), json!("secret"));
+        assert_eq!(
+            old.rotate(&new, "auth.token", json!("plain")).unwrap(),
+            json!("plain")
+        );
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! The optional on-disk envelope `{ "meta": {...}, "data": {...} }` written by stores built with
+//! [`StoreBuilder::envelope`](crate::StoreBuilder::envelope).
+
+use crate::{Error, JsonValue, ValueType};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::collections::HashMap;
+
+/// The version of the envelope format, stored as `meta.format`.
+const FORMAT: u32 = 1;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct EnvelopeMeta {
+    /// Required, so flat stores that happen to have `meta` and `data` keys aren't mistaken for envelopes.
+    format: u32,
+    #[serde(default)]
+    pub(crate) keys: HashMap<String, KeyMeta>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct KeyMeta {
+    /// Milliseconds since the Unix epoch at which the value was last changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) updated_at: Option<u64>,
+    /// Milliseconds since the Unix epoch at which the key expires.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) expires_at: Option<u64>,
+    #[serde(rename = "type")]
+    pub(crate) value_type: ValueType,
+}
+
+/// Separates the metadata from the data if `map` is an envelope.
+fn split<V>(
+    map: &mut HashMap<String, V>,
+    parse_meta: impl FnOnce(&V) -> Option<EnvelopeMeta>,
+) -> Option<(EnvelopeMeta, V)> {
+    if map.len() != 2 || !map.contains_key("data") {
+        return None;
+    }
+    let meta = parse_meta(map.get("meta")?)?;
+    Some((meta, map.remove("data")?))
+}
+
+/// Unwraps a deserialized store file, returning its metadata if it is an envelope.
+pub(crate) fn unwrap(
+    mut map: HashMap<String, JsonValue>,
+) -> Result<(HashMap<String, JsonValue>, Option<EnvelopeMeta>), Error> {
+    match split(&mut map, |meta| EnvelopeMeta::deserialize(meta).ok()) {
+        Some((meta, data)) => Ok((HashMap::deserialize(data)?, Some(meta))),
+        None => Ok((map, None)),
+    }
+}
+
+/// Like [`unwrap`], for stores loaded lazily.
+pub(crate) fn unwrap_raw(
+    mut map: HashMap<String, Box<RawValue>>,
+) -> Result<(HashMap<String, Box<RawValue>>, Option<EnvelopeMeta>), Error> {
+    match split(&mut map, |meta| serde_json::from_str(meta.get()).ok()) {
+        Some((meta, data)) => Ok((serde_json::from_str(data.get())?, Some(meta))),
+        None => Ok((map, None)),
+    }
+}
+
+/// Wraps the contents of a store into an envelope.
+pub(crate) fn wrap(
+    data: &HashMap<String, JsonValue>,
+    keys: HashMap<String, KeyMeta>,
+) -> Result<HashMap<String, JsonValue>, Error> {
+    let meta = EnvelopeMeta {
+        format: FORMAT,
+        keys,
+    };
+    Ok(HashMap::from([
+        ("meta".to_string(), serde_json::to_value(meta)?),
+        ("data".to_string(), serde_json::to_value(data)?),
+    ]))
+}
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
+use crate::ValueType;
 use serde::{Serialize, Serializer};
 use std::path::PathBuf;
 
@@ -22,6 +23,100 @@ pub enum Error {
     /// Store not found
     #[error("Store \"{0}\" not found")]
     NotFound(PathBuf),
+    /// A value did not match the type registered in the store's schema
+    #[error("Type mismatch for key \"{key}\": expected {expected}, found {found}")]
+    TypeMismatch {
+        key: String,
+        expected: ValueType,
+        found: ValueType,
+    },
+    /// A `before_save` hook cancelled saving the store
+    #[error("Saving store \"{0}\" was cancelled")]
+    SaveCancelled(PathBuf),
+    /// An imported key already exists in the store
+    #[error("Key \"{0}\" already exists in the store")]
+    ImportConflict(String),
+    /// Creating or restoring a backup archive failed
+    #[error("Backup failed: {0}")]
+    Backup(String),
+    /// No preset with the given name was registered
+    #[error("Preset \"{0}\" not found")]
+    PresetNotFound(String),
+    /// A key did not reach the awaited state in time
+    #[error("Timed out waiting for key \"{0}\"")]
+    Timeout(String),
+    /// The store is in single-writer mode and the modifying context doesn't hold the writer token
+    #[error("Only the writer of store \"{0}\" may modify it")]
+    NotWriter(PathBuf),
+    /// The store was opened read-only because another instance of the app owns it
+    #[error("Store \"{0}\" is read-only")]
+    ReadOnly(PathBuf),
+    /// The store path is a symbolic link and the store's policy refuses to use it
+    #[error("Store \"{0}\" is a symbolic link")]
+    Symlink(PathBuf),
+    /// The store path doesn't end in the extension of the store's format
+    #[error("Store \"{path}\" doesn't have the extension \".{expected}\"")]
+    ExtensionMismatch { path: PathBuf, expected: String },
+    /// A store is already open at the path of a store being registered
+    #[error("Store \"{0}\" already exists")]
+    AlreadyExists(PathBuf),
+    /// A store that can be modified is already open at the path
+    #[error("Store \"{0}\" is already open for writing")]
+    AlreadyOpen(PathBuf),
+    /// No index with the given name was declared for the store
+    #[error("Index \"{0}\" not found")]
+    IndexNotFound(String),
+    /// The store couldn't be saved because the disk is full
+    #[error("Not enough disk space to save store \"{0}\"")]
+    DiskFull(PathBuf),
+    /// The store wasn't built with full-text search, or the `search` feature is disabled
+    #[error("Full-text search is not enabled for store \"{0}\"")]
+    SearchDisabled(PathBuf),
+    /// No cursor with the given id is open
+    #[error("Cursor {0} not found")]
+    CursorNotFound(u64),
+    /// No edit session with the given id is open
+    #[error("Edit session {0} not found")]
+    EditNotFound(u64),
+    /// The key is reserved for the plugin's metadata
+    #[error("Key \"{0}\" is reserved for internal use")]
+    ReservedKey(String),
+    /// The value has the shape the store uses for compressed values
+    #[error("The value of key \"{0}\" can't be stored because it looks like a compressed value")]
+    ReservedValue(String),
+    /// The store was built with options that can't be combined with sharding
+    #[error("Store \"{0}\" can't be sharded: {1}")]
+    Sharding(PathBuf, String),
+    /// The store file doesn't match its signature, e.g. because it was edited by hand
+    #[error("Store \"{0}\" was modified outside of the app")]
+    TamperDetected(PathBuf),
+    /// Encrypting or decrypting the value of a key failed
+    #[error("Encryption failed: {0}")]
+    Encryption(String),
+    /// No system tray menu item with the given id exists
+    #[error("Tray menu item \"{0}\" not found")]
+    TrayItemNotFound(String),
+    /// A deep link couldn't be parsed or asks to write a store or key that isn't allowed
+    #[error("Invalid deep link: {0}")]
+    DeepLink(String),
+    /// A keyboard shortcut couldn't be parsed
+    #[error("Invalid shortcut \"{0}\"")]
+    InvalidShortcut(String),
+    /// A keyboard shortcut is already bound to another action of the keymap
+    #[error("Shortcut \"{shortcut}\" is already bound to \"{action}\"")]
+    ShortcutConflict { shortcut: String, action: String },
+    /// A value isn't a timestamp in RFC 3339 format, or a timestamp can't be represented in it
+    #[error("Invalid datetime {0}")]
+    InvalidDatetime(String),
+    /// The id the OS assigns to the machine couldn't be read
+    #[error("Failed to read the machine id: {0}")]
+    MachineId(String),
+    /// A compressed value couldn't be decompressed
+    #[error("Failed to decompress value: {0}")]
+    Compression(String),
+    /// A corrupt store file couldn't be repaired
+    #[error("Failed to repair store: {0}")]
+    Repair(String),
     /// Some Tauri API failed
     #[error(transparent)]
     Tauri(#[from] tauri::Error),
@@ -0,0 +1,138 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    atomic::write_atomic,
+    envelope::{self, EnvelopeMeta, KeyMeta},
+    store::{default_serialize, DeserializeFn, SerializeFn},
+    Clock, Error, JsonValue, SystemClock,
+};
+use std::{collections::HashMap, fs, io, path::PathBuf};
+
+/// A store file opened without a running app, see [`open_store_file`].
+#[derive(Clone)]
+pub struct StoreFile {
+    path: PathBuf,
+    serialize: SerializeFn,
+    contents: HashMap<String, JsonValue>,
+    /// The metadata of a file saved with [`StoreBuilder::envelope`](crate::StoreBuilder::envelope),
+    /// written back around the contents on save.
+    meta: Option<EnvelopeMeta>,
+}
+
+/// Opens the store file at the absolute path `path` without an [`AppHandle`](tauri::AppHandle).
+///
+/// Lets apps build a `myapp config get/set` CLI or tooling that reads and writes the same files as
+/// the plugin. A missing file is treated as an empty store. Stores are located in the app data directory,
+/// which a CLI can resolve with [`tauri::api::path::app_data_dir`].
+///
+/// The file is overwritten when a running app saves the same store, so prefer editing it while the app is closed.
+/// Values compressed with [`StoreBuilder::compress_values_above`](crate::StoreBuilder::compress_values_above)
+/// are returned and kept as they are stored. Files saved with [`StoreBuilder::envelope`](crate::StoreBuilder::envelope)
+/// keep their envelope, with the metadata of changed keys updated.
+///
+/// # Examples
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use tauri_plugin_store::open_store_file;
+///
+/// let mut store = open_store_file("/home/user/.local/share/com.example.app/settings.json", |bytes| {
+///   serde_json::from_slice(bytes).map_err(Into::into)
+/// })?;
+///
+/// println!("{:?}", store.get("theme"));
+/// store.insert("theme".to_string(), "dark".into());
+/// store.save()?;
+///
+/// # Ok(())
+/// # }
+/// ```
+pub fn open_store_file(
+    path: impl Into<PathBuf>,
+    deserialize: DeserializeFn,
+) -> Result<StoreFile, Error> {
+    let path = path.into();
+    let (contents, meta) = match fs::read(&path) {
+        Ok(bytes) => envelope::unwrap(deserialize(&bytes).map_err(Error::Deserialize)?)?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => (HashMap::new(), None),
+        Err(err) => return Err(err.into()),
+    };
+
+    Ok(StoreFile {
+        path,
+        serialize: default_serialize,
+        contents,
+        meta,
+    })
+}
+
+impl StoreFile {
+    /// Sets the function used by [`Self::save`], which must match the store's
+    /// [`StoreBuilder::serialize`](crate::StoreBuilder::serialize). Defaults to JSON.
+    pub fn serialize(mut self, serialize: SerializeFn) -> Self {
+        self.serialize = serialize;
+        self
+    }
+
+    pub fn get(&self, key: impl AsRef<str>) -> Option<&JsonValue> {
+        self.contents.get(key.as_ref())
+    }
+
+    pub fn insert(&mut self, key: String, value: JsonValue) {
+        if let Some(meta) = &mut self.meta {
+            // like a store, inserting clears the key's expiration
+            let key_meta = KeyMeta {
+                updated_at: Some(SystemClock.now_millis()),
+                expires_at: None,
+                value_type: (&value).into(),
+            };
+            meta.keys.insert(key.clone(), key_meta);
+        }
+        self.contents.insert(key, value);
+    }
+
+    pub fn delete(&mut self, key: impl AsRef<str>) -> bool {
+        if let Some(meta) = &mut self.meta {
+            meta.keys.remove(key.as_ref());
+        }
+        self.contents.remove(key.as_ref()).is_some()
+    }
+
+    pub fn has(&self, key: impl AsRef<str>) -> bool {
+        self.contents.contains_key(key.as_ref())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.contents.keys()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &JsonValue)> {
+        self.contents.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.contents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_empty()
+    }
+
+    /// Writes the contents back to the file.
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let bytes = match &self.meta {
+            Some(meta) => {
+                let contents = envelope::wrap(&self.contents, meta.keys.clone())?;
+                (self.serialize)(&contents)
+            }
+            None => (self.serialize)(&self.contents),
+        }
+        .map_err(Error::Serialize)?;
+        write_atomic(&self.path, &bytes)?;
+        Ok(())
+    }
+}
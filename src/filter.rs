@@ -0,0 +1,77 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use serde::Deserialize;
+
+/// Selects keys by glob patterns, where `*` matches any sequence of characters and `?` matches a single one.
+///
+/// A key is selected if it matches at least one `include` pattern (or `include` is empty)
+/// and no `exclude` pattern.
+///
+/// # Examples
+/// ```
+/// use tauri_plugin_store::KeyFilter;
+///
+/// let filter = KeyFilter::default().exclude("auth.*");
+///
+/// assert!(filter.matches("theme"));
+/// assert!(!filter.matches("auth.token"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct KeyFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl KeyFilter {
+    /// Adds an include pattern.
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Adds an exclude pattern.
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Returns `true` if `key` is selected by this filter.
+    pub fn matches(&self, key: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|pattern| glob_match(pattern, key)))
+            && !self.exclude.iter().any(|pattern| glob_match(pattern, key))
+    }
+}
+
+/// Matches `text` against a glob `pattern` supporting `*` and `?`.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` in the pattern and the text position it was tried at
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, star_t)) => {
+                    p = star + 1;
+                    t = star_t + 1;
+                    backtrack = Some((star, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
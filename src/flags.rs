@@ -0,0 +1,114 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    client_id::CLIENT_ID_KEY, ensure_store, store::fnv1a, ClientIdScope, Error, JsonValue, Store,
+    StoreCollection,
+};
+use log::warn;
+use semver::Version;
+use serde::Deserialize;
+use std::path::Path;
+use tauri::{AppHandle, Runtime};
+
+/// The rules of a feature flag stored as an object.
+///
+/// A flag may also be stored as a plain boolean.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FlagRules {
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    /// Percentage (0-100) of clients the flag is enabled for.
+    rollout: Option<f64>,
+    /// Operating systems as reported by [`std::env::consts::OS`].
+    #[serde(default)]
+    platforms: Vec<String>,
+    /// Inclusive lower bound of the app version.
+    min_version: Option<Version>,
+    /// Exclusive upper bound of the app version.
+    max_version: Option<Version>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl<R: Runtime> Store<R> {
+    /// Evaluates the feature flag stored under `name`.
+    ///
+    /// A flag is either a boolean or an object with the following optional rules, all of which must pass:
+    ///
+    /// - `enabled`: turns the flag off regardless of the other rules.
+    /// - `rollout`: percentage of clients the flag is enabled for, bucketed by a stable client id.
+    /// - `platforms`: list of operating systems, e.g. `["macos", "windows"]`.
+    /// - `minVersion` / `maxVersion`: app version range, lower bound inclusive and upper bound exclusive.
+    ///
+    /// Missing flags are disabled. Rollouts are bucketed by `client_id`, and disabled without one.
+    /// [`StoreCollection::flag`] evaluates flags with the app's client id.
+    pub fn flag(&self, name: &str, client_id: Option<&str>) -> Result<bool, Error> {
+        let rules = match self.get(name) {
+            None => return Ok(false),
+            Some(JsonValue::Bool(enabled)) => return Ok(*enabled),
+            Some(value) => FlagRules::deserialize(value)?,
+        };
+
+        if !rules.enabled {
+            return Ok(false);
+        }
+        if !rules.platforms.is_empty()
+            && !rules
+                .platforms
+                .iter()
+                .any(|platform| platform == std::env::consts::OS)
+        {
+            return Ok(false);
+        }
+
+        let version = self.host.app_version();
+        if rules.min_version.map_or(false, |min| version < min)
+            || rules.max_version.map_or(false, |max| version >= max)
+        {
+            return Ok(false);
+        }
+
+        if let Some(rollout) = rules.rollout {
+            let Some(client_id) = client_id else {
+                return Ok(false);
+            };
+            let bucket = fnv1a(format!("{}:{}", client_id, name).as_bytes()) % 100;
+            return Ok((bucket as f64) < rollout);
+        }
+
+        Ok(true)
+    }
+}
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Evaluates the feature flag `name` of the store at `path`, see [`Store::flag`].
+    ///
+    /// Rollouts are bucketed by the app's [`ClientIdScope::User`] client id, or by the id stored under
+    /// [`CLIENT_ID_KEY`] in the store itself by earlier versions, so clients keep their buckets.
+    /// The store isn't modified. If the client id can't be read, rollouts are disabled.
+    pub fn flag(&self, app: &AppHandle<R>, path: &Path, name: &str) -> Result<bool, Error> {
+        let legacy_id = {
+            let mut stores = self.stores.lock().expect("mutex poisoned");
+            let key = ensure_store(app, self, &mut stores, path)?;
+            match stores[&key].get(CLIENT_ID_KEY) {
+                Some(JsonValue::String(id)) => Some(id.clone()),
+                _ => None,
+            }
+        };
+        // read without holding the collection, the app's client id is kept in another store
+        let client_id = legacy_id.or_else(|| {
+            self.client_id(app, None, ClientIdScope::User)
+                .map_err(|err| warn!("Failed to read the client id for flag {:?}: {}", name, err))
+                .ok()
+        });
+
+        let mut stores = self.stores.lock().expect("mutex poisoned");
+        let key = ensure_store(app, self, &mut stores, path)?;
+        stores[&key].flag(name, client_id.as_deref())
+    }
+}
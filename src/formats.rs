@@ -0,0 +1,49 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Deserializers of the file formats recognized by [`FileFormat::detect`](crate::FileFormat::detect), see
+//! [`FileFormat::deserializer`](crate::FileFormat::deserializer).
+
+#[cfg(any(feature = "compression", feature = "zstd"))]
+use crate::FileFormat;
+use crate::JsonValue;
+#[cfg(feature = "compression")]
+use flate2::read::GzDecoder;
+#[cfg(feature = "compression")]
+use std::io::Read;
+use std::{collections::HashMap, error::Error};
+
+type DeserializeResult = Result<HashMap<String, JsonValue>, Box<dyn Error + Send + Sync>>;
+
+/// Deserializes decompressed contents, detecting their format.
+#[cfg(any(feature = "compression", feature = "zstd"))]
+fn deserialize_decompressed(bytes: &[u8]) -> DeserializeResult {
+    let format = FileFormat::detect(bytes).ok_or("unrecognized format of compressed contents")?;
+    let deserialize = format
+        .deserializer()
+        .ok_or_else(|| format!("compressed {format:?} contents aren't supported"))?;
+    deserialize(bytes)
+}
+
+#[cfg(feature = "compression")]
+pub(crate) fn gzip_deserialize(bytes: &[u8]) -> DeserializeResult {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+    deserialize_decompressed(&decompressed)
+}
+
+#[cfg(feature = "zstd")]
+pub(crate) fn zstd_deserialize(bytes: &[u8]) -> DeserializeResult {
+    deserialize_decompressed(&zstd::decode_all(bytes)?)
+}
+
+#[cfg(feature = "toml")]
+pub(crate) fn toml_deserialize(bytes: &[u8]) -> DeserializeResult {
+    Ok(toml::from_str(std::str::from_utf8(bytes)?)?)
+}
+
+#[cfg(feature = "msgpack")]
+pub(crate) fn msgpack_deserialize(bytes: &[u8]) -> DeserializeResult {
+    Ok(rmp_serde::from_slice(bytes)?)
+}
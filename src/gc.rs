@@ -0,0 +1,147 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Cleanup of store files that no store of the app refers to anymore.
+
+use crate::{
+    collection_key, envelope, mirror::change_log_path, signature::signature_path, Error,
+    StoreCollection, EXPIRATIONS_KEY, META_KEY,
+};
+use serde_json::value::RawValue;
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+use tauri::{AppHandle, Runtime};
+
+/// Collects the files under `dir` recursively, skipping symbolic links.
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Whether the file at `path` was written by this plugin: it has a change log or a signature next to it,
+/// or it is a JSON object with the plugin's metadata or an envelope.
+///
+/// Flat stores without any metadata can't be told apart from other files, so they are never considered.
+fn is_store_file(path: &Path) -> bool {
+    if change_log_path(path).is_file() || signature_path(path).is_file() {
+        return true;
+    }
+    let Ok(bytes) = fs::read(path) else {
+        return false;
+    };
+    match serde_json::from_slice::<HashMap<String, Box<RawValue>>>(&bytes) {
+        Ok(map) if map.contains_key(META_KEY) || map.contains_key(EXPIRATIONS_KEY) => true,
+        Ok(map) => matches!(envelope::unwrap_raw(map), Ok((_, Some(_)))),
+        Err(_) => false,
+    }
+}
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Finds store files in the app data directory that belong neither to a store of the collection nor to
+    /// one of `allowed_paths`, and removes them unless `dry_run` is set.
+    ///
+    /// Store files are files with the extension of a store of the collection or `json` that were identifiably written
+    /// by this plugin, see [`is_store_file`]; other files, e.g. the settings of another library, are never removed.
+    /// Files next to an orphaned store file whose name extends its name, e.g. its change log, are orphaned as well.
+    /// Stores opened on demand by the frontend are only known once opened, so pass their paths in `allowed_paths`.
+    ///
+    /// Returns the orphaned files, relative to the app data directory.
+    pub fn gc(
+        &self,
+        app: &AppHandle<R>,
+        allowed_paths: &[PathBuf],
+        dry_run: bool,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let app_dir = app
+            .path_resolver()
+            .app_data_dir()
+            .expect("failed to resolve app dir");
+        if !app_dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut known = allowed_paths
+            .iter()
+            .map(|path| collection_key(&app_dir.join(path)))
+            .collect::<Vec<_>>();
+        let mut extensions = HashSet::from([OsStr::new("json").to_os_string()]);
+        for store in self.stores.lock().expect("mutex poisoned").values() {
+            let path = store.store_path();
+            if let Some(extension) = path.extension() {
+                extensions.insert(extension.to_os_string());
+            }
+            known.push(collection_key(&path));
+            known.extend(store.legacy_path().as_deref().map(collection_key));
+        }
+        // a sharded store is a directory, so everything below a known path is known
+        let is_known = |path: &Path| {
+            let path = collection_key(path);
+            known.iter().any(|known| path.starts_with(known))
+        };
+
+        let mut files = Vec::new();
+        walk(&app_dir, &mut files)?;
+        let orphans = files
+            .iter()
+            .filter(|path| {
+                path.extension()
+                    .map_or(false, |extension| extensions.contains(extension))
+                    && !is_known(path.as_path())
+                    && is_store_file(path)
+            })
+            .collect::<HashSet<_>>();
+        let is_sidecar = |path: &Path| {
+            let (Some(dir), Some(name)) = (path.parent(), path.file_name().and_then(OsStr::to_str))
+            else {
+                return false;
+            };
+            orphans.iter().any(|orphan| {
+                orphan.parent() == Some(dir)
+                    && orphan
+                        .file_name()
+                        .and_then(OsStr::to_str)
+                        .map_or(false, |orphan| {
+                            name.len() > orphan.len() + 1
+                                && name.starts_with(orphan)
+                                && name.as_bytes()[orphan.len()] == b'.'
+                        })
+            })
+        };
+        let mut removed = files
+            .iter()
+            .filter(|path| {
+                orphans.contains(path) || (is_sidecar(path.as_path()) && !is_known(path.as_path()))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        removed.sort();
+
+        if !dry_run {
+            for path in &removed {
+                fs::remove_file(path)?;
+            }
+        }
+
+        Ok(removed
+            .into_iter()
+            .map(|path| {
+                path.strip_prefix(&app_dir)
+                    .map(Path::to_path_buf)
+                    .unwrap_or(path)
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{Error, JsonValue};
+use semver::Version;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Runtime};
+
+/// The environment a [`Store`](crate::Store) lives in: where its file is located and who receives its events.
+///
+/// Implemented for [`AppHandle`]. [`DirectoryHost`] lets the store core run outside of a Tauri app,
+/// e.g. in integration tests, CLIs and migration scripts.
+pub trait StoreHost: Send + Sync {
+    /// Resolves the path a store was created with to the absolute path of its file.
+    fn resolve_store_path(&self, path: &Path) -> PathBuf;
+
+    /// Delivers a store event, e.g. a change event, to the frontend and Rust listeners.
+    fn emit_event(&self, event: &str, payload: JsonValue) -> Result<(), Error>;
+
+    /// The version of the app, used by the version rules of feature flags.
+    fn app_version(&self) -> Version;
+}
+
+impl<R: Runtime> StoreHost for AppHandle<R> {
+    fn resolve_store_path(&self, path: &Path) -> PathBuf {
+        let app_dir = self
+            .path_resolver()
+            .app_data_dir()
+            .expect("failed to resolve app dir");
+        app_dir.join(path)
+    }
+
+    fn emit_event(&self, event: &str, payload: JsonValue) -> Result<(), Error> {
+        // Rust listeners, e.g. of `listen_to_store_changes`, only receive triggered events
+        self.trigger_global(event, Some(payload.to_string()));
+        self.emit_all(event, payload)?;
+        Ok(())
+    }
+
+    fn app_version(&self) -> Version {
+        self.package_info().version.clone()
+    }
+}
+
+/// A [`StoreHost`] resolving store paths relative to a directory and dropping all events.
+///
+/// # Examples
+/// ```
+/// use tauri_plugin_store::{DirectoryHost, StoreBuilder};
+///
+/// let store = StoreBuilder::<tauri::Wry>::with_host(DirectoryHost::new("fixtures"), "settings.json".into())
+///   .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct DirectoryHost {
+    dir: PathBuf,
+    version: Version,
+}
+
+impl DirectoryHost {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            version: Version::new(0, 0, 0),
+        }
+    }
+
+    /// Sets the app version reported to feature flags, `0.0.0` by default.
+    pub fn version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+}
+
+impl StoreHost for DirectoryHost {
+    fn resolve_store_path(&self, path: &Path) -> PathBuf {
+        self.dir.join(path)
+    }
+
+    fn emit_event(&self, _event: &str, _payload: JsonValue) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn app_version(&self) -> Version {
+        self.version.clone()
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Queries over fields of object values, optionally backed by secondary indexes declared with
+//! [`StoreBuilder::index`](crate::StoreBuilder::index).
+
+use crate::{filter::glob_match, Error, JsonValue, KeyFilter, Store};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use tauri::Runtime;
+
+/// An aggregation computed by [`Store::aggregate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Aggregate {
+    /// The number of numeric fields.
+    Count,
+    /// The sum of the numeric fields.
+    Sum,
+    /// The smallest numeric field.
+    Min,
+    /// The largest numeric field.
+    Max,
+}
+
+#[derive(Clone)]
+pub(crate) struct Index {
+    /// Glob pattern selecting the indexed keys.
+    keys: String,
+    /// JSON pointer to the indexed field within the values.
+    field: String,
+    /// Keys by the serialized value of their field.
+    by_value: HashMap<String, BTreeSet<String>>,
+    /// The serialized field value of every indexed key.
+    by_key: HashMap<String, String>,
+}
+
+impl Index {
+    pub(crate) fn new(keys: String, field: String) -> Self {
+        Self {
+            keys,
+            field,
+            by_value: HashMap::new(),
+            by_key: HashMap::new(),
+        }
+    }
+
+    /// Updates the entry of `key`, with `None` if it was removed.
+    pub(crate) fn update(&mut self, key: &str, value: Option<&JsonValue>) {
+        if !glob_match(&self.keys, key) {
+            return;
+        }
+        if let Some(previous) = self.by_key.remove(key) {
+            if let Some(keys) = self.by_value.get_mut(&previous) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.by_value.remove(&previous);
+                }
+            }
+        }
+        let Some(field) = value.and_then(|value| value.pointer(&self.field)) else {
+            return;
+        };
+        let field = field.to_string();
+        self.by_value
+            .entry(field.clone())
+            .or_default()
+            .insert(key.to_string());
+        self.by_key.insert(key.to_string(), field);
+    }
+
+    fn clear(&mut self) {
+        self.by_value.clear();
+        self.by_key.clear();
+    }
+}
+
+impl<R: Runtime> Store<R> {
+    /// Rebuilds all indexes from the contents of the store, e.g. after it was loaded.
+    pub(crate) fn rebuild_indexes(&mut self) {
+        let mut indexes = std::mem::take(&mut self.indexes);
+        for index in indexes.values_mut() {
+            index.clear();
+            for (key, value) in self.entries() {
+                index.update(key, Some(value));
+            }
+        }
+        self.indexes = indexes;
+        #[cfg(feature = "search")]
+        if let Some(mut search) = self.search.take() {
+            search.clear();
+            for (key, value) in self.entries() {
+                search.update(key, Some(value));
+            }
+            self.search = Some(search);
+        }
+    }
+
+    /// Returns the keys whose indexed field equals `value` in the index registered as `index`, sorted.
+    pub fn find_by(&self, index: &str, value: &JsonValue) -> Result<Vec<&String>, Error> {
+        let index = self
+            .indexes
+            .get(index)
+            .ok_or_else(|| Error::IndexNotFound(index.to_string()))?;
+        Ok(index
+            .by_value
+            .get(&value.to_string())
+            .map(|keys| keys.iter().collect())
+            .unwrap_or_default())
+    }
+
+    /// Returns the entries whose field at the JSON pointer `field` is a number within `min..=max`,
+    /// ordered by that number and then by key. Missing bounds are unbounded.
+    ///
+    /// An empty pointer compares the values themselves, for stores holding plain numbers.
+    pub fn find_range(
+        &self,
+        field: &str,
+        min: Option<f64>,
+        max: Option<f64>,
+    ) -> Vec<(&String, &JsonValue)> {
+        let mut matches = self
+            .entries()
+            .filter_map(|(key, value)| {
+                let number = value.pointer(field)?.as_f64()?;
+                let in_range =
+                    min.map_or(true, |min| number >= min) && max.map_or(true, |max| number <= max);
+                in_range.then_some((number, key, value))
+            })
+            .collect::<Vec<_>>();
+        matches
+            .sort_by(|(a, a_key, _), (b, b_key, _)| a.total_cmp(b).then_with(|| a_key.cmp(b_key)));
+        matches
+            .into_iter()
+            .map(|(_, key, value)| (key, value))
+            .collect()
+    }
+
+    /// Computes `op` over the numbers at the JSON pointer `field` of the values of the keys selected
+    /// by `filter`, skipping values where the field is missing or not a number.
+    ///
+    /// Returns `None` for [`Aggregate::Min`] and [`Aggregate::Max`] if no number was found.
+    ///
+    /// # Examples
+    /// ```
+    /// use tauri_plugin_store::{Aggregate, KeyFilter, Store};
+    ///
+    /// fn total_score<R: tauri::Runtime>(store: &Store<R>) -> f64 {
+    ///   store
+    ///     .aggregate("/score", Aggregate::Sum, &KeyFilter::default().include("games.*"))
+    ///     .unwrap_or_default()
+    /// }
+    /// ```
+    pub fn aggregate(&self, field: &str, op: Aggregate, filter: &KeyFilter) -> Option<f64> {
+        let mut numbers = self
+            .entries()
+            .filter(|(key, _)| filter.matches(key))
+            .filter_map(|(_, value)| value.pointer(field)?.as_f64());
+        match op {
+            Aggregate::Count => Some(numbers.count() as f64),
+            Aggregate::Sum => Some(numbers.sum()),
+            Aggregate::Min => numbers.next().map(|first| numbers.fold(first, f64::min)),
+            Aggregate::Max => numbers.next().map(|first| numbers.fold(first, f64::max)),
+        }
+    }
+}
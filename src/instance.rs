@@ -0,0 +1,68 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use fs2::FileExt;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+const LOCK_FILE: &str = ".store-instance.lock";
+
+/// Marks the app instance that owns the store files, so other instances open them read-only.
+///
+/// The lock file is locked by the operating system for as long as the instance runs, so the lock is released
+/// even if the instance crashes. The file holds the id of the owning process.
+pub(crate) struct InstanceLock {
+    path: PathBuf,
+    file: File,
+}
+
+impl InstanceLock {
+    /// Tries to become the primary instance, returning `None` if another running instance already is.
+    pub(crate) fn acquire(dir: &Path) -> io::Result<Option<Self>> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(LOCK_FILE);
+
+        let mut file = loop {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .open(&path)?;
+            if let Err(err) = file.try_lock_exclusive() {
+                if err.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                    return Ok(None);
+                }
+                return Err(err);
+            }
+            // the previous owner removes the file while holding the lock, so it may be gone by now
+            if path.is_file() {
+                break file;
+            }
+        };
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        write!(file, "{}", process::id())?;
+        file.sync_all()?;
+
+        Ok(Some(Self { path, file }))
+    }
+
+    /// Removes the lock file if this process still owns it, then releases the lock.
+    pub(crate) fn release(&self) -> io::Result<()> {
+        // read through the locked handle, as other handles can't read a locked file on Windows
+        let mut owner = String::new();
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_string(&mut owner)?;
+        // removed while still locked, so no other instance can take over a file that is about to disappear
+        if owner.trim().parse::<u32>().ok() == Some(process::id()) {
+            fs::remove_file(&self.path)?;
+        }
+        self.file.unlock()
+    }
+}
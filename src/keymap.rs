@@ -0,0 +1,144 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{Error, Store};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use tauri::Runtime;
+
+/// The order modifiers appear in normalized shortcuts.
+const MODIFIERS: [&str; 4] = ["Ctrl", "Alt", "Shift", "Super"];
+
+fn modifier(name: &str) -> Option<&'static str> {
+    match name.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => Some("Ctrl"),
+        "alt" | "option" => Some("Alt"),
+        "shift" => Some("Shift"),
+        "super" | "cmd" | "command" | "meta" | "win" => Some("Super"),
+        "cmdorctrl" | "commandorcontrol" | "cmdorcontrol" | "commandorctrl" => {
+            Some(if cfg!(target_os = "macos") {
+                "Super"
+            } else {
+                "Ctrl"
+            })
+        }
+        _ => None,
+    }
+}
+
+fn key(name: &str) -> String {
+    match name.to_ascii_lowercase().as_str() {
+        "esc" | "escape" => "Escape".into(),
+        "return" | "enter" => "Enter".into(),
+        "del" | "delete" => "Delete".into(),
+        "plus" => "+".into(),
+        "space" => "Space".into(),
+        "up" | "arrowup" => "Up".into(),
+        "down" | "arrowdown" => "Down".into(),
+        "left" | "arrowleft" => "Left".into(),
+        "right" | "arrowright" => "Right".into(),
+        lower => {
+            let mut chars = lower.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
+    }
+}
+
+/// Normalizes a shortcut like `cmdorctrl+shift+p` into the canonical form stored by [`Store::keymap_set`],
+/// e.g. `Ctrl+Shift+P`, or `Super+Shift+P` on macOS.
+///
+/// Modifier aliases are unified, `CmdOrCtrl` is resolved for the current platform, modifiers are sorted
+/// and keys are capitalized.
+pub fn normalize_shortcut(shortcut: &str) -> Result<String, Error> {
+    let invalid = || Error::InvalidShortcut(shortcut.to_string());
+
+    let trimmed = shortcut.trim();
+    // `Ctrl++` binds the plus key
+    let (rest, plus) = match trimmed.strip_suffix("++") {
+        Some(rest) => (rest, true),
+        None => (trimmed, false),
+    };
+    let mut modifiers = Vec::new();
+    let mut keys = Vec::new();
+    for part in rest.split('+').map(str::trim) {
+        if part.is_empty() {
+            if plus && rest.is_empty() {
+                continue;
+            }
+            return Err(invalid());
+        }
+        match modifier(part) {
+            Some(modifier) => modifiers.push(modifier),
+            None => keys.push(key(part)),
+        }
+    }
+    if plus {
+        keys.push("+".into());
+    }
+    let [key] = <[String; 1]>::try_from(keys).map_err(|_| invalid())?;
+
+    let mut parts: Vec<String> = MODIFIERS
+        .iter()
+        .filter(|name| modifiers.contains(name))
+        .map(|name| name.to_string())
+        .collect();
+    parts.push(key);
+    Ok(parts.join("+"))
+}
+
+impl<R: Runtime> Store<R> {
+    /// Returns the shortcuts of the keymap stored under the key `keymap`, by action.
+    pub fn keymap(&self, keymap: &str) -> Result<BTreeMap<String, String>, Error> {
+        match self.get(keymap) {
+            Some(value) => Ok(BTreeMap::deserialize(value)?),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+
+    /// Returns the shortcut bound to `action` in the keymap stored under the key `keymap`.
+    pub fn keymap_get(&self, keymap: &str, action: &str) -> Result<Option<String>, Error> {
+        Ok(self.keymap(keymap)?.remove(action))
+    }
+
+    /// Binds `action` to `shortcut` in the keymap stored under the key `keymap`, returning the normalized shortcut,
+    /// see [`normalize_shortcut`].
+    ///
+    /// Fails with [`Error::ShortcutConflict`] if another action is bound to the same shortcut.
+    pub fn keymap_set(
+        &mut self,
+        keymap: &str,
+        action: &str,
+        shortcut: &str,
+    ) -> Result<String, Error> {
+        let shortcut = normalize_shortcut(shortcut)?;
+        let mut bindings = self.keymap(keymap)?;
+        if let Some((other, _)) = bindings
+            .iter()
+            .find(|(other, bound)| *other != action && **bound == shortcut)
+        {
+            return Err(Error::ShortcutConflict {
+                shortcut,
+                action: other.clone(),
+            });
+        }
+        if bindings.get(action) != Some(&shortcut) {
+            bindings.insert(action.to_string(), shortcut.clone());
+            self.insert(keymap.to_string(), serde_json::to_value(bindings)?)?;
+        }
+        Ok(shortcut)
+    }
+
+    /// Unbinds `action` in the keymap stored under the key `keymap`, returning whether it was bound.
+    pub fn keymap_remove(&mut self, keymap: &str, action: &str) -> Result<bool, Error> {
+        let mut bindings = self.keymap(keymap)?;
+        if bindings.remove(action).is_none() {
+            return Ok(false);
+        }
+        self.insert(keymap.to_string(), serde_json::to_value(bindings)?)?;
+        Ok(true)
+    }
+}
@@ -2,35 +2,498 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
+use atomic::write_atomic;
+#[cfg(feature = "backup")]
+pub use backup::BackupManifest;
+pub use cache::CachedValue;
+pub use client_id::{ClientIdScope, CLIENT_ID_KEY, DEFAULT_CLIENT_ID_PATH, MACHINE_ID_KEY};
+pub use clock::{Clock, SystemClock};
+pub use config::TypedConfig;
+use cursor::Cursor;
+pub use deep_link::DeepLinkUpdate;
+use deep_link::DeepLinks;
+pub use diagnostics::{Diagnostics, StoreDiagnostics};
+use edit::Edit;
 pub use error::Error;
+pub use file::{open_store_file, StoreFile};
+pub use filter::KeyFilter;
+pub use host::{DirectoryHost, StoreHost};
+pub use index::Aggregate;
+use instance::InstanceLock;
+pub use keymap::normalize_shortcut;
+pub use lock::LockStatus;
 use log::warn;
-use serde::Serialize;
+pub use mirror::StoreMirror;
+pub use numbers::NumberMode;
+pub use onboarding::ONBOARDING_KEY;
+pub use queue::QueueItem;
+pub use reader::StoreReader;
+pub use repair::RepairReport;
+use replication::Replication;
+use save_queue::SaveQueue;
+pub use scope::ScopedStore;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 pub use serde_json::Value as JsonValue;
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
     path::{Path, PathBuf},
-    sync::Mutex,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc, Mutex, Once,
+    },
+    time::Duration,
+};
+pub use store::{
+    ConflictStrategy, ErrorPolicy, ExtensionPolicy, FileFormat, HistoryEntry, KeyOrder,
+    PatchOperation, PersistenceStatus, SaveStrategy, Store, StoreBuilder, StoreDiff, SymlinkPolicy,
+    ValueChange, ValueType, WatchId, BATCH_EVENT, CHANGE_EVENT, DISK_FULL_EVENT, ERROR_EVENT,
+    EXPIRATIONS_KEY, LOCKED_EVENT, META_KEY, PAYLOAD_SCHEMA, REDACTED, RESET_EVENT, UNLOCKED_EVENT,
+    VERIFY_EVENT,
 };
-pub use store::{Store, StoreBuilder};
 use tauri::{
     plugin::{self, TauriPlugin},
-    AppHandle, Manager, RunEvent, Runtime, State,
+    AppHandle, EventHandler, Manager, RunEvent, Runtime, State, Window, WindowEvent,
 };
+#[cfg(feature = "datetime")]
+pub use time::OffsetDateTime;
+pub use transition::LegacyFormat;
+#[cfg(feature = "tray")]
+pub use tray::TrayBinding;
+pub use verify::{StoreIssue, VerifyReport};
+pub use window_state::WindowState;
+pub use writer::Writer;
 
+mod atomic;
+#[cfg(feature = "backup")]
+mod backup;
+mod cache;
+mod client_id;
+mod clock;
+mod coalesce;
+#[cfg(feature = "compression")]
+mod compression;
+mod config;
+mod counters;
+mod cursor;
+#[cfg(feature = "datetime")]
+pub mod datetime;
+mod deep_link;
+mod diagnostics;
+mod edit;
+#[cfg(feature = "encryption")]
+mod encryption;
+mod envelope;
 mod error;
+mod file;
+mod filter;
+mod flags;
+#[cfg(any(
+    feature = "compression",
+    feature = "zstd",
+    feature = "toml",
+    feature = "msgpack"
+))]
+mod formats;
+mod gc;
+#[cfg(any(feature = "encryption", feature = "signing"))]
+mod hex;
+mod host;
+mod index;
+mod instance;
+mod keymap;
+mod lock;
+mod mirror;
+mod numbers;
+mod obfuscate;
+mod onboarding;
+mod prune;
+mod queue;
+mod reader;
+mod refs;
+mod repair;
+mod replication;
+mod save_queue;
+mod scope;
+#[cfg(feature = "search")]
+mod search;
+mod shard;
+mod signature;
 mod store;
+mod transition;
+#[cfg(feature = "tray")]
+mod tray;
+mod verify;
+mod window_state;
+mod writer;
+
+/// How often stores with a timed [`SaveStrategy`] are checked for due saves.
+const SAVE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+/// How often counters are flushed into their stores unless configured otherwise.
+const COUNTER_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+/// How long focus may take to move to another window of the app before a blur counts as the app losing focus.
+const BLUR_SETTLE_DELAY: Duration = Duration::from_millis(200);
+/// How long `wait_for` waits for a key if the frontend passes no timeout.
+const WAIT_FOR_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The payload of the [`CHANGE_EVENT`] emitted for every changed key.
+///
+/// Borrowed when emitted and owned when received, e.g. by [`listen_to_store_changes`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePayload<'a> {
+    /// The [`PAYLOAD_SCHEMA`] version the payload was emitted with.
+    #[serde(default = "legacy_schema")]
+    pub schema: u32,
+    /// The path of the store, as it was opened.
+    pub path: Cow<'a, Path>,
+    pub key: Cow<'a, str>,
+    /// The new value, `null` if the key was removed.
+    pub value: Cow<'a, JsonValue>,
+    /// The label of the window whose command caused the change.
+    pub window: Option<Cow<'a, str>>,
+    /// The transaction id passed by the frontend with the write, if any.
+    pub txn_id: Option<Cow<'a, str>>,
+}
 
+/// Version 1 of [`PAYLOAD_SCHEMA`], in which change payloads had no `schema` field.
+fn legacy_schema() -> u32 {
+    1
+}
+
+/// The change payload emitted by stores built with [`StoreBuilder::legacy_change_payloads`].
 #[derive(Serialize, Clone)]
-struct ChangePayload<'a> {
+struct LegacyChangePayload<'a> {
     path: &'a Path,
     key: &'a str,
     value: &'a JsonValue,
 }
 
+#[derive(Serialize, Clone)]
+struct ResetPayload<'a> {
+    schema: u32,
+    path: &'a Path,
+    patch: &'a [PatchOperation],
+}
+
+/// A [`ResetPayload`] as received by [`listen_to_store_changes`].
+#[derive(Deserialize)]
+struct ReceivedPatch {
+    schema: u32,
+    path: PathBuf,
+    patch: Vec<PatchOperation>,
+}
+
+#[derive(Serialize, Clone)]
+struct DiskFullPayload<'a> {
+    schema: u32,
+    path: &'a Path,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct LockPayload<'a> {
+    schema: u32,
+    path: &'a Path,
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl_ms: Option<u64>,
+}
+
+#[derive(Serialize, Clone)]
+struct ErrorPayload<'a> {
+    schema: u32,
+    path: &'a Path,
+    operation: &'a str,
+    error: String,
+}
+
 #[derive(Default)]
 pub struct StoreCollection<R: Runtime> {
     stores: Mutex<HashMap<PathBuf, Store<R>>>,
     frozen: bool,
+    presets: HashMap<String, HashMap<String, JsonValue>>,
+    read_only: bool,
+    /// Set by [`Builder::save_on_change`].
+    save_on_change: bool,
+    instance_lock: Option<InstanceLock>,
+    cursors: Mutex<HashMap<u64, Cursor>>,
+    next_cursor: AtomicU64,
+    edits: Mutex<HashMap<u64, Edit>>,
+    next_edit: AtomicU64,
+    /// The labels of the webviews that opened each store, see [`Builder::unload_unreferenced`].
+    references: Mutex<HashMap<PathBuf, HashSet<String>>>,
+    /// The stores registered with [`Builder::store`] or [`Self::register`], which are never unloaded.
+    registered: Mutex<HashSet<PathBuf>>,
+    unload_grace: Option<Duration>,
+    /// The rules added with [`Self::replicate`].
+    replications: Mutex<Vec<Replication>>,
+    deep_links: DeepLinks,
+    /// Increments of counters that weren't flushed yet, by store and key, see [`Self::counter_add`].
+    counters: Mutex<HashMap<(PathBuf, String), i64>>,
+    counter_flush_interval: Duration,
+    /// Guards the background thread that flushes the counters, started by the first increment.
+    counter_timer: Once,
+    /// The menu items bound with [`Self::bind_tray_item`].
+    #[cfg(feature = "tray")]
+    tray_items: Mutex<Vec<tray::TrayItem>>,
+    /// Guards the background thread that saves stores with a timed [`SaveStrategy`].
+    save_timer: Once,
+    /// The saves queued with [`Self::queue_save`].
+    save_queue: SaveQueue,
+}
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Starts checking the stores for due saves in the background, unless it was started already.
+    ///
+    /// Stores opened after startup may be the first ones with a timed [`SaveStrategy`].
+    fn start_save_timer(&self, app: &AppHandle<R>) {
+        if self.read_only {
+            return;
+        }
+        self.save_timer.call_once(|| {
+            let app_handle = app.clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(SAVE_CHECK_INTERVAL);
+                save_due(&app_handle);
+            });
+        });
+    }
+
+    /// Returns `true` if another instance of the app owns the store files, see [`Builder::read_only_secondary_instances`].
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Saves all stores of `group` as a unit.
+    ///
+    /// If one of the stores fails to save, the files of the stores saved before it are restored
+    /// to their previous contents and the error is returned.
+    pub fn save_group(&self, group: &str) -> Result<(), Error> {
+        let stores = self.stores.lock().expect("mutex poisoned");
+        let members: Vec<&Store<R>> = stores
+            .values()
+            .filter(|store| store.group() == Some(group))
+            .collect();
+
+        let previous: Vec<(PathBuf, Option<Vec<u8>>)> = members
+            .iter()
+            .map(|store| {
+                let path = store.store_path();
+                let bytes = std::fs::read(&path).ok();
+                (path, bytes)
+            })
+            .collect();
+
+        for (i, store) in members.iter().enumerate() {
+            if let Err(err) = store.save() {
+                // the failing store may have been replaced before a later step of its save failed
+                for (path, bytes) in &previous[..=i] {
+                    let restored = match bytes {
+                        Some(bytes) => write_atomic(path, bytes),
+                        None => std::fs::remove_file(path).or_else(|err| match err.kind() {
+                            std::io::ErrorKind::NotFound => Ok(()),
+                            _ => Err(err),
+                        }),
+                    };
+                    if let Err(err) = restored {
+                        warn!("Failed to restore store {:?}: {}", path, err);
+                    }
+                }
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies the preset registered as `name` with [`Builder::preset`] to the store at `path`.
+    pub fn apply_preset(&self, app: &AppHandle<R>, path: &Path, name: &str) -> Result<(), Error> {
+        let preset = self
+            .presets
+            .get(name)
+            .ok_or_else(|| Error::PresetNotFound(name.to_string()))?;
+
+        let mut stores = self.stores.lock().expect("mutex poisoned");
+        let key = ensure_store(app, self, &mut stores, path)?;
+        stores
+            .get_mut(&key)
+            .expect("failed to retrieve store. This is a bug!")
+            .insert_many(preset.clone())
+    }
+
+    /// Opens the file at `path` as a read-only store, so it can be viewed through the store API.
+    ///
+    /// Relative paths are resolved like other store paths, absolute paths can point anywhere, e.g. to a bundled
+    /// resource or a file the user selected in a dialog. A read-only store already open at `path` is reloaded.
+    /// Fails if the file can't be read or the collection is frozen.
+    pub fn open_readonly(
+        &self,
+        app: &AppHandle<R>,
+        path: PathBuf,
+        format: FileFormat,
+    ) -> Result<(), Error> {
+        if self.frozen {
+            return Err(Error::NotFound(path));
+        }
+        let key = collection_key(&path);
+        if let Some(store) = self.stores.lock().expect("mutex poisoned").get(&key) {
+            if !store.is_read_only() {
+                return Err(Error::AlreadyOpen(path));
+            }
+        }
+
+        // load outside of the lock so commands aren't blocked by the disk read
+        let deserialize = format.deserializer().ok_or_else(|| {
+            Error::Deserialize(format!("{format:?} files can't be opened read-only").into())
+        })?;
+        let mut store = StoreBuilder::new(app.clone(), normalize_path(&path))
+            .deserialize(deserialize)
+            .read_only()
+            .build();
+        store.load()?;
+        self.stores
+            .lock()
+            .expect("mutex poisoned")
+            .insert(key, store);
+        Ok(())
+    }
+
+    /// Adds a store built at runtime to the collection and loads it, e.g. one built by another plugin.
+    ///
+    /// Fails with [`Error::AlreadyExists`] if a store is already open at its path, e.g. because a frontend command
+    /// created it on demand, instead of letting two instances of the same file diverge.
+    pub fn register(&self, store: Store<R>) -> Result<(), Error> {
+        self.insert_new(store)
+            .map_err(|store| Error::AlreadyExists(store.path))
+    }
+
+    /// Registers the store built by `builder` unless a store is already open at its path, then calls `f` with
+    /// the store of the collection, so init code and on-demand loads by the frontend always share one instance.
+    ///
+    /// With `apply_defaults`, the defaults of `builder` are added to an existing store with [`Store::add_defaults`].
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use tauri::Manager;
+    /// use tauri_plugin_store::{StoreBuilder, StoreCollection};
+    ///
+    /// fn init<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<(), tauri_plugin_store::Error> {
+    ///   let builder = StoreBuilder::new(app.clone(), "settings.json".into())
+    ///     .defaults([("theme".to_string(), "dark".into())].into());
+    ///   let theme = app
+    ///     .state::<StoreCollection<R>>()
+    ///     .register_or_get(builder, true, |store| store.get("theme").cloned())?;
+    ///   Ok(())
+    /// }
+    /// ```
+    pub fn register_or_get<T>(
+        &self,
+        builder: StoreBuilder<R>,
+        apply_defaults: bool,
+        f: impl FnOnce(&mut Store<R>) -> T,
+    ) -> Result<T, Error> {
+        let store = builder.build();
+        let path = store.path.clone();
+        let key = collection_key(&path);
+        let defaults = store.defaults.clone().filter(|_| apply_defaults);
+
+        // checked first so an existing store isn't read from disk again
+        let exists = self
+            .stores
+            .lock()
+            .expect("mutex poisoned")
+            .contains_key(&key);
+        let created = !exists && self.insert_new(store).is_ok();
+
+        if !created {
+            // registered before the stores are locked, keeping the collection's lock order
+            self.registered
+                .lock()
+                .expect("mutex poisoned")
+                .insert(key.clone());
+        }
+
+        let mut stores = self.stores.lock().expect("mutex poisoned");
+        // an existing store may have been unloaded in the meantime
+        let Some(store) = stores.get_mut(&key) else {
+            drop(stores);
+            if !created {
+                self.registered.lock().expect("mutex poisoned").remove(&key);
+            }
+            return Err(Error::NotFound(path));
+        };
+        if !created {
+            if let Some(defaults) = defaults {
+                store.add_defaults(defaults)?;
+            }
+        }
+        Ok(f(store))
+    }
+
+    /// Loads `store` and adds it to the collection as a registered store, returning it if a store is already
+    /// open at its path.
+    fn insert_new(&self, mut store: Store<R>) -> Result<(), Store<R>> {
+        let key = collection_key(&store.path);
+        if self
+            .stores
+            .lock()
+            .expect("mutex poisoned")
+            .contains_key(&key)
+        {
+            return Err(store);
+        }
+        if self.read_only {
+            store.read_only = true;
+        }
+        // load outside of the lock so commands aren't blocked by the disk read
+        if let Err(err) = store.load() {
+            store.report_error("load", err);
+        }
+        store.save_on_change |= self.save_on_change;
+        store.save_queue = Some(self.save_queue.clone());
+        match self
+            .stores
+            .lock()
+            .expect("mutex poisoned")
+            .entry(key.clone())
+        {
+            // opened while the store was loading
+            Entry::Occupied(_) => return Err(store),
+            Entry::Vacant(entry) => {
+                entry.insert(store);
+            }
+        }
+        self.registered.lock().expect("mutex poisoned").insert(key);
+        Ok(())
+    }
+
+    /// Reloads all stores of `group` from disk as a unit, discarding their unsaved changes.
+    ///
+    /// The files are loaded into detached copies of the stores, which replace them once all loaded. If one of
+    /// the stores fails to load, none of them are changed and the error is returned.
+    pub fn reload_group(&self, group: &str) -> Result<(), Error> {
+        let mut stores = self.stores.lock().expect("mutex poisoned");
+
+        let mut reloaded = Vec::new();
+        for (path, store) in stores.iter() {
+            if store.group() == Some(group) {
+                let mut copy = store.detached();
+                copy.load()?;
+                reloaded.push((path.clone(), copy));
+            }
+        }
+        for (path, mut copy) in reloaded {
+            if let Some(store) = stores.get_mut(&path) {
+                copy.attach(store);
+                *store = copy;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 pub fn with_store<R: Runtime, T, F: FnOnce(&mut Store<R>) -> Result<T, Error>>(
@@ -41,12 +504,230 @@ pub fn with_store<R: Runtime, T, F: FnOnce(&mut Store<R>) -> Result<T, Error>>(
 ) -> Result<T, Error> {
     let mut stores = collection.stores.lock().expect("mutex poisoned");
 
-    let path = path.as_ref();
-    if !stores.contains_key(path) {
+    let key = ensure_store(&app, &collection, &mut stores, path.as_ref())?;
+
+    let store = stores
+        .get_mut(&key)
+        .expect("failed to retrieve store. This is a bug!");
+    store.replicating = collection.is_replicated(&key);
+    let result = f(store);
+    if let Err(err) = store.save_if_due() {
+        store.report_error("save", err);
+    }
+    if matches!(
+        store.save_strategy(),
+        SaveStrategy::Debounced(_) | SaveStrategy::Interval(_)
+    ) && store.has_unsaved_changes()
+    {
+        collection.start_save_timer(&app);
+    }
+    if store.replicating {
+        collection.replicate_changes(&app, &mut stores, key);
+    }
+    result
+}
+
+/// Saves every store with unsaved changes and flushes pending counter increments, like the plugin does when
+/// the app exits.
+///
+/// [`RunEvent::Exit`] doesn't fire when the process is terminated by a signal or console control event, so apps
+/// that want to keep their changes in that case can call this from their own handler.
+///
+/// # Examples
+/// ```no_run
+/// #[tauri::command]
+/// fn prepare_shutdown(app: tauri::AppHandle) {
+///   tauri_plugin_store::flush(&app);
+/// }
+/// ```
+pub fn flush<R: Runtime>(app: &AppHandle<R>) {
+    app.state::<StoreCollection<R>>().flush_counters(app);
+    save_all(app);
+}
+
+/// Calls `handler` with the payload of every change event emitted under the default [`CHANGE_EVENT`] name,
+/// so Rust code can react to store changes without parsing event JSON.
+///
+/// Resets and batches, which are emitted as a single [`RESET_EVENT`] or [`BATCH_EVENT`] with a JSON Patch,
+/// are reported as one change per affected key, without a window or transaction id.
+///
+/// Returns the handler ids to pass to [`Manager::unlisten`].
+///
+/// # Examples
+/// ```no_run
+/// use tauri::Manager;
+///
+/// tauri::Builder::default()
+///   .plugin(tauri_plugin_store::Builder::default().build())
+///   .setup(|app| {
+///     tauri_plugin_store::listen_to_store_changes(&app.handle(), |change| {
+///       println!("{} changed in {:?}", change.key, change.path);
+///     });
+///     Ok(())
+///   });
+/// ```
+pub fn listen_to_store_changes<R: Runtime, F>(app: &AppHandle<R>, handler: F) -> Vec<EventHandler>
+where
+    F: Fn(ChangePayload<'static>) + Send + Sync + 'static,
+{
+    let handler = Arc::new(handler);
+    let mut handlers = Vec::with_capacity(3);
+
+    let on_change = handler.clone();
+    handlers.push(app.listen_global(CHANGE_EVENT, move |event| {
+        match event
+            .payload()
+            .map(serde_json::from_str::<ChangePayload<'static>>)
+        {
+            Some(Ok(change)) => on_change(change),
+            Some(Err(err)) => warn!("Failed to parse store change event: {}", err),
+            None => warn!("Store change event without payload"),
+        }
+    }));
+
+    for event_name in [RESET_EVENT, BATCH_EVENT] {
+        let on_patch = handler.clone();
+        handlers.push(app.listen_global(event_name, move |event| {
+            let received = match event.payload().map(serde_json::from_str::<ReceivedPatch>) {
+                Some(Ok(received)) => received,
+                Some(Err(err)) => {
+                    warn!("Failed to parse store {} event: {}", event_name, err);
+                    return;
+                }
+                None => {
+                    warn!("Store {} event without payload", event_name);
+                    return;
+                }
+            };
+            for operation in received.patch {
+                let key = operation.key();
+                let value = match operation {
+                    PatchOperation::Add { value, .. } | PatchOperation::Replace { value, .. } => {
+                        value
+                    }
+                    PatchOperation::Remove { .. } => JsonValue::Null,
+                };
+                on_patch(ChangePayload {
+                    schema: received.schema,
+                    path: Cow::Owned(received.path.clone()),
+                    key: Cow::Owned(key),
+                    value: Cow::Owned(value),
+                    window: None,
+                    txn_id: None,
+                });
+            }
+        }));
+    }
+
+    handlers
+}
+
+/// Gives other plugins and app code typed access to stores, turning them into a configuration bus.
+///
+/// Implemented for every [`Manager`], e.g. [`AppHandle`] and [`tauri::Window`].
+pub trait StoreExt<R: Runtime> {
+    /// Returns the value stored under `key` in the store at `path`, deserialized into `T`.
+    fn store_value<T: DeserializeOwned>(
+        &self,
+        path: impl AsRef<Path>,
+        key: &str,
+    ) -> Result<Option<T>, Error>;
+
+    /// Calls `callback` with the deserialized value every time `key` changes in the store at `path`.
+    ///
+    /// The callback runs while the store collection is locked, so it must not access stores itself.
+    fn watch_store_key<T, F>(
+        &self,
+        path: impl AsRef<Path>,
+        key: impl Into<String>,
+        callback: F,
+    ) -> Result<WatchId, Error>
+    where
+        T: DeserializeOwned,
+        F: Fn(Result<Option<T>, Error>) + Send + Sync + 'static;
+
+    /// Removes a callback registered with [`Self::watch_store_key`].
+    fn unwatch_store_key(&self, path: impl AsRef<Path>, id: WatchId) -> Result<bool, Error>;
+}
+
+impl<R: Runtime, M: Manager<R>> StoreExt<R> for M {
+    fn store_value<T: DeserializeOwned>(
+        &self,
+        path: impl AsRef<Path>,
+        key: &str,
+    ) -> Result<Option<T>, Error> {
+        with_store(self.app_handle(), self.state(), path, |store| {
+            store
+                .get(key)
+                .map(|value| serde_json::from_value(value.clone()))
+                .transpose()
+                .map_err(Into::into)
+        })
+    }
+
+    fn watch_store_key<T, F>(
+        &self,
+        path: impl AsRef<Path>,
+        key: impl Into<String>,
+        callback: F,
+    ) -> Result<WatchId, Error>
+    where
+        T: DeserializeOwned,
+        F: Fn(Result<Option<T>, Error>) + Send + Sync + 'static,
+    {
+        with_store(self.app_handle(), self.state(), path, |store| {
+            Ok(store.watch(key, move |value| {
+                callback(
+                    value
+                        .map(|value| serde_json::from_value(value.clone()))
+                        .transpose()
+                        .map_err(Into::into),
+                )
+            }))
+        })
+    }
+
+    fn unwatch_store_key(&self, path: impl AsRef<Path>, id: WatchId) -> Result<bool, Error> {
+        with_store(self.app_handle(), self.state(), path, |store| {
+            Ok(store.unwatch(id))
+        })
+    }
+}
+
+/// Treats both `/` and `\` as separators and removes `.` components, so stores are created with canonical paths.
+fn normalize_path(path: &Path) -> PathBuf {
+    let unified = path.to_string_lossy().replace('\\', "/");
+    Path::new(&unified).components().collect()
+}
+
+/// Returns the key of the store at `path` in a [`StoreCollection`], so that different spellings of a path share one store.
+///
+/// On Windows and macOS, whose file systems are case-insensitive by default, the key is lowercased as well.
+pub(crate) fn collection_key(path: &Path) -> PathBuf {
+    let key = normalize_path(path);
+    if cfg!(any(windows, target_os = "macos")) {
+        PathBuf::from(key.to_string_lossy().to_lowercase())
+    } else {
+        key
+    }
+}
+
+/// Makes sure a store for `path` exists in `stores`, loading it from disk if needed, and returns its key.
+fn ensure_store<R: Runtime>(
+    app: &AppHandle<R>,
+    collection: &StoreCollection<R>,
+    stores: &mut HashMap<PathBuf, Store<R>>,
+    path: &Path,
+) -> Result<PathBuf, Error> {
+    let key = collection_key(path);
+    if !stores.contains_key(&key) {
         if collection.frozen {
             return Err(Error::NotFound(path.to_path_buf()));
         }
-        let mut store = StoreBuilder::new(app, path.to_path_buf()).build();
+        let mut store = StoreBuilder::new(app.clone(), normalize_path(path)).build();
+        store.read_only = collection.read_only;
+        store.save_on_change = collection.save_on_change;
+        store.save_queue = Some(collection.save_queue.clone());
         // ignore loading errors, just use the default
         if let Err(err) = store.load() {
             warn!(
@@ -54,23 +735,60 @@ pub fn with_store<R: Runtime, T, F: FnOnce(&mut Store<R>) -> Result<T, Error>>(
                 path, err
             );
         }
-        stores.insert(path.to_path_buf(), store);
+        stores.insert(key.clone(), store);
     }
-
-    f(stores
-        .get_mut(path)
-        .expect("failed to retrieve store. This is a bug!"))
+    Ok(key)
 }
 
 #[tauri::command]
 async fn set<R: Runtime>(
     app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    key: String,
+    value: JsonValue,
+    txn_id: Option<String>,
+) -> Result<(), Error> {
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), txn_id, |store| store.insert(key, value))
+    })
+}
+
+#[tauri::command]
+async fn set_with_ttl<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
     stores: State<'_, StoreCollection<R>>,
     path: PathBuf,
     key: String,
     value: JsonValue,
+    ttl_ms: u64,
+    txn_id: Option<String>,
 ) -> Result<(), Error> {
-    with_store(app, stores, path, |store| store.insert(key, value))
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), txn_id, |store| {
+            store.insert_with_ttl(key, value, Duration::from_millis(ttl_ms))
+        })
+    })
+}
+
+#[tauri::command]
+async fn compare_and_set<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    key: String,
+    expected: JsonValue,
+    value: JsonValue,
+    txn_id: Option<String>,
+) -> Result<bool, Error> {
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), txn_id, |store| {
+            store.compare_and_set(key, &expected, value)
+        })
+    })
 }
 
 #[tauri::command]
@@ -80,7 +798,49 @@ async fn get<R: Runtime>(
     path: PathBuf,
     key: String,
 ) -> Result<Option<JsonValue>, Error> {
-    with_store(app, stores, path, |store| Ok(store.get(key).cloned()))
+    with_store(app, stores, path, |store| store.get_checked(key))
+}
+
+#[tauri::command]
+async fn wait_for<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    key: String,
+    value: Option<JsonValue>,
+    timeout_ms: Option<u64>,
+) -> Result<JsonValue, Error> {
+    let matches = move |current: Option<&JsonValue>| match &value {
+        Some(expected) => current == Some(expected),
+        None => current.is_some(),
+    };
+
+    // `None` means the window went away, so nobody waits for the value anymore
+    let (tx, rx) = mpsc::sync_channel(1);
+    let destroyed = tx.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            let _ = destroyed.try_send(None);
+        }
+    });
+    let id = with_store(app.clone(), stores.clone(), &path, |store| {
+        if let Some(current) = store.get(&key).filter(|&current| matches(Some(current))) {
+            let _ = tx.try_send(Some(current.clone()));
+        }
+        Ok(store.watch(key.clone(), move |current| {
+            if let Some(current) = current.filter(|&current| matches(Some(current))) {
+                let _ = tx.try_send(Some(current.clone()));
+            }
+        }))
+    })?;
+
+    let timeout = timeout_ms.map_or(WAIT_FOR_TIMEOUT, Duration::from_millis);
+    let received =
+        tauri::async_runtime::spawn_blocking(move || rx.recv_timeout(timeout).ok().flatten()).await;
+    with_store(app, stores, &path, |store| Ok(store.unwatch(id)))?;
+
+    received?.ok_or(Error::Timeout(key))
 }
 
 #[tauri::command]
@@ -93,32 +853,138 @@ async fn has<R: Runtime>(
     with_store(app, stores, path, |store| Ok(store.has(key)))
 }
 
+#[tauri::command]
+async fn type_of<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    key: String,
+) -> Result<ValueType, Error> {
+    with_store(app, stores, path, |store| Ok(store.type_of(key)))
+}
+
 #[tauri::command]
 async fn delete<R: Runtime>(
     app: AppHandle<R>,
+    window: Window<R>,
     stores: State<'_, StoreCollection<R>>,
     path: PathBuf,
     key: String,
+    txn_id: Option<String>,
 ) -> Result<bool, Error> {
-    with_store(app, stores, path, |store| store.delete(key))
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), txn_id, |store| store.delete(key))
+    })
+}
+
+#[tauri::command]
+async fn prune<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    txn_id: Option<String>,
+) -> Result<Vec<String>, Error> {
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), txn_id, |store| store.prune())
+    })
+}
+
+#[tauri::command]
+async fn writer<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+) -> Result<Option<Writer>, Error> {
+    with_store(app, stores, path, |store| Ok(store.writer().cloned()))
+}
+
+#[tauri::command]
+async fn transfer_writer<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    to: Option<String>,
+) -> Result<(), Error> {
+    let to = to.map_or(Writer::Backend, Writer::Window);
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), None, |store| store.transfer_writer(to))
+    })
 }
 
 #[tauri::command]
 async fn clear<R: Runtime>(
     app: AppHandle<R>,
+    window: Window<R>,
     stores: State<'_, StoreCollection<R>>,
     path: PathBuf,
+    scope: Option<String>,
+    txn_id: Option<String>,
 ) -> Result<(), Error> {
-    with_store(app, stores, path, |store| store.clear())
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), txn_id, |store| match scope {
+            Some(scope) => store.scoped(scope).clear(),
+            None => store.clear(),
+        })
+    })
 }
 
 #[tauri::command]
 async fn reset<R: Runtime>(
     app: AppHandle<R>,
+    window: Window<R>,
     collection: State<'_, StoreCollection<R>>,
     path: PathBuf,
+    silent: Option<bool>,
+) -> Result<(), Error> {
+    with_store(app, collection, path, |store| {
+        store.with_origin(window.label(), None, |store| {
+            if silent.unwrap_or(false) {
+                store.with_events_suppressed(Store::reset)
+            } else {
+                store.reset()
+            }
+        })
+    })
+}
+
+#[tauri::command]
+async fn add_defaults<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    defaults: HashMap<String, JsonValue>,
+) -> Result<(), Error> {
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), None, |store| store.add_defaults(defaults))
+    })
+}
+
+#[tauri::command]
+async fn reset_key<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    key: String,
+    txn_id: Option<String>,
 ) -> Result<(), Error> {
-    with_store(app, collection, path, |store| store.reset())
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), txn_id, |store| store.reset_key(key))
+    })
+}
+
+#[tauri::command]
+async fn overridden_keys<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+) -> Result<Vec<String>, Error> {
+    with_store(app, stores, path, |store| {
+        Ok(store.overridden_keys().into_iter().cloned().collect())
+    })
 }
 
 #[tauri::command]
@@ -126,9 +992,23 @@ async fn keys<R: Runtime>(
     app: AppHandle<R>,
     stores: State<'_, StoreCollection<R>>,
     path: PathBuf,
+    scope: Option<String>,
+) -> Result<Vec<String>, Error> {
+    with_store(app, stores, path, |store| match scope {
+        Some(scope) => Ok(store.scoped(scope).keys().map(String::from).collect()),
+        None => Ok(store.keys().cloned().collect()),
+    })
+}
+
+#[tauri::command]
+async fn keys_sorted<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    order: KeyOrder,
 ) -> Result<Vec<String>, Error> {
     with_store(app, stores, path, |store| {
-        Ok(store.keys().cloned().collect())
+        Ok(store.keys_sorted(order).into_iter().cloned().collect())
     })
 }
 
@@ -137,9 +1017,11 @@ async fn values<R: Runtime>(
     app: AppHandle<R>,
     stores: State<'_, StoreCollection<R>>,
     path: PathBuf,
+    scope: Option<String>,
 ) -> Result<Vec<JsonValue>, Error> {
-    with_store(app, stores, path, |store| {
-        Ok(store.values().cloned().collect())
+    with_store(app, stores, path, |store| match scope {
+        Some(scope) => Ok(store.scoped(scope).values().cloned().collect()),
+        None => Ok(store.values().cloned().collect()),
     })
 }
 
@@ -148,12 +1030,18 @@ async fn entries<R: Runtime>(
     app: AppHandle<R>,
     stores: State<'_, StoreCollection<R>>,
     path: PathBuf,
+    scope: Option<String>,
 ) -> Result<Vec<(String, JsonValue)>, Error> {
-    with_store(app, stores, path, |store| {
-        Ok(store
+    with_store(app, stores, path, |store| match scope {
+        Some(scope) => Ok(store
+            .scoped(scope)
             .entries()
             .map(|(k, v)| (k.to_owned(), v.to_owned()))
-            .collect())
+            .collect()),
+        None => Ok(store
+            .entries()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect()),
     })
 }
 
@@ -162,46 +1050,845 @@ async fn length<R: Runtime>(
     app: AppHandle<R>,
     stores: State<'_, StoreCollection<R>>,
     path: PathBuf,
+    scope: Option<String>,
 ) -> Result<usize, Error> {
-    with_store(app, stores, path, |store| Ok(store.len()))
+    with_store(app, stores, path, |store| match scope {
+        Some(scope) => Ok(store.scoped(scope).len()),
+        None => Ok(store.len()),
+    })
 }
 
 #[tauri::command]
-async fn load<R: Runtime>(
+async fn cursor_open<R: Runtime>(
     app: AppHandle<R>,
-    stores: State<'_, StoreCollection<R>>,
+    collection: State<'_, StoreCollection<R>>,
     path: PathBuf,
-) -> Result<(), Error> {
-    with_store(app, stores, path, |store| store.load())
+) -> Result<u64, Error> {
+    collection.cursor_open(&app, &path)
 }
 
 #[tauri::command]
-async fn save<R: Runtime>(
+async fn cursor_next<R: Runtime>(
+    _app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+    cursor: u64,
+    limit: usize,
+) -> Result<Vec<(String, JsonValue)>, Error> {
+    collection.cursor_next(cursor, limit)
+}
+
+#[tauri::command]
+async fn cursor_close<R: Runtime>(
+    _app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+    cursor: u64,
+) -> Result<bool, Error> {
+    Ok(collection.cursor_close(cursor))
+}
+
+#[tauri::command]
+async fn begin_edit<R: Runtime>(
     app: AppHandle<R>,
-    stores: State<'_, StoreCollection<R>>,
+    collection: State<'_, StoreCollection<R>>,
     path: PathBuf,
+) -> Result<u64, Error> {
+    collection.begin_edit(&app, &path)
+}
+
+#[tauri::command]
+async fn edit_set<R: Runtime>(
+    _app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+    edit: u64,
+    key: String,
+    value: Option<JsonValue>,
 ) -> Result<(), Error> {
-    with_store(app, stores, path, |store| store.save())
+    collection.edit_set(edit, key, value)
+}
+
+#[tauri::command]
+async fn edit_get<R: Runtime>(
+    _app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+    edit: u64,
+    key: String,
+) -> Result<Option<JsonValue>, Error> {
+    collection.edit_get(edit, &key)
+}
+
+#[tauri::command]
+async fn commit_edit<R: Runtime>(
+    _app: AppHandle<R>,
+    window: Window<R>,
+    collection: State<'_, StoreCollection<R>>,
+    edit: u64,
+) -> Result<(), Error> {
+    collection.commit_edit(edit, Some(window.label()))
+}
+
+#[tauri::command]
+async fn discard_edit<R: Runtime>(
+    _app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+    edit: u64,
+) -> Result<bool, Error> {
+    Ok(collection.discard_edit(edit))
+}
+
+#[tauri::command]
+async fn find_by<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    index: String,
+    value: JsonValue,
+) -> Result<Vec<(String, JsonValue)>, Error> {
+    with_store(app, stores, path, |store| {
+        Ok(store
+            .find_by(&index, &value)?
+            .into_iter()
+            .filter_map(|key| store.get(key).map(|value| (key.clone(), value.clone())))
+            .collect())
+    })
+}
+
+#[tauri::command]
+async fn find_range<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    field: String,
+    min: Option<f64>,
+    max: Option<f64>,
+) -> Result<Vec<(String, JsonValue)>, Error> {
+    with_store(app, stores, path, |store| {
+        Ok(store
+            .find_range(&field, min, max)
+            .into_iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    })
+}
+
+#[tauri::command]
+async fn aggregate<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    field: String,
+    op: Aggregate,
+    filter: Option<KeyFilter>,
+) -> Result<Option<f64>, Error> {
+    with_store(app, stores, path, |store| {
+        Ok(store.aggregate(&field, op, &filter.unwrap_or_default()))
+    })
+}
+
+#[cfg(feature = "search")]
+#[tauri::command]
+async fn search<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<(String, JsonValue)>, Error> {
+    with_store(app, stores, path, |store| {
+        Ok(store
+            .search(&query, limit.unwrap_or(usize::MAX))?
+            .into_iter()
+            .filter_map(|key| store.get(key).map(|value| (key.clone(), value.clone())))
+            .collect())
+    })
+}
+
+#[cfg(not(feature = "search"))]
+#[tauri::command]
+async fn search<R: Runtime>(
+    _app: AppHandle<R>,
+    _stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+) -> Result<Vec<(String, JsonValue)>, Error> {
+    Err(Error::SearchDisabled(path))
+}
+
+#[tauri::command]
+async fn checksum<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+) -> Result<String, Error> {
+    with_store(app, stores, path, |store| store.checksum())
+}
+
+#[tauri::command]
+async fn diff<R: Runtime>(
+    app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+    path_a: PathBuf,
+    path_b: PathBuf,
+) -> Result<StoreDiff, Error> {
+    let mut stores = collection.stores.lock().expect("mutex poisoned");
+
+    let key_a = ensure_store(&app, &collection, &mut stores, &path_a)?;
+    let key_b = ensure_store(&app, &collection, &mut stores, &path_b)?;
+
+    Ok(stores[&key_a].diff(&stores[&key_b]))
+}
+
+#[tauri::command]
+async fn import_map<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    entries: HashMap<String, JsonValue>,
+    strategy: ConflictStrategy,
+    marker: Option<String>,
+    silent: Option<bool>,
+) -> Result<bool, Error> {
+    with_store(app, stores, path, |store| {
+        if silent.unwrap_or(false) {
+            store.with_events_suppressed(|store| {
+                store.import_map(entries, strategy, marker.as_deref())
+            })
+        } else {
+            store.import_map(entries, strategy, marker.as_deref())
+        }
+    })
+}
+
+#[tauri::command]
+async fn save_group<R: Runtime>(
+    _app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+    group: String,
+) -> Result<(), Error> {
+    collection.save_group(&group)
+}
+
+#[tauri::command]
+async fn reload_group<R: Runtime>(
+    _app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+    group: String,
+) -> Result<(), Error> {
+    collection.reload_group(&group)
+}
+
+#[tauri::command]
+async fn validate_import<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    bytes: Vec<u8>,
+    strategy: ConflictStrategy,
+) -> Result<StoreDiff, Error> {
+    with_store(app, stores, path, |store| {
+        store.validate_import(&bytes, strategy)
+    })
+}
+
+#[tauri::command]
+async fn apply_preset<R: Runtime>(
+    app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    name: String,
+) -> Result<(), Error> {
+    collection.apply_preset(&app, &path, &name)
+}
+
+#[tauri::command]
+async fn client_id<R: Runtime>(
+    app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+    path: Option<PathBuf>,
+    scope: Option<ClientIdScope>,
+) -> Result<String, Error> {
+    collection.client_id(&app, path.as_deref(), scope.unwrap_or_default())
+}
+
+#[tauri::command]
+async fn flag<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    name: String,
+) -> Result<bool, Error> {
+    stores.flag(&app, &path, &name)
+}
+
+#[tauri::command]
+async fn queue_push<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    queue: String,
+    value: JsonValue,
+) -> Result<u64, Error> {
+    with_store(app, stores, path, |store| store.queue_push(&queue, value))
+}
+
+#[tauri::command]
+async fn queue_pop_batch<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    queue: String,
+    max: usize,
+) -> Result<Vec<QueueItem>, Error> {
+    with_store(app, stores, path, |store| {
+        store.queue_pop_batch(&queue, max)
+    })
+}
+
+#[tauri::command]
+async fn queue_ack<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    queue: String,
+    ids: Vec<u64>,
+) -> Result<usize, Error> {
+    with_store(app, stores, path, |store| store.queue_ack(&queue, &ids))
+}
+
+#[tauri::command]
+async fn queue_requeue<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    queue: String,
+) -> Result<usize, Error> {
+    with_store(app, stores, path, |store| store.queue_requeue(&queue))
+}
+
+#[tauri::command]
+async fn queue_len<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    queue: String,
+) -> Result<usize, Error> {
+    with_store(app, stores, path, |store| store.queue_len(&queue))
+}
+
+#[tauri::command]
+async fn keymap<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    keymap: String,
+) -> Result<BTreeMap<String, String>, Error> {
+    with_store(app, stores, path, |store| store.keymap(&keymap))
+}
+
+#[tauri::command]
+async fn keymap_set<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    keymap: String,
+    action: String,
+    shortcut: String,
+) -> Result<String, Error> {
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), None, |store| {
+            store.keymap_set(&keymap, &action, &shortcut)
+        })
+    })
+}
+
+#[tauri::command]
+async fn keymap_remove<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    keymap: String,
+    action: String,
+) -> Result<bool, Error> {
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), None, |store| {
+            store.keymap_remove(&keymap, &action)
+        })
+    })
+}
+
+#[tauri::command]
+async fn cache_put<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    key: String,
+    value: JsonValue,
+    fresh_for_ms: u64,
+    max_age_ms: Option<u64>,
+) -> Result<(), Error> {
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), None, |store| {
+            store.cache_put(
+                key,
+                value,
+                Duration::from_millis(fresh_for_ms),
+                max_age_ms.map(Duration::from_millis),
+            )
+        })
+    })
+}
+
+#[tauri::command]
+async fn cache_get<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    key: String,
+) -> Result<Option<CachedValue>, Error> {
+    with_store(app, stores, path, |store| store.cache_get(&key))
+}
+
+#[tauri::command]
+async fn first_run<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+) -> Result<bool, Error> {
+    with_store(app, stores, path, |store| store.first_run())
+}
+
+#[tauri::command]
+async fn mark_step_done<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    step: String,
+) -> Result<bool, Error> {
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), None, |store| store.mark_step_done(&step))
+    })
+}
+
+#[tauri::command]
+async fn is_step_done<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    step: String,
+) -> Result<bool, Error> {
+    with_store(app, stores, path, |store| store.is_step_done(&step))
+}
+
+#[tauri::command]
+async fn reset_onboarding<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+) -> Result<(), Error> {
+    with_store(app, stores, path, |store| {
+        store.with_origin(window.label(), None, |store| store.reset_onboarding())
+    })
+}
+
+#[tauri::command]
+async fn acquire_lock<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    name: String,
+    ttl_ms: u64,
+) -> Result<bool, Error> {
+    with_store(app, stores, path, |store| {
+        Ok(store.acquire_lock(&name, window.label(), Duration::from_millis(ttl_ms)))
+    })
+}
+
+#[tauri::command]
+async fn lock_status<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+) -> Result<Vec<LockStatus>, Error> {
+    with_store(app, stores, path, |store| Ok(store.lock_status()))
+}
+
+#[tauri::command]
+async fn release_lock<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    name: String,
+) -> Result<bool, Error> {
+    with_store(app, stores, path, |store| {
+        Ok(store.release_lock(&name, window.label()))
+    })
+}
+
+#[tauri::command]
+async fn history<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    key: Option<String>,
+) -> Result<Vec<HistoryEntry>, Error> {
+    with_store(app, stores, path, |store| Ok(store.history(key.as_deref())))
+}
+
+#[tauri::command]
+async fn persistence_status<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+) -> Result<PersistenceStatus, Error> {
+    with_store(app, stores, path, |store| Ok(store.persistence_status()))
+}
+
+#[tauri::command]
+async fn export<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    filter: KeyFilter,
+) -> Result<HashMap<String, JsonValue>, Error> {
+    with_store(app, stores, path, |store| Ok(store.export(&filter)))
+}
+
+#[tauri::command]
+async fn export_redacted<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+) -> Result<String, Error> {
+    with_store(app, stores, path, |store| store.export_redacted())
+}
+
+#[tauri::command]
+async fn diagnostics<R: Runtime>(
+    _app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+) -> Result<Diagnostics, Error> {
+    Ok(collection.diagnostics())
+}
+
+#[tauri::command]
+async fn verify_all<R: Runtime>(
+    app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+) -> Result<VerifyReport, Error> {
+    Ok(collection.verify_all(&app))
+}
+
+#[tauri::command]
+async fn counter_add<R: Runtime>(
+    app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    name: String,
+    n: i64,
+) -> Result<(), Error> {
+    collection.counter_add(&app, &path, &name, n);
+    Ok(())
+}
+
+#[tauri::command]
+async fn counter_get<R: Runtime>(
+    app: AppHandle<R>,
+    collection: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    name: String,
+) -> Result<i64, Error> {
+    collection.counter_get(&app, &path, &name)
+}
+
+#[tauri::command]
+async fn load<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    defaults: Option<HashMap<String, JsonValue>>,
+) -> Result<(), Error> {
+    with_store(app, stores, path, |store| {
+        let loaded = store.load();
+        // apply the defaults even if the store file doesn't exist yet
+        if let Some(defaults) = defaults {
+            store.add_defaults(defaults)?;
+        }
+        loaded
+    })
+}
+
+#[tauri::command]
+async fn load_keys<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    keys: Vec<String>,
+) -> Result<(), Error> {
+    with_store(app, stores, path, |store| store.load_keys(&keys))
+}
+
+#[tauri::command]
+async fn repair<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+) -> Result<RepairReport, Error> {
+    with_store(app, stores, path, |store| store.repair())
+}
+
+#[tauri::command]
+async fn open<R: Runtime>(
+    app: AppHandle<R>,
+    window: Window<R>,
+    collection: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+) -> Result<(), Error> {
+    collection.open(&app, &path, window.label())
+}
+
+#[tauri::command]
+async fn compact<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+) -> Result<(), Error> {
+    with_store(app, stores, path, |store| store.compact())
+}
+
+#[tauri::command]
+async fn save<R: Runtime>(
+    app: AppHandle<R>,
+    stores: State<'_, StoreCollection<R>>,
+    path: PathBuf,
+    wait: Option<bool>,
+) -> Result<(), Error> {
+    if wait.unwrap_or(false) {
+        return with_store(app, stores, path, |store| store.save());
+    }
+    // the checks are run right away, so the caller learns about read-only stores and cancelled saves
+    with_store(app, stores.clone(), &path, |store| store.check_save())?;
+    stores.queue_save(&path);
+    Ok(())
+}
+
+/// Inserts a store registered with the plugin builder, keeping the first of two stores at the same path.
+fn insert_registered<R: Runtime>(stores: &mut HashMap<PathBuf, Store<R>>, store: Store<R>) {
+    match stores.entry(collection_key(&store.path)) {
+        Entry::Occupied(_) => warn!(
+            "Store {:?} was registered twice, ignoring the second registration",
+            store.path
+        ),
+        Entry::Vacant(entry) => {
+            entry.insert(store);
+        }
+    }
+}
+
+/// Saves every store in the collection that was modified since it was last saved, reporting failures.
+fn save_all<R: Runtime>(app_handle: &AppHandle<R>) {
+    let collection = app_handle.state::<StoreCollection<R>>();
+
+    if collection.read_only {
+        return;
+    }
+
+    for store in collection
+        .stores
+        .lock()
+        .expect("mutex poisoned")
+        .values_mut()
+        .filter(|store| {
+            !store.is_read_only() && store.save_strategy() != SaveStrategy::ExplicitOnly
+        })
+    {
+        if let Err(err) = store.commit_staged() {
+            store.report_error("commit staged values of", err);
+        }
+        if let Err(err) = store.save_if_dirty() {
+            store.report_error("save", err);
+        }
+    }
+}
+
+/// Saves the stores built with [`StoreBuilder::frontend_owned`] that have unsaved changes, reporting failures.
+fn flush_frontend_owned<R: Runtime>(app_handle: &AppHandle<R>) {
+    let collection = app_handle.state::<StoreCollection<R>>();
+
+    if collection.read_only {
+        return;
+    }
+
+    for store in collection
+        .stores
+        .lock()
+        .expect("mutex poisoned")
+        .values()
+        .filter(|store| store.frontend_owned)
+    {
+        if let Err(err) = store.save_if_dirty() {
+            store.report_error("save", err);
+        }
+    }
+}
+
+/// Saves the stores of the collection whose [`SaveStrategy`] calls for it, reporting failures.
+fn save_due<R: Runtime>(app_handle: &AppHandle<R>) {
+    let collection = app_handle.state::<StoreCollection<R>>();
+
+    for store in collection.stores.lock().expect("mutex poisoned").values() {
+        if let Err(err) = store.save_if_due() {
+            store.report_error("save", err);
+        }
+    }
+}
+
+/// Commits the due values staged by stores built with [`StoreBuilder::coalesce`], reporting failures.
+fn commit_due_all<R: Runtime>(app_handle: &AppHandle<R>) {
+    let collection = app_handle.state::<StoreCollection<R>>();
+
+    for store in collection
+        .stores
+        .lock()
+        .expect("mutex poisoned")
+        .values_mut()
+    {
+        if let Err(err) = store.commit_due() {
+            store.report_error("commit staged values of", err);
+        }
+    }
+}
+
+/// Removes expired keys from every store in the collection, reporting failures.
+fn sweep_all<R: Runtime>(app_handle: &AppHandle<R>) {
+    let collection = app_handle.state::<StoreCollection<R>>();
+
+    for store in collection
+        .stores
+        .lock()
+        .expect("mutex poisoned")
+        .values_mut()
+    {
+        if let Err(err) = store.sweep_expired() {
+            store.report_error("remove expired keys of", err);
+        }
+    }
+}
+
+/// Loads the stores at `paths` into the collection, skipping the ones that are already loaded.
+fn preload_stores<R: Runtime>(app_handle: &AppHandle<R>, paths: Vec<PathBuf>) {
+    let collection = app_handle.state::<StoreCollection<R>>();
+
+    for path in paths {
+        if collection
+            .stores
+            .lock()
+            .expect("mutex poisoned")
+            .contains_key(&collection_key(&path))
+        {
+            continue;
+        }
+
+        // load outside of the lock so commands aren't blocked by the disk read
+        let mut store = StoreBuilder::new(app_handle.clone(), normalize_path(&path)).build();
+        if let Err(err) = store.load() {
+            warn!(
+                "Failed to preload store {:?} from disk: {}. Falling back to default values.",
+                path, err
+            );
+        }
+        store.save_on_change = collection.save_on_change;
+        store.save_queue = Some(collection.save_queue.clone());
+        collection
+            .stores
+            .lock()
+            .expect("mutex poisoned")
+            .entry(collection_key(&path))
+            .or_insert(store);
+    }
+}
+
+/// Window events that cause all stores to be saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WindowSaveTrigger {
+    /// The last focused window of the app lost focus.
+    Blur,
+    /// A window was minimized.
+    Minimize,
+}
+
+/// A store registered with [`Builder::store`], either already built or built during plugin initialization.
+pub enum StoreRegistration<R: Runtime> {
+    Built(Store<R>),
+    Deferred(StoreBuilder<R>),
+}
+
+impl<R: Runtime> From<Store<R>> for StoreRegistration<R> {
+    fn from(store: Store<R>) -> Self {
+        Self::Built(store)
+    }
+}
+
+impl<R: Runtime> From<StoreBuilder<R>> for StoreRegistration<R> {
+    fn from(builder: StoreBuilder<R>) -> Self {
+        Self::Deferred(builder)
+    }
 }
 
 // #[derive(Default)]
 pub struct Builder<R: Runtime> {
     stores: HashMap<PathBuf, Store<R>>,
+    deferred_stores: Vec<StoreBuilder<R>>,
     frozen: bool,
+    window_save_triggers: HashSet<WindowSaveTrigger>,
+    window_state: Option<PathBuf>,
+    preload: Vec<PathBuf>,
+    presets: HashMap<String, HashMap<String, JsonValue>>,
+    sweep_interval: Option<Duration>,
+    counter_flush_interval: Duration,
+    verify_on_startup: bool,
+    save_on_change: bool,
+    instance_lock: bool,
+    unload_grace: Option<Duration>,
+    deep_links: DeepLinks,
 }
 
 impl<R: Runtime> Default for Builder<R> {
     fn default() -> Self {
         Self {
             stores: Default::default(),
+            deferred_stores: Default::default(),
             frozen: false,
+            window_save_triggers: Default::default(),
+            window_state: None,
+            preload: Default::default(),
+            presets: Default::default(),
+            sweep_interval: None,
+            counter_flush_interval: COUNTER_FLUSH_INTERVAL,
+            verify_on_startup: false,
+            save_on_change: false,
+            instance_lock: false,
+            unload_grace: None,
+            deep_links: Default::default(),
         }
     }
 }
 
 impl<R: Runtime> Builder<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
     /// Registers a store with the plugin.
     ///
+    /// Accepts a built [`Store`], or a [`StoreBuilder`] created with [`StoreBuilder::from_path`] which is
+    /// built once the app exists, so stores with defaults and serializers can be declared up front.
+    /// If a store was already registered at the same path, the new one is ignored with a warning.
+    ///
     /// # Examples
     ///
     /// ```
@@ -215,8 +1902,11 @@ impl<R: Runtime> Builder<R> {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn store(mut self, store: Store<R>) -> Self {
-        self.stores.insert(store.path.clone(), store);
+    pub fn store(mut self, store: impl Into<StoreRegistration<R>>) -> Self {
+        match store.into() {
+            StoreRegistration::Built(store) => insert_registered(&mut self.stores, store),
+            StoreRegistration::Deferred(builder) => self.deferred_stores.push(builder),
+        }
         self
     }
 
@@ -236,10 +1926,9 @@ impl<R: Runtime> Builder<R> {
     /// # }
     /// ```
     pub fn stores<T: IntoIterator<Item = Store<R>>>(mut self, stores: T) -> Self {
-        self.stores = stores
-            .into_iter()
-            .map(|store| (store.path.clone(), store))
-            .collect();
+        for store in stores {
+            insert_registered(&mut self.stores, store);
+        }
         self
     }
 
@@ -265,6 +1954,269 @@ impl<R: Runtime> Builder<R> {
         self
     }
 
+    /// Opens all stores read-only if another instance of the app is already running.
+    ///
+    /// The first instance marks itself as the owner of the store files by locking a file in the app data
+    /// directory before any store is loaded. Stores of later instances reject modifications with [`Error::ReadOnly`]
+    /// and are never saved, instead of both instances overwriting each other's changes. The operating system releases
+    /// the lock when the first instance exits, even if it crashed, so the next instance to start takes over.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::PluginBuilder;
+    ///
+    /// let builder = PluginBuilder::default().read_only_secondary_instances();
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_only_secondary_instances(mut self) -> Self {
+        self.instance_lock = true;
+        self
+    }
+
+    /// Loads the given stores on a background thread during plugin initialization.
+    ///
+    /// This way the first access from the frontend doesn't have to wait for the disk read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::PluginBuilder;
+    ///
+    /// let builder = PluginBuilder::default().preload(["settings.json", "cache.json"]);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn preload<P: Into<PathBuf>, T: IntoIterator<Item = P>>(mut self, paths: T) -> Self {
+        self.preload.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// Registers a named preset of key-value pairs that can be applied to any store with `apply_preset`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::PluginBuilder;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut dark = HashMap::new();
+    /// dark.insert("theme".to_string(), "dark".into());
+    ///
+    /// let builder = PluginBuilder::default().preset("dark", dark);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn preset(mut self, name: impl Into<String>, entries: HashMap<String, JsonValue>) -> Self {
+        self.presets.insert(name.into(), entries);
+        self
+    }
+
+    /// Periodically removes expired keys of all stores in the background, emitting delete events.
+    ///
+    /// Keys expire if they were inserted with a TTL, e.g. through [`Store::insert_with_ttl`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::PluginBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let builder = PluginBuilder::default().sweep_interval(Duration::from_secs(60));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn sweep_interval(mut self, interval: Duration) -> Self {
+        self.sweep_interval = Some(interval);
+        self
+    }
+
+    /// Sets how often the increments of [`StoreCollection::counter_add`] are written to their stores. Defaults to 30 seconds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::PluginBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let builder = PluginBuilder::default().counter_flush_interval(Duration::from_secs(60));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn counter_flush_interval(mut self, interval: Duration) -> Self {
+        self.counter_flush_interval = interval;
+        self
+    }
+
+    /// Runs [`StoreCollection::verify_all`] when the plugin is initialized, quarantining broken store files
+    /// and emitting a `store://verified` event with the outcome.
+    ///
+    /// Webviews usually start listening after the event was emitted, so they should call `verifyAll` instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::PluginBuilder;
+    ///
+    /// let builder = PluginBuilder::default().verify_on_startup();
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify_on_startup(mut self) -> Self {
+        self.verify_on_startup = true;
+        self
+    }
+
+    /// Saves every store of the collection right after each modification, see [`StoreBuilder::save_on_change`].
+    ///
+    /// Applies to registered stores as well as the ones opened by the frontend, so settings survive the app
+    /// being killed before the save on exit runs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::PluginBuilder;
+    ///
+    /// let builder = PluginBuilder::default().save_on_change();
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn save_on_change(mut self) -> Self {
+        self.save_on_change = true;
+        self
+    }
+
+    /// Unloads stores opened with `Store.open` in the frontend once every webview that opened them was destroyed
+    /// and `grace` has passed without another webview opening them. Unsaved changes are saved first.
+    ///
+    /// Keeps memory bounded in apps that open many transient document windows. Stores registered with
+    /// [`Self::store`] stay loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::PluginBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let builder = PluginBuilder::default().unload_unreferenced(Duration::from_secs(30));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn unload_unreferenced(mut self, grace: Duration) -> Self {
+        self.unload_grace = Some(grace);
+        self
+    }
+
+    /// Allows deep links handled with [`StoreCollection::handle_deep_link`] to write the keys of the store at `path`
+    /// selected by `keys`, e.g. for "click to apply configuration" links in onboarding emails.
+    ///
+    /// Deep link updates are disabled for stores that weren't allowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{KeyFilter, PluginBuilder};
+    ///
+    /// let builder = PluginBuilder::default()
+    ///   .allow_deep_link_updates("settings.json", KeyFilter::default().include("theme").include("locale"));
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn allow_deep_link_updates(mut self, path: impl AsRef<Path>, keys: KeyFilter) -> Self {
+        self.deep_links
+            .allowed
+            .insert(collection_key(path.as_ref()), keys);
+        self
+    }
+
+    /// Calls `confirm` before applying the writes of a deep link, which are dropped unless it returns `true`,
+    /// e.g. to ask the user with a blocking dialog.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::PluginBuilder;
+    ///
+    /// let builder = PluginBuilder::default()
+    ///   .confirm_deep_link_updates(|update| update.entries.len() <= 3);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn confirm_deep_link_updates<F: Fn(&DeepLinkUpdate) -> bool + Send + Sync + 'static>(
+        mut self,
+        confirm: F,
+    ) -> Self {
+        self.deep_links.confirm = Some(Arc::new(confirm));
+        self
+    }
+
+    /// Persists the size, position and maximized state of every window in the store at `path`, keyed by
+    /// the window's label, and restores them when a window with the same label is created again.
+    ///
+    /// The state is saved when a window is closed and when the app exits. Windows are restored once their webview
+    /// is ready, so create them hidden and show them afterwards to avoid a visible jump.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::PluginBuilder;
+    ///
+    /// let builder = PluginBuilder::default().window_state("window-state.json");
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn window_state(mut self, path: impl Into<PathBuf>) -> Self {
+        self.window_state = Some(path.into());
+        self
+    }
+
+    /// Saves all stores when one of the given window events occurs.
+    ///
+    /// This sits between saving on every change and only saving on exit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{PluginBuilder, WindowSaveTrigger};
+    ///
+    /// let builder = PluginBuilder::default()
+    ///   .save_on_window_events([WindowSaveTrigger::Blur, WindowSaveTrigger::Minimize]);
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn save_on_window_events<T: IntoIterator<Item = WindowSaveTrigger>>(
+        mut self,
+        triggers: T,
+    ) -> Self {
+        self.window_save_triggers.extend(triggers);
+        self
+    }
+
     /// Builds the plugin.
     ///
     /// # Examples
@@ -282,38 +2234,281 @@ impl<R: Runtime> Builder<R> {
     /// # }
     /// ```
     pub fn build(mut self) -> TauriPlugin<R> {
+        let window_save_triggers = std::mem::take(&mut self.window_save_triggers);
+        let blur_check_pending = Arc::new(AtomicBool::new(false));
+        let window_state = self.window_state.take();
+        let restore_window_state = window_state.clone();
+        let preload = std::mem::take(&mut self.preload);
+        let sweep_interval = self.sweep_interval;
+        let counter_flush_interval = self.counter_flush_interval;
+        let lock_instance = self.instance_lock;
+        let unload_grace = self.unload_grace;
+        let verify_on_startup = self.verify_on_startup;
+        let save_on_change = self.save_on_change;
+
         plugin::Builder::new("store")
             .invoke_handler(tauri::generate_handler![
-                set, get, has, delete, clear, reset, keys, values, length, entries, load, save
+                set,
+                set_with_ttl,
+                compare_and_set,
+                get,
+                wait_for,
+                has,
+                type_of,
+                delete,
+                writer,
+                transfer_writer,
+                clear,
+                reset,
+                add_defaults,
+                reset_key,
+                overridden_keys,
+                keys,
+                keys_sorted,
+                values,
+                length,
+                entries,
+                cursor_open,
+                cursor_next,
+                cursor_close,
+                begin_edit,
+                edit_set,
+                edit_get,
+                commit_edit,
+                discard_edit,
+                find_by,
+                find_range,
+                aggregate,
+                search,
+                checksum,
+                diff,
+                import_map,
+                validate_import,
+                apply_preset,
+                flag,
+                client_id,
+                queue_push,
+                queue_pop_batch,
+                queue_ack,
+                queue_requeue,
+                queue_len,
+                keymap,
+                keymap_set,
+                keymap_remove,
+                cache_put,
+                cache_get,
+                first_run,
+                mark_step_done,
+                is_step_done,
+                reset_onboarding,
+                acquire_lock,
+                release_lock,
+                lock_status,
+                history,
+                persistence_status,
+                export,
+                export_redacted,
+                prune,
+                diagnostics,
+                counter_add,
+                counter_get,
+                verify_all,
+                load,
+                load_keys,
+                open,
+                repair,
+                compact,
+                save,
+                save_group,
+                reload_group
             ])
             .setup(move |app_handle| {
-                for (path, store) in self.stores.iter_mut() {
-                    // ignore loading errors, just use the default
+                for builder in self.deferred_stores {
+                    let store = builder.app(app_handle.clone()).build();
+                    insert_registered(&mut self.stores, store);
+                }
+
+                // taken before the stores load, so a secondary instance never writes to them
+                let mut instance_lock = None;
+                if lock_instance {
+                    let app_dir = app_handle
+                        .path_resolver()
+                        .app_data_dir()
+                        .expect("failed to resolve app dir");
+                    match InstanceLock::acquire(&app_dir) {
+                        Ok(Some(lock)) => instance_lock = Some(lock),
+                        Ok(None) => {
+                            for store in self.stores.values_mut() {
+                                store.read_only = true;
+                            }
+                        }
+                        Err(err) => warn!("Failed to acquire the store instance lock: {}", err),
+                    }
+                }
+                let read_only = lock_instance && instance_lock.is_none();
+
+                let save_queue = SaveQueue::start(app_handle);
+                for store in self.stores.values_mut() {
+                    // fall back to the default values unless the store's error policy says otherwise
                     if let Err(err) = store.load() {
-                        warn!(
-              "Failed to load store {:?} from disk: {}. Falling back to default values.",
-              path, err
-            );
+                        store.report_error("load", err);
                     }
+                    store.save_on_change |= save_on_change;
+                    store.save_queue = Some(save_queue.clone());
                 }
 
+                let registered = Mutex::new(self.stores.keys().cloned().collect());
+
+                let timed_saves = self.stores.values().any(|store| {
+                    matches!(
+                        store.save_strategy(),
+                        SaveStrategy::Debounced(_) | SaveStrategy::Interval(_)
+                    )
+                });
+                let commit_interval = self
+                    .stores
+                    .values()
+                    .filter_map(Store::coalesce_interval)
+                    .min();
+
                 app_handle.manage(StoreCollection {
                     stores: Mutex::new(self.stores),
                     frozen: self.frozen,
+                    presets: self.presets,
+                    read_only,
+                    save_on_change,
+                    instance_lock,
+                    cursors: Default::default(),
+                    next_cursor: Default::default(),
+                    edits: Default::default(),
+                    next_edit: Default::default(),
+                    references: Default::default(),
+                    registered,
+                    unload_grace,
+                    replications: Default::default(),
+                    deep_links: self.deep_links,
+                    counters: Default::default(),
+                    counter_flush_interval,
+                    counter_timer: Once::new(),
+                    #[cfg(feature = "tray")]
+                    tray_items: Default::default(),
+                    save_timer: Once::new(),
+                    save_queue,
                 });
 
+                if verify_on_startup {
+                    app_handle
+                        .state::<StoreCollection<R>>()
+                        .verify_all(app_handle);
+                }
+
+                if !preload.is_empty() {
+                    let app_handle = app_handle.clone();
+                    std::thread::spawn(move || preload_stores(&app_handle, preload));
+                }
+
+                if timed_saves {
+                    app_handle
+                        .state::<StoreCollection<R>>()
+                        .start_save_timer(app_handle);
+                }
+
+                if let Some(interval) = commit_interval {
+                    let app_handle = app_handle.clone();
+                    std::thread::spawn(move || loop {
+                        std::thread::sleep(interval);
+                        commit_due_all(&app_handle);
+                    });
+                }
+
+                if let Some(interval) = sweep_interval {
+                    let app_handle = app_handle.clone();
+                    std::thread::spawn(move || loop {
+                        std::thread::sleep(interval);
+                        sweep_all(&app_handle);
+                    });
+                }
+
                 Ok(())
             })
-            .on_event(|app_handle, event| {
-                if let RunEvent::Exit = event {
+            .on_webview_ready(move |window| {
+                if let Some(path) = &restore_window_state {
+                    window_state::restore(&window, path);
+                }
+            })
+            .on_page_load(|window, _| {
+                // the previous page may have been reloaded between `set` and `save`
+                flush_frontend_owned(&window.app_handle());
+            })
+            .on_event(move |app_handle, event| match event {
+                RunEvent::Exit => {
+                    // queued saves finish first, so they can't overwrite the final contents afterwards
+                    app_handle.state::<StoreCollection<R>>().save_queue.close();
+                    app_handle
+                        .state::<StoreCollection<R>>()
+                        .flush_counters(app_handle);
+                    save_all(app_handle);
                     let collection = app_handle.state::<StoreCollection<R>>();
-
-                    for store in collection.stores.lock().expect("mutex poisoned").values() {
-                        if let Err(err) = store.save() {
-                            eprintln!("failed to save store {:?} with error {:?}", store.path, err);
+                    if let Some(lock) = &collection.instance_lock {
+                        if let Err(err) = lock.release() {
+                            warn!("Failed to release the store instance lock: {}", err);
+                        }
+                    }
+                }
+                RunEvent::ExitRequested { .. } => {
+                    if let Some(path) = &window_state {
+                        for label in app_handle.windows().keys() {
+                            window_state::save(app_handle, path, label);
+                        }
+                    }
+                }
+                RunEvent::WindowEvent { label, event, .. } => {
+                    if let (WindowEvent::CloseRequested { .. }, Some(path)) = (event, &window_state)
+                    {
+                        window_state::save(app_handle, path, label);
+                    }
+                    if let WindowEvent::Destroyed = event {
+                        app_handle
+                            .state::<StoreCollection<R>>()
+                            .release_window(app_handle, label);
+                    }
+                    let triggered = match event {
+                        WindowEvent::Focused(false)
+                            if window_save_triggers.contains(&WindowSaveTrigger::Blur) =>
+                        {
+                            // focus moving between windows of the app blurs one before focusing the other,
+                            // so the windows are checked once focus has settled
+                            if !blur_check_pending.swap(true, Ordering::SeqCst) {
+                                let app_handle = app_handle.clone();
+                                let blur_check_pending = blur_check_pending.clone();
+                                std::thread::spawn(move || {
+                                    std::thread::sleep(BLUR_SETTLE_DELAY);
+                                    blur_check_pending.store(false, Ordering::SeqCst);
+                                    if !app_handle
+                                        .windows()
+                                        .values()
+                                        .any(|window| window.is_focused().unwrap_or(false))
+                                    {
+                                        save_all(&app_handle);
+                                    }
+                                });
+                            }
+                            false
+                        }
+                        WindowEvent::Resized(_) => {
+                            window_save_triggers.contains(&WindowSaveTrigger::Minimize)
+                                && app_handle
+                                    .get_window(label)
+                                    .and_then(|window| window.is_minimized().ok())
+                                    .unwrap_or(false)
                         }
+                        _ => false,
+                    };
+                    if triggered {
+                        save_all(app_handle);
                     }
                 }
+                _ => {}
             })
             .build()
     }
@@ -0,0 +1,110 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{LockPayload, Store, LOCKED_EVENT, PAYLOAD_SCHEMA, UNLOCKED_EVENT};
+use log::warn;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tauri::Runtime;
+
+/// A named lease held by one owner until it is released or expires. Only kept in memory.
+#[derive(Debug, Clone)]
+pub(crate) struct Lease {
+    owner: String,
+    expires_at: Instant,
+}
+
+/// A lease that is currently held, as returned by [`Store::lock_status`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockStatus {
+    pub name: String,
+    pub owner: String,
+    /// Milliseconds until the lease expires unless it is renewed.
+    pub expires_in_ms: u64,
+}
+
+impl<R: Runtime> Store<R> {
+    /// Acquires the lease `name` for `owner` for the duration of `ttl`, returning `false` if someone else holds it.
+    ///
+    /// Acquiring a lease already held by `owner` renews it. Leases let multiple windows coordinate
+    /// exclusive access to a section of the store; they are advisory and don't prevent writes.
+    ///
+    /// Emits a `store://locked` event unless the lease was renewed.
+    pub fn acquire_lock(&mut self, name: &str, owner: &str, ttl: Duration) -> bool {
+        let now = Instant::now();
+        let renewed = match self.leases.get(name) {
+            Some(lease) if lease.owner != owner && lease.expires_at > now => return false,
+            Some(lease) => lease.owner == owner && lease.expires_at > now,
+            None => false,
+        };
+        self.leases.insert(
+            name.to_string(),
+            Lease {
+                owner: owner.to_string(),
+                expires_at: now + ttl,
+            },
+        );
+        if !renewed {
+            self.emit_lock_event(LOCKED_EVENT, name, Some(owner), Some(ttl));
+        }
+        true
+    }
+
+    /// Releases the lease `name` if it is held by `owner`, returning whether it was released.
+    ///
+    /// Emits a `store://unlocked` event. Expired leases don't emit one, listeners can rely on the
+    /// TTL of the `store://locked` event instead.
+    pub fn release_lock(&mut self, name: &str, owner: &str) -> bool {
+        match self.leases.get(name) {
+            Some(lease) if lease.owner == owner => {
+                self.leases.remove(name);
+                self.emit_lock_event(UNLOCKED_EVENT, name, None, None);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the current owner of the lease `name`, if it is held and hasn't expired.
+    pub fn lock_owner(&self, name: &str) -> Option<&str> {
+        self.leases
+            .get(name)
+            .filter(|lease| lease.expires_at > Instant::now())
+            .map(|lease| lease.owner.as_str())
+    }
+
+    /// Returns all leases that are currently held, sorted by name.
+    pub fn lock_status(&self) -> Vec<LockStatus> {
+        let now = Instant::now();
+        let mut status: Vec<LockStatus> = self
+            .leases
+            .iter()
+            .filter(|(_, lease)| lease.expires_at > now)
+            .map(|(name, lease)| LockStatus {
+                name: name.clone(),
+                owner: lease.owner.clone(),
+                expires_in_ms: (lease.expires_at - now).as_millis() as u64,
+            })
+            .collect();
+        status.sort_by(|a, b| a.name.cmp(&b.name));
+        status
+    }
+
+    fn emit_lock_event(&self, event: &str, name: &str, owner: Option<&str>, ttl: Option<Duration>) {
+        let payload = LockPayload {
+            schema: PAYLOAD_SCHEMA,
+            path: &self.path,
+            name,
+            owner,
+            ttl_ms: ttl.map(|ttl| ttl.as_millis() as u64),
+        };
+        if let Err(err) = self.emit(event, payload) {
+            warn!(
+                "Failed to emit lock event for store {:?}: {}",
+                self.path, err
+            );
+        }
+    }
+}
@@ -0,0 +1,276 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    envelope,
+    store::{default_deserialize, DeserializeFn},
+    Error, JsonValue, Store, EXPIRATIONS_KEY, META_KEY,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    time::SystemTime,
+};
+use tauri::Runtime;
+
+/// A line of the change log written by stores with [`StoreBuilder::change_log`](crate::StoreBuilder::change_log)
+/// or [`StoreBuilder::journal`](crate::StoreBuilder::journal).
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub(crate) enum Change {
+    Set { key: String, value: JsonValue },
+    Remove { key: String },
+}
+
+/// Returns the path of the change log of the store file at `store_path`.
+pub(crate) fn change_log_path(store_path: &Path) -> PathBuf {
+    let mut path = store_path.as_os_str().to_owned();
+    path.push(".changes");
+    path.into()
+}
+
+/// Parses the complete lines of a change log, returning the changes and the number of bytes they span.
+///
+/// An incomplete last line, e.g. because the app is in the middle of appending it, is left for later.
+fn parse_changes(bytes: &[u8]) -> Result<(Vec<Change>, usize), Error> {
+    let complete = bytes
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(0, |newline| newline + 1);
+    let changes = bytes[..complete]
+        .split(|byte| *byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(serde_json::from_slice)
+        .collect::<Result<_, _>>()?;
+    Ok((changes, complete))
+}
+
+/// Returns the size and modification time of the file at `path`, or `None` if it doesn't exist.
+fn file_info(path: &Path) -> io::Result<Option<(u64, SystemTime)>> {
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(Some((metadata.len(), metadata.modified()?))),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+impl<R: Runtime> Store<R> {
+    /// Appends changes to the store's change log, waiting for them to reach the disk if `sync` is set.
+    pub(crate) fn append_changes<'a>(
+        &self,
+        changes: impl IntoIterator<Item = (&'a str, Option<&'a JsonValue>)>,
+        sync: bool,
+    ) -> Result<(), Error> {
+        let mut lines = Vec::new();
+        for (key, value) in changes {
+            let change = match value {
+                Some(value) => {
+                    #[cfg(feature = "encryption")]
+                    let value = match &self.encryption {
+                        Some(encryption) if encryption.applies(key) => {
+                            encryption.encrypt(key, value)?
+                        }
+                        _ => value.clone(),
+                    };
+                    #[cfg(not(feature = "encryption"))]
+                    let value = value.clone();
+                    Change::Set {
+                        key: key.to_string(),
+                        value,
+                    }
+                }
+                None => Change::Remove {
+                    key: key.to_string(),
+                },
+            };
+            serde_json::to_writer(&mut lines, &change)?;
+            lines.push(b'\n');
+        }
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let store_path = self.store_path();
+        fs::create_dir_all(store_path.parent().expect("invalid store path"))?;
+        let mut log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(change_log_path(&store_path))?;
+        log.write_all(&lines)?;
+        if sync {
+            log.sync_data()?;
+        }
+        self.log_len
+            .fetch_add(lines.len() as u64, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Returns the changes recorded in the store's change log since it was last emptied.
+    pub(crate) fn read_change_log(&self) -> Result<Vec<Change>, Error> {
+        match fs::read(change_log_path(&self.store_path())) {
+            // a torn last line is a change that was never acknowledged
+            Ok(bytes) => {
+                self.log_len.store(bytes.len() as u64, Ordering::Relaxed);
+                Ok(parse_changes(&bytes)?.0)
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Empties the store's change log once its changes are part of the store file.
+    pub(crate) fn truncate_change_log(&self) -> Result<(), Error> {
+        File::create(change_log_path(&self.store_path()))?;
+        self.log_len.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Whether the change log grew beyond the threshold set with
+    /// [`StoreBuilder::compact_after`](crate::StoreBuilder::compact_after).
+    pub(crate) fn compaction_due(&self) -> bool {
+        !self.read_only
+            && self
+                .compact_after
+                .map_or(false, |limit| self.log_len.load(Ordering::Relaxed) > limit)
+    }
+
+    /// Rewrites the store file from memory and empties its change log, dropping the log entries of
+    /// overwritten and removed keys.
+    ///
+    /// Stores without a change log or journal are simply saved.
+    pub fn compact(&self) -> Result<(), Error> {
+        self.save()
+    }
+}
+
+/// A read-only copy of a store that follows the changes made by the app from another process.
+///
+/// Lets a tray helper, sidecar or CLI companion read the settings of an app without an [`AppHandle`](tauri::AppHandle).
+/// The store must be built with [`StoreBuilder::change_log`](crate::StoreBuilder::change_log), otherwise
+/// the mirror only sees the changes once the store is saved. Values compressed with
+/// [`StoreBuilder::compress_values_above`](crate::StoreBuilder::compress_values_above) are returned as they are stored.
+///
+/// # Examples
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use tauri_plugin_store::StoreMirror;
+///
+/// let mut mirror = StoreMirror::open("/home/user/.local/share/com.example.app/settings.json")?;
+///
+/// loop {
+///   if mirror.refresh()? {
+///     println!("theme is now {:?}", mirror.get("theme"));
+///   }
+///   std::thread::sleep(std::time::Duration::from_secs(1));
+/// }
+/// # }
+/// ```
+pub struct StoreMirror {
+    path: PathBuf,
+    deserialize: DeserializeFn,
+    contents: HashMap<String, JsonValue>,
+    /// The modification time of the store file when it was last read.
+    modified: Option<SystemTime>,
+    /// The number of bytes of the change log that have been applied.
+    log_offset: u64,
+}
+
+impl StoreMirror {
+    /// Opens the store file at the absolute path `path`, which doesn't need to exist yet.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        Self::with_deserializer(path, default_deserialize)
+    }
+
+    /// Opens a store file written with a custom [`StoreBuilder::serialize`](crate::StoreBuilder::serialize) function.
+    pub fn with_deserializer(
+        path: impl Into<PathBuf>,
+        deserialize: DeserializeFn,
+    ) -> Result<Self, Error> {
+        let mut mirror = Self {
+            path: path.into(),
+            deserialize,
+            contents: HashMap::new(),
+            modified: None,
+            log_offset: 0,
+        };
+        mirror.refresh()?;
+        Ok(mirror)
+    }
+
+    /// Applies the changes made by the app since the last call, returning `true` if there were any.
+    pub fn refresh(&mut self) -> Result<bool, Error> {
+        let modified = file_info(&self.path)?.map(|(_, modified)| modified);
+        let log_path = change_log_path(&self.path);
+        let log_len = file_info(&log_path)?.map_or(0, |(len, _)| len);
+
+        let mut changed = false;
+        // the log is emptied whenever the store is saved, so its entries are now part of the store file
+        if modified != self.modified || log_len < self.log_offset {
+            self.contents = match modified {
+                Some(_) => {
+                    let contents =
+                        (self.deserialize)(&fs::read(&self.path)?).map_err(Error::Deserialize)?;
+                    envelope::unwrap(contents)?.0
+                }
+                None => HashMap::new(),
+            };
+            self.contents.remove(META_KEY);
+            self.contents.remove(EXPIRATIONS_KEY);
+            self.modified = modified;
+            self.log_offset = 0;
+            changed = true;
+        }
+
+        if log_len > self.log_offset {
+            let mut log = File::open(&log_path)?;
+            log.seek(SeekFrom::Start(self.log_offset))?;
+            let mut bytes = Vec::new();
+            log.read_to_end(&mut bytes)?;
+
+            let (changes, complete) = parse_changes(&bytes)?;
+            for change in changes {
+                match change {
+                    Change::Set { key, value } => {
+                        self.contents.insert(key, value);
+                    }
+                    Change::Remove { key } => {
+                        self.contents.remove(&key);
+                    }
+                }
+            }
+            self.log_offset += complete as u64;
+            changed |= complete > 0;
+        }
+
+        Ok(changed)
+    }
+
+    pub fn get(&self, key: impl AsRef<str>) -> Option<&JsonValue> {
+        self.contents.get(key.as_ref())
+    }
+
+    pub fn has(&self, key: impl AsRef<str>) -> bool {
+        self.contents.contains_key(key.as_ref())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.contents.keys()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &JsonValue)> {
+        self.contents.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.contents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_empty()
+    }
+}
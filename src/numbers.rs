@@ -0,0 +1,135 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{JsonValue, Store};
+use serde_json::Number;
+use tauri::Runtime;
+
+/// The largest integer a JavaScript `number` represents exactly, `Number.MAX_SAFE_INTEGER`.
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// How a [`Store`] stores numbers, see [`StoreBuilder::number_mode`](crate::StoreBuilder::number_mode).
+///
+/// `i64` and `u64` values always keep their precision in Rust and in the store file. Numbers with more digits
+/// keep theirs with the `arbitrary-precision` feature, otherwise they are rounded to the nearest `f64`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NumberMode {
+    /// Store numbers as they are. Integers beyond `Number.MAX_SAFE_INTEGER` lose precision in the frontend.
+    #[default]
+    Preserve,
+    /// Store integers beyond `Number.MAX_SAFE_INTEGER`, e.g. 64-bit IDs, as decimal strings,
+    /// so they survive a round-trip through the frontend. Applies to written values as well as loaded ones,
+    /// e.g. from files saved before the mode was set.
+    ///
+    /// Rust code reading them has to accept strings, e.g. with `serde_with::DisplayFromStr`.
+    StringifyUnsafe,
+}
+
+fn is_js_safe(number: &Number) -> bool {
+    if let Some(number) = number.as_i64() {
+        number.unsigned_abs() <= MAX_SAFE_INTEGER
+    } else if let Some(number) = number.as_u64() {
+        number <= MAX_SAFE_INTEGER
+    } else {
+        // floats, integers wider than 64 bits only exist with `arbitrary-precision`
+        number.is_f64()
+    }
+}
+
+/// Replaces the integers in `value` that aren't safe in JavaScript with strings, returning whether there were any.
+pub(crate) fn stringify_unsafe(value: &mut JsonValue) -> bool {
+    match value {
+        JsonValue::Number(number) if !is_js_safe(number) => {
+            *value = JsonValue::String(number.to_string());
+            true
+        }
+        JsonValue::Array(array) => array
+            .iter_mut()
+            .fold(false, |changed, value| stringify_unsafe(value) || changed),
+        JsonValue::Object(object) => object
+            .values_mut()
+            .fold(false, |changed, value| stringify_unsafe(value) || changed),
+        _ => false,
+    }
+}
+
+/// Whether the JSON `raw` may contain an integer that isn't safe in JavaScript, which has at least 16 digits.
+pub(crate) fn may_hold_unsafe_integer(raw: &str) -> bool {
+    let mut digits = 0;
+    raw.bytes().any(|byte| {
+        digits = if byte.is_ascii_digit() { digits + 1 } else { 0 };
+        digits >= 16
+    })
+}
+
+impl<R: Runtime> Store<R> {
+    /// Applies the store's [`NumberMode`] to a value about to be written.
+    pub(crate) fn normalize_numbers(&self, mut value: JsonValue) -> JsonValue {
+        if self.number_mode == NumberMode::StringifyUnsafe {
+            stringify_unsafe(&mut value);
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn stringifies_unsafe_integers() {
+        let mut value = json!(9_007_199_254_740_993_u64);
+        assert!(stringify_unsafe(&mut value));
+        assert_eq!(value, json!("9007199254740993"));
+
+        let mut value = json!(-9_007_199_254_740_993_i64);
+        assert!(stringify_unsafe(&mut value));
+        assert_eq!(value, json!("-9007199254740993"));
+
+        let mut value = json!(u64::MAX);
+        assert!(stringify_unsafe(&mut value));
+        assert_eq!(value, json!("18446744073709551615"));
+    }
+
+    #[test]
+    fn keeps_safe_numbers() {
+        for number in [
+            json!(0),
+            json!(MAX_SAFE_INTEGER),
+            json!(-(MAX_SAFE_INTEGER as i64)),
+            json!(1.5),
+            json!(1e300),
+        ] {
+            let mut value = number.clone();
+            assert!(!stringify_unsafe(&mut value));
+            assert_eq!(value, number);
+        }
+    }
+
+    #[test]
+    fn stringifies_nested_integers() {
+        let mut value = json!({
+            "ids": [1, 9_007_199_254_740_993_u64, "2"],
+            "user": { "id": 9_007_199_254_740_994_u64, "age": 30 },
+        });
+        assert!(stringify_unsafe(&mut value));
+        assert_eq!(
+            value,
+            json!({
+                "ids": [1, "9007199254740993", "2"],
+                "user": { "id": "9007199254740994", "age": 30 },
+            })
+        );
+    }
+
+    #[test]
+    fn detects_long_digit_runs() {
+        assert!(!may_hold_unsafe_integer(r#"{"a": 123456789012345}"#));
+        assert!(!may_hold_unsafe_integer(r#"[123456789, 1234567]"#));
+        assert!(may_hold_unsafe_integer(r#"{"a": 9007199254740993}"#));
+        // a false positive is only a wasted parse
+        assert!(may_hold_unsafe_integer(r#""1234567890123456""#));
+    }
+}
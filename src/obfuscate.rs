@@ -0,0 +1,58 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! A file format that keeps users from casually editing a store, see
+//! [`StoreBuilder::obfuscated`](crate::StoreBuilder::obfuscated).
+//!
+//! This is not encryption: anyone with the plugin's source can restore the contents.
+
+use crate::{store::fnv1a, JsonValue};
+use std::collections::HashMap;
+
+pub(crate) const MAGIC: &[u8; 4] = b"TSOB";
+const SALT_LEN: usize = 8;
+
+/// XORs `bytes` with a keystream derived from `salt`.
+fn apply_keystream(salt: &[u8], bytes: &mut [u8]) {
+    // splitmix64
+    let mut state = fnv1a(salt);
+    for chunk in bytes.chunks_mut(8) {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        for (byte, key) in chunk.iter_mut().zip(z.to_le_bytes()) {
+            *byte ^= key;
+        }
+    }
+}
+
+pub(crate) fn obfuscated_serialize(
+    cache: &HashMap<String, JsonValue>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut salt = [0; SALT_LEN];
+    getrandom::getrandom(&mut salt)?;
+    let mut body = serde_json::to_vec(cache)?;
+    apply_keystream(&salt, &mut body);
+
+    let mut bytes = Vec::with_capacity(MAGIC.len() + SALT_LEN + body.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&salt);
+    bytes.extend_from_slice(&body);
+    Ok(bytes)
+}
+
+pub(crate) fn obfuscated_deserialize(
+    bytes: &[u8],
+) -> Result<HashMap<String, JsonValue>, Box<dyn std::error::Error + Send + Sync>> {
+    let rest = bytes
+        .strip_prefix(MAGIC.as_slice())
+        .filter(|rest| rest.len() >= SALT_LEN)
+        .ok_or("not an obfuscated store file")?;
+    let (salt, body) = rest.split_at(SALT_LEN);
+    let mut body = body.to_vec();
+    apply_keystream(salt, &mut body);
+    Ok(serde_json::from_slice(&body)?)
+}
@@ -0,0 +1,77 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{Error, Store};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::Runtime;
+
+/// The key under which the first-run and onboarding state of a store is kept.
+pub const ONBOARDING_KEY: &str = "__onboarding";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OnboardingState {
+    /// Milliseconds since the Unix epoch at which [`Store::first_run`] was first called.
+    first_run_at: Option<u64>,
+    /// Milliseconds since the Unix epoch at which each step was marked as done.
+    #[serde(default)]
+    steps: BTreeMap<String, u64>,
+}
+
+impl<R: Runtime> Store<R> {
+    fn onboarding_state(&self) -> Result<OnboardingState, Error> {
+        match self.get(ONBOARDING_KEY) {
+            Some(value) => Ok(OnboardingState::deserialize(value)?),
+            None => Ok(OnboardingState::default()),
+        }
+    }
+
+    /// Writes the state and saves the store right away, so a step is never repeated after a crash.
+    fn set_onboarding_state(&mut self, state: &OnboardingState) -> Result<(), Error> {
+        self.insert(ONBOARDING_KEY.to_string(), serde_json::to_value(state)?)?;
+        self.save()
+    }
+
+    /// Returns `true` exactly once per store: the first time this is called.
+    ///
+    /// The state is kept under [`ONBOARDING_KEY`] and saved before this returns.
+    pub fn first_run(&mut self) -> Result<bool, Error> {
+        let mut state = self.onboarding_state()?;
+        if state.first_run_at.is_some() {
+            return Ok(false);
+        }
+        state.first_run_at = Some(self.clock.now_millis());
+        self.set_onboarding_state(&state)?;
+        Ok(true)
+    }
+
+    /// Marks the onboarding step `step` as done, returning `false` if it already was.
+    ///
+    /// The state is saved before this returns.
+    pub fn mark_step_done(&mut self, step: &str) -> Result<bool, Error> {
+        let mut state = self.onboarding_state()?;
+        if state.steps.contains_key(step) {
+            return Ok(false);
+        }
+        state
+            .steps
+            .insert(step.to_string(), self.clock.now_millis());
+        self.set_onboarding_state(&state)?;
+        Ok(true)
+    }
+
+    /// Returns `true` if the onboarding step `step` was marked as done.
+    pub fn is_step_done(&self, step: &str) -> Result<bool, Error> {
+        Ok(self.onboarding_state()?.steps.contains_key(step))
+    }
+
+    /// Forgets the first run and all completed onboarding steps, e.g. to replay the onboarding.
+    pub fn reset_onboarding(&mut self) -> Result<(), Error> {
+        if self.delete(ONBOARDING_KEY)? {
+            self.save()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{Error, JsonValue, Store};
+use tauri::Runtime;
+
+/// Returns whether `value` carries no data: `null`, an empty object or an empty array.
+pub(crate) fn is_prunable(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Null => true,
+        JsonValue::Object(object) => object.is_empty(),
+        JsonValue::Array(array) => array.is_empty(),
+        _ => false,
+    }
+}
+
+impl<R: Runtime> Store<R> {
+    /// Removes the keys whose values are `null`, empty objects or empty arrays, emitting a single `store://batch` event.
+    ///
+    /// Many frontends "delete" a key by setting it to `null`, which otherwise keeps it in the file forever.
+    /// Returns the removed keys, sorted.
+    pub fn prune(&mut self) -> Result<Vec<String>, Error> {
+        self.check_writable()?;
+        self.check_writer()?;
+        let mut pruned: Vec<String> = self
+            .entries()
+            .filter(|(_, value)| is_prunable(value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        pruned.sort();
+        // applied as one batch, so the store is saved once
+        self.apply_batch(pruned.iter().map(|key| (key.clone(), None)).collect())?;
+        Ok(pruned)
+    }
+}
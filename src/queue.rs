@@ -0,0 +1,94 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{Error, JsonValue, Store};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tauri::Runtime;
+
+/// An item of a queue stored with [`Store::queue_push`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueueItem {
+    pub id: u64,
+    pub value: JsonValue,
+}
+
+/// The persisted state of a queue, stored as the value of the queue's key.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueueState {
+    next_id: u64,
+    items: VecDeque<QueueItem>,
+    in_flight: Vec<QueueItem>,
+}
+
+impl<R: Runtime> Store<R> {
+    fn queue_state(&self, queue: &str) -> Result<QueueState, Error> {
+        match self.get(queue) {
+            Some(value) => Ok(QueueState::deserialize(value)?),
+            None => Ok(QueueState::default()),
+        }
+    }
+
+    fn set_queue_state(&mut self, queue: &str, state: &QueueState) -> Result<(), Error> {
+        self.insert(queue.to_string(), serde_json::to_value(state)?)
+    }
+
+    /// Appends `value` to the durable FIFO queue stored under the key `queue`, returning the id of the new item.
+    pub fn queue_push(&mut self, queue: &str, value: JsonValue) -> Result<u64, Error> {
+        let mut state = self.queue_state(queue)?;
+        let id = state.next_id;
+        state.next_id += 1;
+        state.items.push_back(QueueItem { id, value });
+        self.set_queue_state(queue, &state)?;
+        Ok(id)
+    }
+
+    /// Takes up to `max` items from the front of the queue.
+    ///
+    /// The items are moved to the queue's in-flight set until they are acknowledged with [`Self::queue_ack`],
+    /// and can be delivered again with [`Self::queue_requeue`], e.g. after a restart.
+    pub fn queue_pop_batch(&mut self, queue: &str, max: usize) -> Result<Vec<QueueItem>, Error> {
+        let mut state = self.queue_state(queue)?;
+        let count = max.min(state.items.len());
+        let batch: Vec<QueueItem> = state.items.drain(..count).collect();
+        if !batch.is_empty() {
+            state.in_flight.extend(batch.iter().cloned());
+            self.set_queue_state(queue, &state)?;
+        }
+        Ok(batch)
+    }
+
+    /// Removes processed items from the queue's in-flight set, returning how many were removed.
+    pub fn queue_ack(&mut self, queue: &str, ids: &[u64]) -> Result<usize, Error> {
+        let mut state = self.queue_state(queue)?;
+        let len = state.in_flight.len();
+        state.in_flight.retain(|item| !ids.contains(&item.id));
+        let removed = len - state.in_flight.len();
+        if removed > 0 {
+            self.set_queue_state(queue, &state)?;
+        }
+        Ok(removed)
+    }
+
+    /// Moves all unacknowledged in-flight items back to the front of the queue, returning how many were moved.
+    pub fn queue_requeue(&mut self, queue: &str) -> Result<usize, Error> {
+        let mut state = self.queue_state(queue)?;
+        let mut in_flight = std::mem::take(&mut state.in_flight);
+        let count = in_flight.len();
+        if count > 0 {
+            in_flight.sort_by_key(|item| item.id);
+            for item in in_flight.into_iter().rev() {
+                state.items.push_front(item);
+            }
+            self.set_queue_state(queue, &state)?;
+        }
+        Ok(count)
+    }
+
+    /// Returns the number of items waiting in the queue, not counting in-flight ones.
+    pub fn queue_len(&self, queue: &str) -> Result<usize, Error> {
+        Ok(self.queue_state(queue)?.items.len())
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use arc_swap::ArcSwap;
+use serde_json::Value as JsonValue;
+use std::{collections::HashMap, sync::Arc};
+
+/// A cheap, cloneable read handle to the contents of a [`Store`](crate::Store).
+///
+/// Reads never take the store mutex. The handle always sees the latest snapshot,
+/// which is replaced every time the store changes.
+///
+/// # Examples
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use tauri_plugin_store::StoreBuilder;
+///
+/// let mut store = StoreBuilder::new("store.bin".parse()?).build();
+/// let reader = store.reader();
+///
+/// std::thread::spawn(move || {
+///   let theme = reader.get("theme");
+/// });
+///
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct StoreReader {
+    snapshot: Arc<ArcSwap<HashMap<String, JsonValue>>>,
+}
+
+impl StoreReader {
+    pub(crate) fn new(contents: HashMap<String, JsonValue>) -> Self {
+        Self {
+            snapshot: Arc::new(ArcSwap::from_pointee(contents)),
+        }
+    }
+
+    pub(crate) fn publish(&self, contents: HashMap<String, JsonValue>) {
+        self.snapshot.store(Arc::new(contents));
+    }
+
+    /// Returns the current contents of the store.
+    pub fn snapshot(&self) -> Arc<HashMap<String, JsonValue>> {
+        self.snapshot.load_full()
+    }
+
+    /// Returns a copy of the value stored under `key`.
+    pub fn get(&self, key: impl AsRef<str>) -> Option<JsonValue> {
+        self.snapshot.load().get(key.as_ref()).cloned()
+    }
+
+    /// Returns `true` if the given `key` exists in the store.
+    pub fn has(&self, key: impl AsRef<str>) -> bool {
+        self.snapshot.load().contains_key(key.as_ref())
+    }
+}
+
+impl std::fmt::Debug for StoreReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StoreReader")
+            .field("snapshot", &self.snapshot.load())
+            .finish()
+    }
+}
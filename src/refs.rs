@@ -0,0 +1,90 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Tracking of the webviews that opened a store, to unload it once all of them are gone.
+
+use crate::{ensure_store, Error, SaveStrategy, StoreCollection};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Runtime};
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Opens the store at `path` on behalf of the webview labeled `window`.
+    ///
+    /// With [`Builder::unload_unreferenced`](crate::Builder::unload_unreferenced), the store is unloaded
+    /// once every webview that opened it was destroyed.
+    pub fn open(&self, app: &AppHandle<R>, path: &Path, window: &str) -> Result<(), Error> {
+        let key = {
+            let mut stores = self.stores.lock().expect("mutex poisoned");
+            ensure_store(app, self, &mut stores, path)?
+        };
+        if self.unload_grace.is_some() {
+            self.references
+                .lock()
+                .expect("mutex poisoned")
+                .entry(key)
+                .or_default()
+                .insert(window.to_string());
+        }
+        Ok(())
+    }
+
+    /// Drops the references of the destroyed webview labeled `window`, unloading the stores no other webview
+    /// refers to after the grace period.
+    pub(crate) fn release_window(&self, app: &AppHandle<R>, window: &str) {
+        let Some(grace) = self.unload_grace else {
+            return;
+        };
+        let mut unreferenced = Vec::new();
+        self.references
+            .lock()
+            .expect("mutex poisoned")
+            .retain(|key, windows| {
+                if windows.remove(window) && windows.is_empty() {
+                    unreferenced.push(key.clone());
+                    return false;
+                }
+                true
+            });
+        if unreferenced.is_empty() {
+            return;
+        }
+
+        if grace.is_zero() {
+            self.unload(unreferenced);
+        } else {
+            let app = app.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(grace);
+                app.state::<StoreCollection<R>>().unload(unreferenced);
+            });
+        }
+    }
+
+    /// Saves and removes the stores at `keys`, unless they were opened again or registered with the plugin.
+    fn unload(&self, keys: Vec<PathBuf>) {
+        let references = self.references.lock().expect("mutex poisoned");
+        let registered = self.registered.lock().expect("mutex poisoned");
+        let mut stores = self.stores.lock().expect("mutex poisoned");
+        for key in keys {
+            if references.contains_key(&key) || registered.contains(&key) {
+                continue;
+            }
+            let Some(store) = stores.get(&key) else {
+                continue;
+            };
+            if !self.read_only
+                && !store.is_read_only()
+                && store.save_strategy() != SaveStrategy::ExplicitOnly
+                && store.has_unsaved_changes()
+            {
+                // keep the store loaded rather than losing its changes
+                if let Err(err) = store.save() {
+                    store.report_error("save", err);
+                    continue;
+                }
+            }
+            stores.remove(&key);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Best-effort recovery of corrupt store files.
+
+use crate::{
+    envelope::{self, EnvelopeMeta},
+    Error, JsonValue, Store, EXPIRATIONS_KEY, META_KEY,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Map;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use tauri::Runtime;
+
+/// What [`Store::repair`] salvaged from a corrupt store file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairReport {
+    /// Whether the file was corrupt. If not, it was left untouched.
+    pub corrupt: bool,
+    /// The keys that were recovered.
+    pub recovered: Vec<String>,
+    /// The keys whose values couldn't be recovered. Nested keys are joined with `.`.
+    pub lost: Vec<String>,
+    /// The number of bytes that couldn't be attributed to any key, e.g. because a key itself was corrupt.
+    pub skipped_bytes: usize,
+    /// Where the corrupt file was moved to.
+    pub backup: Option<PathBuf>,
+}
+
+/// Returns the path the corrupt file of a store is moved to before it is repaired.
+pub(crate) fn backup_path(store_path: &Path) -> PathBuf {
+    let mut path = store_path.as_os_str().to_owned();
+    path.push(".corrupt");
+    path.into()
+}
+
+fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while bytes.get(pos).map_or(false, u8::is_ascii_whitespace) {
+        pos += 1;
+    }
+    pos
+}
+
+/// Parses a single JSON value at `pos`, returning it and the position after it.
+fn parse_at<T: DeserializeOwned>(bytes: &[u8], pos: usize) -> Option<(T, usize)> {
+    let mut values = serde_json::Deserializer::from_slice(&bytes[pos..]).into_iter::<T>();
+    match values.next() {
+        Some(Ok(value)) => Some((value, pos + values.byte_offset())),
+        _ => None,
+    }
+}
+
+/// Returns the position after the next `,` that is followed by what looks like a key.
+fn next_entry(bytes: &[u8], pos: usize) -> Option<usize> {
+    (pos..bytes.len())
+        .find(|&i| bytes[i] == b',' && bytes.get(skip_whitespace(bytes, i + 1)) == Some(&b'"'))
+        .map(|i| i + 1)
+}
+
+/// Parses the object at `pos` leniently, skipping entries that aren't valid JSON.
+///
+/// Returns the recovered entries and the position after the object, or the end of the input if it is truncated.
+fn salvage_object(
+    bytes: &[u8],
+    pos: usize,
+    prefix: &str,
+    report: &mut RepairReport,
+) -> Option<(Map<String, JsonValue>, usize)> {
+    let mut pos = skip_whitespace(bytes, pos);
+    if bytes.get(pos) != Some(&b'{') {
+        return None;
+    }
+    pos += 1;
+
+    let mut entries = Map::new();
+    loop {
+        pos = skip_whitespace(bytes, pos);
+        match bytes.get(pos) {
+            None => return Some((entries, pos)),
+            Some(b'}') => return Some((entries, pos + 1)),
+            _ => {}
+        }
+
+        let entry = parse_at::<String>(bytes, pos).and_then(|(key, after_key)| {
+            let colon = skip_whitespace(bytes, after_key);
+            if bytes.get(colon) != Some(&b':') {
+                return Some((key, None));
+            }
+            let value_pos = skip_whitespace(bytes, colon + 1);
+            let value = parse_at::<JsonValue>(bytes, value_pos).or_else(|| {
+                salvage_object(bytes, value_pos, &format!("{prefix}{key}."), report)
+                    .map(|(object, end)| (JsonValue::Object(object), end))
+            });
+            Some((key, value))
+        });
+
+        match entry {
+            Some((key, Some((value, end)))) => {
+                entries.insert(key, value);
+                pos = skip_whitespace(bytes, end);
+                match bytes.get(pos) {
+                    Some(b',') => pos += 1,
+                    Some(b'}') => return Some((entries, pos + 1)),
+                    None => return Some((entries, pos)),
+                    Some(_) => {}
+                }
+                continue;
+            }
+            Some((key, None)) => report.lost.push(format!("{prefix}{key}")),
+            None => {}
+        }
+
+        // resynchronize at the next entry
+        let next = next_entry(bytes, pos).unwrap_or(bytes.len());
+        report.skipped_bytes += next - pos;
+        pos = next;
+    }
+}
+
+/// Recovers what it can from the corrupt store file contents `bytes`, unwrapping an envelope.
+///
+/// The returned report lists the recovered and lost keys of the store, without the envelope's `data.` prefix.
+fn salvage(
+    bytes: &[u8],
+) -> Result<
+    (
+        HashMap<String, JsonValue>,
+        Option<EnvelopeMeta>,
+        RepairReport,
+    ),
+    Error,
+> {
+    let mut report = RepairReport {
+        corrupt: true,
+        ..Default::default()
+    };
+    let (entries, end) = salvage_object(bytes, 0, "", &mut report)
+        .ok_or_else(|| Error::Repair("the file doesn't contain a JSON object".into()))?;
+    report.skipped_bytes += bytes.len() - skip_whitespace(bytes, end);
+
+    let (values, meta) = envelope::unwrap(entries.into_iter().collect())?;
+    if meta.is_some() {
+        report.lost = report
+            .lost
+            .into_iter()
+            .filter_map(|key| key.strip_prefix("data.").map(String::from))
+            .collect();
+    }
+
+    report.recovered = values
+        .keys()
+        .filter(|key| *key != META_KEY && *key != EXPIRATIONS_KEY)
+        .cloned()
+        .collect();
+    report.recovered.sort();
+    Ok((values, meta, report))
+}
+
+impl<R: Runtime> Store<R> {
+    /// Attempts to recover the contents of a corrupt store file.
+    ///
+    /// The file is parsed leniently: values that aren't valid JSON are skipped and a truncated file is read up
+    /// to the last complete value. The recovered values are merged into the store like [`Self::load`] would,
+    /// the corrupt file is moved next to the store file with a `.corrupt` extension and the store is saved.
+    /// Files that can be read normally are left untouched.
+    pub fn repair(&mut self) -> Result<RepairReport, Error> {
+        if self.read_only {
+            return Err(Error::ReadOnly(self.path.clone()));
+        }
+        if self.shard_separator.is_some() {
+            return Err(Error::Repair("sharded stores can't be repaired".into()));
+        }
+
+        let store_path = self.store_path();
+        let bytes = fs::read(&store_path)?;
+        if (self.deserialize)(&bytes).is_ok() {
+            return Ok(RepairReport::default());
+        }
+
+        let (values, meta, mut report) = salvage(&bytes)?;
+        let backup = backup_path(&store_path);
+        fs::rename(&store_path, &backup)?;
+        report.backup = Some(backup);
+
+        self.merge_loaded(values);
+        self.apply_loaded_meta(meta)?;
+        self.rebuild_indexes();
+        self.contents_changed();
+        self.save()?;
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn salvage_str(input: &str) -> (JsonValue, RepairReport) {
+        let mut report = RepairReport::default();
+        let (entries, end) = salvage_object(input.as_bytes(), 0, "", &mut report).unwrap();
+        assert!(end <= input.len());
+        (JsonValue::Object(entries), report)
+    }
+
+    #[test]
+    fn reads_valid_objects_as_they_are() {
+        let (entries, report) =
+            salvage_str(r#" { "a": 1, "b": { "c": [true, null] }, "d": "x, \"y\"" } "#);
+        assert_eq!(
+            entries,
+            json!({ "a": 1, "b": { "c": [true, null] }, "d": "x, \"y\"" })
+        );
+        assert_eq!(report, RepairReport::default());
+    }
+
+    #[test]
+    fn reads_truncated_files_up_to_the_last_complete_value() {
+        let (entries, report) = salvage_str(r#"{"a": 1"#);
+        assert_eq!(entries, json!({ "a": 1 }));
+        assert!(report.lost.is_empty());
+
+        let (entries, report) = salvage_str(r#"{"a": 1, "b"#);
+        assert_eq!(entries, json!({ "a": 1 }));
+        assert!(report.lost.is_empty());
+
+        let (entries, report) = salvage_str(r#"{"a": 1, "b":"#);
+        assert_eq!(entries, json!({ "a": 1 }));
+        assert_eq!(report.lost, ["b"]);
+
+        let (entries, report) = salvage_str(r#"{"a": 1, "b": "unterminated"#);
+        assert_eq!(entries, json!({ "a": 1 }));
+        assert_eq!(report.lost, ["b"]);
+
+        let (entries, report) = salvage_str(r#"{"a": 1, "b": {"c": 2, "d": [3"#);
+        assert_eq!(entries, json!({ "a": 1, "b": { "c": 2 } }));
+        assert_eq!(report.lost, ["b.d"]);
+    }
+
+    #[test]
+    fn skips_garbage_between_entries() {
+        let (entries, report) = salvage_str(r#"{"a": 1 xx, "b": 2,,, "c": 3}"#);
+        assert_eq!(entries, json!({ "a": 1, "b": 2, "c": 3 }));
+        assert!(report.lost.is_empty());
+        // `xx,` and `,,`
+        assert_eq!(report.skipped_bytes, 5);
+
+        let (entries, report) = salvage_str(r#"{"a": 1 "b": 2}"#);
+        assert_eq!(entries, json!({ "a": 1, "b": 2 }));
+        assert_eq!(report.skipped_bytes, 0);
+    }
+
+    #[test]
+    fn loses_only_corrupt_values() {
+        let (entries, report) = salvage_str(r#"{"a": 1, "b": tru, "c": 3}"#);
+        assert_eq!(entries, json!({ "a": 1, "c": 3 }));
+        assert_eq!(report.lost, ["b"]);
+        // `"b": tru,`
+        assert_eq!(report.skipped_bytes, 9);
+
+        let (entries, report) = salvage_str(r#"{"a" 1, "b": 2}"#);
+        assert_eq!(entries, json!({ "b": 2 }));
+        assert_eq!(report.lost, ["a"]);
+
+        let (entries, report) = salvage_str(r#"{"list": [1, 2, oops], "b": 1}"#);
+        assert_eq!(entries, json!({ "b": 1 }));
+        assert_eq!(report.lost, ["list"]);
+    }
+
+    #[test]
+    fn recovers_nested_objects() {
+        let (entries, report) =
+            salvage_str(r#"{"window": {"width": 800, "height": 6x0, "x": 5}, "theme": "dark"}"#);
+        assert_eq!(
+            entries,
+            json!({ "window": { "width": 800, "x": 5 }, "theme": "dark" })
+        );
+        assert_eq!(report.lost, ["window.height"]);
+
+        let (entries, report) = salvage_str(r#"{"a": {"b": {"c": oops, "d": 1}}, "e": 2}"#);
+        assert_eq!(entries, json!({ "a": { "b": { "d": 1 } }, "e": 2 }));
+        assert_eq!(report.lost, ["a.b.c"]);
+    }
+
+    #[test]
+    fn rejects_files_without_an_object() {
+        for input in ["", "   ", "[1, 2]", "\"text\"", "garbage {\"a\": 1}"] {
+            assert!(
+                matches!(salvage(input.as_bytes()), Err(Error::Repair(_))),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn unwraps_envelopes() {
+        let input = r#"{"meta": {"format": 1, "keys": {"a": {"type": "number"}, "b": {"type": "string"}}}, "data": {"a": 1, "b": "unterminated"#;
+        let (values, meta, report) = salvage(input.as_bytes()).unwrap();
+        assert_eq!(values, HashMap::from([("a".to_string(), json!(1))]));
+        assert!(meta.unwrap().keys.contains_key("a"));
+        assert!(report.corrupt);
+        assert_eq!(report.recovered, ["a"]);
+        assert_eq!(report.lost, ["b"]);
+    }
+
+    #[test]
+    fn reports_recovered_keys_sorted_without_metadata() {
+        let input = format!(r#"{{"b": 1, "{META_KEY}": {{}}, "a": 2, "c": x"#);
+        let (values, meta, report) = salvage(input.as_bytes()).unwrap();
+        assert!(meta.is_none());
+        assert!(values.contains_key(META_KEY));
+        assert_eq!(report.recovered, ["a", "b"]);
+        assert_eq!(report.lost, ["c"]);
+    }
+}
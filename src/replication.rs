@@ -0,0 +1,97 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Mirroring of selected keys from one store into another.
+
+use crate::{collection_key, ensure_store, KeyFilter, Store, StoreCollection};
+use log::warn;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use tauri::{AppHandle, Runtime};
+
+#[derive(Debug, Clone)]
+pub(crate) struct Replication {
+    source: PathBuf,
+    filter: KeyFilter,
+    target: PathBuf,
+}
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Mirrors the keys of the store at `source` selected by `filter` into the store at `target`,
+    /// e.g. to copy a theme choice from a per-profile store into a machine-wide one.
+    ///
+    /// Keys are copied when they change, including deletions; the current contents of `source` aren't copied.
+    /// Rules may chain and form cycles, a key is only written to a store that doesn't already hold its value.
+    pub fn replicate(&self, source: &Path, filter: KeyFilter, target: &Path) {
+        self.replications
+            .lock()
+            .expect("mutex poisoned")
+            .push(Replication {
+                source: collection_key(source),
+                filter,
+                target: collection_key(target),
+            });
+    }
+
+    /// Returns `true` if the store at `key` is the source of a replication rule.
+    pub(crate) fn is_replicated(&self, key: &Path) -> bool {
+        self.replications
+            .lock()
+            .expect("mutex poisoned")
+            .iter()
+            .any(|rule| rule.source == key)
+    }
+
+    /// Applies the changes recorded by the store at `source` to the targets of its replication rules.
+    pub(crate) fn replicate_changes(
+        &self,
+        app: &AppHandle<R>,
+        stores: &mut HashMap<PathBuf, Store<R>>,
+        source: PathBuf,
+    ) {
+        let rules = self.replications.lock().expect("mutex poisoned");
+        let mut pending = vec![source];
+        while let Some(source) = pending.pop() {
+            let changes = match stores.get_mut(&source) {
+                Some(store) => std::mem::take(&mut store.replication_outbox),
+                None => continue,
+            };
+            if changes.is_empty() {
+                continue;
+            }
+            for rule in rules.iter().filter(|rule| rule.source == source) {
+                let target = match ensure_store(app, self, stores, &rule.target) {
+                    Ok(target) => target,
+                    Err(err) => {
+                        warn!(
+                            "Failed to replicate store {:?} into {:?}: {}",
+                            source, rule.target, err
+                        );
+                        continue;
+                    }
+                };
+                let store = stores.get_mut(&target).expect("store was loaded above");
+                store.replicating = rules.iter().any(|rule| rule.source == target);
+                for (key, value) in changes.iter().filter(|(key, _)| rule.filter.matches(key)) {
+                    if store.get(key) == value.as_ref() {
+                        continue;
+                    }
+                    let result = match value {
+                        Some(value) => store.insert(key.clone(), value.clone()),
+                        None => store.delete(key).map(|_| ()),
+                    };
+                    if let Err(err) = result {
+                        store.report_error("replicate into", err);
+                    }
+                }
+                if let Err(err) = store.save_if_due() {
+                    store.report_error("save", err);
+                }
+                pending.push(target);
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Saves written by a worker thread, so commands don't wait for disk IO.
+
+use crate::{collection_key, StoreCollection};
+use log::warn;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    thread::JoinHandle,
+    time::Instant,
+};
+use tauri::{AppHandle, Manager, Runtime};
+
+/// A handle to the worker, shared by the collection and its stores.
+#[derive(Clone)]
+pub(crate) struct SaveQueue {
+    /// `None` once the queue was closed with [`Self::close`].
+    sender: Arc<Mutex<Option<mpsc::Sender<PathBuf>>>>,
+    /// The stores waiting to be saved, so repeated requests are only queued once.
+    pending: Arc<Mutex<HashSet<PathBuf>>>,
+    worker: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl SaveQueue {
+    /// Starts the worker thread, which saves the stores of the app's collection as they are queued.
+    pub(crate) fn start<R: Runtime>(app: &AppHandle<R>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let app = app.clone();
+        let worker = std::thread::spawn(move || {
+            for key in receiver {
+                save_queued(&app, key);
+            }
+        });
+        Self {
+            sender: Arc::new(Mutex::new(Some(sender))),
+            pending: Default::default(),
+            worker: Arc::new(Mutex::new(Some(worker))),
+        }
+    }
+
+    /// Whether saves can still be queued, see [`Self::close`].
+    pub(crate) fn is_open(&self) -> bool {
+        self.sender.lock().expect("mutex poisoned").is_some()
+    }
+
+    /// Stops accepting saves and waits until the worker saved the queued stores, e.g. before the app exits.
+    ///
+    /// Must not be called while holding the collection, which the worker needs to save.
+    pub(crate) fn close(&self) {
+        // the worker stops once it received everything sent before its only sender was dropped
+        self.sender.lock().expect("mutex poisoned").take();
+        let worker = self.worker.lock().expect("mutex poisoned").take();
+        if let Some(worker) = worker {
+            if worker.join().is_err() {
+                warn!("The store save worker panicked");
+            }
+        }
+    }
+
+    /// Queues the store at `path` to be saved, unless it is already waiting.
+    pub(crate) fn push(&self, path: &Path) {
+        let key = collection_key(path);
+        let queued = self
+            .pending
+            .lock()
+            .expect("mutex poisoned")
+            .insert(key.clone());
+        if queued
+            && self
+                .sender
+                .lock()
+                .expect("mutex poisoned")
+                .as_ref()
+                .map_or(true, |sender| sender.send(key).is_err())
+        {
+            warn!("Failed to queue the save of store {:?}", path);
+        }
+    }
+}
+
+fn save_queued<R: Runtime>(app: &AppHandle<R>, key: PathBuf) {
+    let collection = app.state::<StoreCollection<R>>();
+    // removed before the contents are serialized, so a request made during the save queues another one
+    collection
+        .save_queue
+        .pending
+        .lock()
+        .expect("mutex poisoned")
+        .remove(&key);
+
+    // failures are emitted regardless of the store's error policy, the frontend only learns about them this way
+    let prepared = {
+        let mut stores = collection.stores.lock().expect("mutex poisoned");
+        // an unloaded store was saved when it was unloaded
+        let Some(store) = stores.get_mut(&key) else {
+            return;
+        };
+        if let Err(err) = store.commit_staged() {
+            store.emit_error("commit staged values of", &err);
+        }
+        // the before_save hook ran when the save was queued
+        if let Err(err) = store.check_writable() {
+            store.emit_error("save", &err);
+            return;
+        }
+        let started = Instant::now();
+        if !store.is_detachable() {
+            let result = store.write_to_disk();
+            if let Err(err) = store.finish_save(started, result) {
+                store.emit_error("save", &err);
+            }
+            return;
+        }
+        match store.prepare_save() {
+            Ok(prepared) => prepared,
+            Err(err) => {
+                if let Err(err) = store.finish_save(started, Err(err)) {
+                    store.emit_error("save", &err);
+                }
+                return;
+            }
+        }
+    };
+
+    // written without holding the collection, a newer save isn't overwritten by these contents
+    let started = Instant::now();
+    let result = prepared.write();
+    let stores = collection.stores.lock().expect("mutex poisoned");
+    match stores.get(&key) {
+        Some(store) => {
+            if let Err(err) = store.finish_save(started, result) {
+                store.emit_error("save", &err);
+            }
+        }
+        None => {
+            if let Err(err) = result {
+                prepared.report_error(err);
+            }
+        }
+    }
+}
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Queues the store at `path` to be saved by a background worker and returns right away.
+    ///
+    /// The contents are serialized when the save starts and written without holding the store, so other
+    /// commands don't wait for the disk. Requests made before that are merged into one save. Failures are logged
+    /// and emitted as `store://error` events regardless of the store's [`ErrorPolicy`](crate::ErrorPolicy),
+    /// as there is no caller to return them to. Callers should run the `before_save` hook and the other checks
+    /// of [`Store::save`](crate::Store::save) first.
+    pub fn queue_save(&self, path: &Path) {
+        self.save_queue.push(path);
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{Error, JsonValue, Store};
+use tauri::Runtime;
+
+/// A view of the keys of a [`Store`] that start with a prefix, created with [`Store::scoped`].
+///
+/// Keys passed to and returned by the view don't include the prefix, so feature modules can be handed
+/// a namespaced slice of a shared store without seeing unrelated keys.
+///
+/// # Examples
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use serde_json::json;
+/// use tauri_plugin_store::StoreBuilder;
+///
+/// let mut store = StoreBuilder::new("settings.json".parse()?).build();
+/// let mut editor = store.scoped("editor.");
+///
+/// // stored as `editor.fontSize`
+/// editor.insert("fontSize", json!(14))?;
+/// assert_eq!(editor.keys().collect::<Vec<_>>(), ["fontSize"]);
+///
+/// # Ok(())
+/// # }
+/// ```
+pub struct ScopedStore<'a, R: Runtime> {
+    store: &'a mut Store<R>,
+    prefix: String,
+}
+
+impl<R: Runtime> Store<R> {
+    /// Returns a view of the keys of the store that start with `prefix`.
+    pub fn scoped(&mut self, prefix: impl Into<String>) -> ScopedStore<'_, R> {
+        ScopedStore {
+            store: self,
+            prefix: prefix.into(),
+        }
+    }
+}
+
+impl<'a, R: Runtime> ScopedStore<'a, R> {
+    /// Returns the prefix of the keys in this view.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+
+    /// Returns a view of the keys of this view that start with `prefix`.
+    pub fn scoped(&mut self, prefix: impl AsRef<str>) -> ScopedStore<'_, R> {
+        let prefix = self.full_key(prefix.as_ref());
+        self.store.scoped(prefix)
+    }
+
+    pub fn insert(&mut self, key: impl AsRef<str>, value: JsonValue) -> Result<(), Error> {
+        let key = self.full_key(key.as_ref());
+        self.store.insert(key, value)
+    }
+
+    pub fn get(&self, key: impl AsRef<str>) -> Option<&JsonValue> {
+        self.store.get(self.full_key(key.as_ref()))
+    }
+
+    pub fn has(&self, key: impl AsRef<str>) -> bool {
+        self.store.has(self.full_key(key.as_ref()))
+    }
+
+    pub fn delete(&mut self, key: impl AsRef<str>) -> Result<bool, Error> {
+        let key = self.full_key(key.as_ref());
+        self.store.delete(key)
+    }
+
+    /// Removes all keys of this view from the store.
+    pub fn clear(&mut self) -> Result<(), Error> {
+        let keys: Vec<String> = self
+            .store
+            .keys()
+            .filter(|key| key.starts_with(&self.prefix))
+            .cloned()
+            .collect();
+        for key in keys {
+            self.store.delete(key)?;
+        }
+        Ok(())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.store
+            .keys()
+            .filter_map(|key| key.strip_prefix(self.prefix.as_str()))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &JsonValue> {
+        self.entries().map(|(_, value)| value)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &JsonValue)> {
+        self.store.entries().filter_map(|(key, value)| {
+            key.strip_prefix(self.prefix.as_str())
+                .map(|key| (key, value))
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys().next().is_none()
+    }
+}
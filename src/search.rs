@@ -0,0 +1,131 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! In-memory full-text search over string values, enabled with the `search` feature and
+//! [`StoreBuilder::full_text_search`](crate::StoreBuilder::full_text_search).
+
+use crate::{Error, JsonValue, Store};
+use std::collections::HashMap;
+use tauri::Runtime;
+
+/// Inverted index from the words of string values to the keys containing them.
+#[derive(Clone, Default)]
+pub(crate) struct SearchIndex {
+    /// Keys by word, with the number of occurrences of the word in the key's value.
+    postings: HashMap<String, HashMap<String, usize>>,
+    /// The distinct words of every indexed key.
+    words: HashMap<String, Vec<String>>,
+}
+
+/// Splits `text` into lowercase alphanumeric words.
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Counts the words of all strings in `value`, including those nested in arrays and objects.
+fn count_words(value: &JsonValue, counts: &mut HashMap<String, usize>) {
+    match value {
+        JsonValue::String(text) => {
+            for word in tokenize(text) {
+                *counts.entry(word).or_default() += 1;
+            }
+        }
+        JsonValue::Array(values) => values.iter().for_each(|value| count_words(value, counts)),
+        JsonValue::Object(map) => map.values().for_each(|value| count_words(value, counts)),
+        _ => {}
+    }
+}
+
+impl SearchIndex {
+    /// Updates the entry of `key`, with `None` if it was removed.
+    pub(crate) fn update(&mut self, key: &str, value: Option<&JsonValue>) {
+        if let Some(words) = self.words.remove(key) {
+            for word in words {
+                if let Some(keys) = self.postings.get_mut(&word) {
+                    keys.remove(key);
+                    if keys.is_empty() {
+                        self.postings.remove(&word);
+                    }
+                }
+            }
+        }
+        let Some(value) = value else {
+            return;
+        };
+        let mut counts = HashMap::new();
+        count_words(value, &mut counts);
+        if counts.is_empty() {
+            return;
+        }
+        let mut words = Vec::with_capacity(counts.len());
+        for (word, count) in counts {
+            self.postings
+                .entry(word.clone())
+                .or_default()
+                .insert(key.to_string(), count);
+            words.push(word);
+        }
+        self.words.insert(key.to_string(), words);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.postings.clear();
+        self.words.clear();
+    }
+
+    /// Returns the keys containing all words of `query`, best matches first.
+    fn search(&self, query: &str) -> Vec<(&String, usize)> {
+        let mut words = tokenize(query).collect::<Vec<_>>();
+        words.sort();
+        words.dedup();
+        let mut postings = Vec::with_capacity(words.len());
+        for word in &words {
+            match self.postings.get(word) {
+                Some(keys) => postings.push(keys),
+                None => return Vec::new(),
+            }
+        }
+        // start from the rarest word to keep the candidate set small
+        postings.sort_by_key(|keys| keys.len());
+        let Some((first, rest)) = postings.split_first() else {
+            return Vec::new();
+        };
+        let mut matches = first
+            .iter()
+            .filter_map(|(key, count)| {
+                rest.iter()
+                    .try_fold(*count, |score, keys| {
+                        keys.get(key).map(|count| score + count)
+                    })
+                    .map(|score| (key, score))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by(|(a, a_score), (b, b_score)| b_score.cmp(a_score).then_with(|| a.cmp(b)));
+        matches
+    }
+}
+
+impl<R: Runtime> Store<R> {
+    /// Returns up to `limit` keys whose string values contain every word of `query`, ordered by the
+    /// number of occurrences of the words and then by key.
+    ///
+    /// Words are runs of alphanumeric characters and are matched case-insensitively.
+    /// Strings nested in arrays and objects are searched as well.
+    ///
+    /// Fails if the store wasn't built with [`StoreBuilder::full_text_search`](crate::StoreBuilder::full_text_search).
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<&String>, Error> {
+        let index = self
+            .search
+            .as_ref()
+            .ok_or_else(|| Error::SearchDisabled(self.path.clone()))?;
+        Ok(index
+            .search(query)
+            .into_iter()
+            .take(limit)
+            .map(|(key, _)| key)
+            .collect())
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use crate::{atomic::write_atomic, Error, JsonValue, Store};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs, io,
+    path::PathBuf,
+};
+use tauri::Runtime;
+
+/// The name of the shard file of keys without a prefix, which can't collide with an encoded prefix.
+const ROOT_SHARD_NAME: &str = "_";
+
+/// Returns the prefix of `key`, which is empty if the key doesn't contain `separator`.
+pub(crate) fn shard_of(key: &str, separator: char) -> &str {
+    key.split_once(separator).map_or("", |(prefix, _)| prefix)
+}
+
+/// Returns the file name of a shard, percent-encoding characters that aren't safe in file names.
+fn file_name(shard: &str, extension: &str) -> String {
+    let mut name = String::with_capacity(shard.len() + extension.len() + 1);
+    if shard.is_empty() {
+        name.push_str(ROOT_SHARD_NAME);
+    }
+    for byte in shard.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' {
+            name.push(byte as char);
+        } else {
+            name.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    name.push('.');
+    name.push_str(extension);
+    name
+}
+
+impl<R: Runtime> Store<R> {
+    /// Returns the extension of the store's shard files, checked to be set before the store is loaded or saved.
+    fn shard_extension(&self) -> &str {
+        self.shard_extension.as_deref().unwrap_or("json")
+    }
+
+    /// Marks a shard as having to be written on the next save.
+    pub(crate) fn shard_changed(&self, shard: &str) {
+        if let Some(dirty) = self.dirty_shards.lock().expect("mutex poisoned").as_mut() {
+            dirty.insert(shard.to_string());
+        }
+    }
+
+    /// Merges all shard files of the store into it.
+    pub(crate) fn load_shards(&mut self, separator: char) -> Result<(), Error> {
+        // contents from before the load, e.g. defaults, may differ from the files
+        let changed: HashSet<String> = self
+            .keys()
+            .map(|key| shard_of(key, separator).to_string())
+            .collect();
+
+        let extension = self.shard_extension();
+        for entry in fs::read_dir(self.store_path())? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .map_or(false, |found| found == OsStr::new(extension))
+            {
+                self.load_bytes(&fs::read(&path)?, None)?;
+            }
+        }
+
+        *self.dirty_shards.lock().expect("mutex poisoned") = Some(changed);
+        Ok(())
+    }
+
+    /// Writes the shards that changed since the last save, removing the files of shards that became empty.
+    ///
+    /// Stores that weren't loaded write all shards and remove every other shard file.
+    pub(crate) fn write_shards(
+        &self,
+        separator: char,
+        contents: &HashMap<String, JsonValue>,
+    ) -> Result<(), Error> {
+        let dir = self.store_path();
+        let extension = self.shard_extension();
+        fs::create_dir_all(&dir)?;
+
+        let mut dirty = self.dirty_shards.lock().expect("mutex poisoned");
+        // keys without a prefix share their file with the plugin's metadata, which may have changed
+        if let Some(dirty) = dirty.as_mut() {
+            dirty.insert(String::new());
+        }
+
+        let mut shards: HashMap<&str, HashMap<String, JsonValue>> = HashMap::new();
+        for (key, value) in contents {
+            let shard = shard_of(key, separator);
+            if dirty.as_ref().map_or(true, |dirty| dirty.contains(shard)) {
+                shards
+                    .entry(shard)
+                    .or_default()
+                    .insert(key.clone(), value.clone());
+            }
+        }
+
+        for (shard, entries) in &shards {
+            let bytes = (self.serialize)(entries).map_err(Error::Serialize)?;
+            write_atomic(&dir.join(file_name(shard, extension)), &bytes)?;
+        }
+        let stale: Vec<PathBuf> = match dirty.as_ref() {
+            Some(dirty) => dirty
+                .iter()
+                .filter(|shard| !shards.contains_key(shard.as_str()))
+                .map(|shard| dir.join(file_name(shard, extension)))
+                .collect(),
+            None => {
+                let written: HashSet<String> = shards
+                    .keys()
+                    .map(|shard| file_name(shard, extension))
+                    .collect();
+                let mut stale = Vec::new();
+                for entry in fs::read_dir(&dir)? {
+                    let path = entry?.path();
+                    let is_shard = path
+                        .extension()
+                        .map_or(false, |found| found == OsStr::new(extension));
+                    let is_written = path
+                        .file_name()
+                        .and_then(OsStr::to_str)
+                        .map_or(false, |name| written.contains(name));
+                    if is_shard && !is_written {
+                        stale.push(path);
+                    }
+                }
+                stale
+            }
+        };
+        for path in stale {
+            match fs::remove_file(path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            }
+        }
+
+        *dirty = Some(HashSet::new());
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! HMAC signatures of store files, enabled with [`StoreBuilder::sign_with`](crate::StoreBuilder::sign_with).
+//!
+//! The paths of signature files are known without the `signing` feature, so their stores are still recognized.
+
+#[cfg(feature = "signing")]
+use crate::{
+    atomic::temp_path,
+    hex::{from_hex, to_hex},
+};
+use crate::{
+    atomic::{stage, write_atomic},
+    Error, Store,
+};
+#[cfg(feature = "signing")]
+use hmac::{Hmac, Mac};
+#[cfg(feature = "signing")]
+use sha2::Sha256;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "signing")]
+use std::{fs, io};
+use tauri::Runtime;
+
+#[cfg(feature = "signing")]
+type HmacSha256 = Hmac<Sha256>;
+
+/// Returns the path of the signature of the store file at `store_path`.
+pub(crate) fn signature_path(store_path: &Path) -> PathBuf {
+    let mut path = store_path.as_os_str().to_owned();
+    path.push(".sig");
+    path.into()
+}
+
+#[cfg(feature = "signing")]
+fn mac(key: &[u8], bytes: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(bytes);
+    mac
+}
+
+/// Writes the store file contents `bytes` to `store_path` along with their `signature`, if the store is signed.
+///
+/// The signature is written next to its destination before the store file is replaced and renamed into place after.
+/// If the app stops in between, [`Store::verify_signature`] finds the new signature and completes the save.
+pub(crate) fn write_signed(
+    store_path: &Path,
+    bytes: &[u8],
+    signature: Option<&str>,
+) -> Result<(), Error> {
+    let signature = signature
+        .map(|signature| stage(&signature_path(store_path), signature.as_bytes()))
+        .transpose()?;
+    write_atomic(store_path, bytes)?;
+    if let Some(signature) = signature {
+        signature.commit()?;
+    }
+    Ok(())
+}
+
+/// Checks `bytes` against the signature in the file at `path`, a missing or malformed signature doesn't match.
+#[cfg(feature = "signing")]
+fn matches_signature(key: &[u8], bytes: &[u8], path: &Path) -> Result<bool, Error> {
+    let hex = match fs::read_to_string(path) {
+        Ok(hex) => hex,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err.into()),
+    };
+    let Some(signature) = from_hex(hex.trim()) else {
+        return Ok(false);
+    };
+    // constant-time comparison
+    Ok(mac(key, bytes).verify_slice(&signature).is_ok())
+}
+
+impl<R: Runtime> Store<R> {
+    /// Returns the signature of the store file contents `bytes`, if the store is signed.
+    #[cfg(feature = "signing")]
+    pub(crate) fn signature(&self, bytes: &[u8]) -> Option<String> {
+        let key = self.signing_key.as_ref()?;
+        Some(to_hex(&mac(key, bytes).finalize().into_bytes()))
+    }
+
+    #[cfg(not(feature = "signing"))]
+    pub(crate) fn signature(&self, _bytes: &[u8]) -> Option<String> {
+        None
+    }
+
+    /// Checks the store file contents `bytes` against their signature, if the store is signed.
+    ///
+    /// A missing signature fails the check as well, so it can't be bypassed by deleting the signature file.
+    #[cfg(feature = "signing")]
+    pub(crate) fn verify_signature(&self, store_path: &Path, bytes: &[u8]) -> Result<(), Error> {
+        let Some(key) = &self.signing_key else {
+            return Ok(());
+        };
+        let signature_path = signature_path(store_path);
+        if matches_signature(key, bytes, &signature_path)? {
+            return Ok(());
+        }
+        // the app stopped after replacing the store file but before committing its signature
+        let staged = temp_path(&signature_path);
+        if matches_signature(key, bytes, &staged)? {
+            fs::rename(staged, signature_path)?;
+            return Ok(());
+        }
+        Err(Error::TamperDetected(self.path.clone()))
+    }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+    use std::process;
+
+    /// A signature file in the temp directory, removed when dropped.
+    struct SignatureFile(PathBuf);
+
+    impl SignatureFile {
+        fn new(name: &str, contents: &str) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("tauri-plugin-store-{}-{name}.sig", process::id()));
+            fs::write(&path, contents).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for SignatureFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn sign(key: &[u8], bytes: &[u8]) -> String {
+        to_hex(&mac(key, bytes).finalize().into_bytes())
+    }
+
+    #[test]
+    fn matches_own_signature() {
+        let file = SignatureFile::new("valid", &format!("{}\n", sign(b"key", b"{\"a\":1}")));
+        assert!(matches_signature(b"key", b"{\"a\":1}", &file.0).unwrap());
+    }
+
+    #[test]
+    fn detects_tampering() {
+        let file = SignatureFile::new("tampered", &sign(b"key", b"{\"a\":1}"));
+        assert!(!matches_signature(b"key", b"{\"a\":2}", &file.0).unwrap());
+        assert!(!matches_signature(b"other key", b"{\"a\":1}", &file.0).unwrap());
+    }
+
+    #[test]
+    fn missing_or_malformed_signature_fails() {
+        let missing =
+            std::env::temp_dir().join(format!("tauri-plugin-store-{}-missing.sig", process::id()));
+        assert!(!matches_signature(b"key", b"{}", &missing).unwrap());
+
+        let signature = sign(b"key", b"{}");
+        for (name, contents) in [
+            ("empty", String::new()),
+            ("not-hex", "not a signature".to_string()),
+            ("truncated", signature[..signature.len() - 2].to_string()),
+        ] {
+            let file = SignatureFile::new(name, &contents);
+            assert!(!matches_signature(b"key", b"{}", &file.0).unwrap());
+        }
+    }
+
+    #[test]
+    fn signature_path_appends_extension() {
+        assert_eq!(
+            signature_path(Path::new("/data/license.json")),
+            PathBuf::from("/data/license.json.sig")
+        );
+    }
+}
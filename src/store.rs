@@ -2,41 +2,720 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
-use crate::{ChangePayload, Error};
-use serde_json::Value as JsonValue;
+use crate::{
+    clock::{Clock, SystemClock},
+    coalesce::Coalescing,
+    envelope::{self, EnvelopeMeta, KeyMeta},
+    index::Index,
+    lock::Lease,
+    mirror::Change,
+    numbers::{may_hold_unsafe_integer, stringify_unsafe, NumberMode},
+    obfuscate::{obfuscated_deserialize, obfuscated_serialize},
+    prune::is_prunable,
+    save_queue::SaveQueue,
+    shard::shard_of,
+    signature::write_signed,
+    transition::LegacyFormat,
+    writer::Writer,
+    ChangePayload, DiskFullPayload, Error, ErrorPayload, KeyFilter, LegacyChangePayload,
+    ResetPayload, StoreHost, StoreReader,
+};
+use log::warn;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::{value::RawValue, Value as JsonValue};
 use std::{
-    collections::HashMap,
-    fs::{create_dir_all, read, File},
-    io::Write,
-    path::PathBuf,
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    ffi::OsStr,
+    fs::{create_dir_all, read, remove_dir, remove_file, symlink_metadata},
+    io,
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicU64, Arc, Mutex},
+    time::{Duration, Instant},
 };
-use tauri::{AppHandle, Manager, Runtime};
+use tauri::{AppHandle, Runtime};
+
+#[cfg(feature = "compression")]
+use crate::compression::{compress, decompress, is_compressed, may_be_compressed};
+#[cfg(feature = "encryption")]
+use crate::encryption::KeyEncryption;
+#[cfg(feature = "search")]
+use crate::search::SearchIndex;
+
+/// The default name of the event emitted for every changed key, with a [`ChangePayload`](crate::ChangePayload).
+///
+/// Stores can use another name, see [`StoreBuilder::change_event`].
+pub const CHANGE_EVENT: &str = "store://change";
+
+/// The version of the payloads of the events emitted by stores, included in their `schema` field.
+///
+/// Fields are only ever added to a payload, never changed or removed, so listeners written against an older
+/// version keep working. Version 1 is the change payload without a `schema` field, holding only `path`, `key`
+/// and `value`, which stores built with [`StoreBuilder::legacy_change_payloads`] still emit.
+pub const PAYLOAD_SCHEMA: u32 = 2;
+
+/// The event emitted when a store is reset, with the store's path and a JSON Patch of the changes.
+pub const RESET_EVENT: &str = "store://reset";
+
+/// The event emitted when a store couldn't be saved because the disk is full, with the store's path.
+pub const DISK_FULL_EVENT: &str = "store://disk-full";
+
+/// The event emitted when the changes of an edit session are committed with
+/// [`StoreCollection::commit_edit`](crate::StoreCollection::commit_edit), with a JSON Patch of the changes.
+pub const BATCH_EVENT: &str = "store://batch";
+
+/// The placeholder [`Store::export_redacted`] replaces the values of sensitive keys with.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// The event emitted when a window acquires a lease with [`Store::acquire_lock`], with the store's path,
+/// the lease's name, its owner and its TTL.
+pub const LOCKED_EVENT: &str = "store://locked";
+
+/// The event emitted when a lease is released with [`Store::release_lock`], with the store's path and the lease's name.
+pub const UNLOCKED_EVENT: &str = "store://unlocked";
+
+/// The event emitted by [`StoreCollection::verify_all`](crate::StoreCollection::verify_all) with its report.
+pub const VERIFY_EVENT: &str = "store://verified";
+
+/// The event emitted for failed background operations of stores built with [`ErrorPolicy::PropagateToFrontend`].
+pub const ERROR_EVENT: &str = "store://error";
+
+/// The key of the section of the store file holding the plugin's own metadata, e.g. the expiration
+/// times of keys inserted with [`Store::insert_with_ttl`]. Inserting it is rejected with [`Error::ReservedKey`].
+pub const META_KEY: &str = "__store__";
+
+/// The key under which expiration times were persisted before they moved into [`META_KEY`].
+///
+/// Still read when loading, so older files are migrated on the next save.
+pub const EXPIRATIONS_KEY: &str = "__expirations";
+
+/// The contents of the [`META_KEY`] section.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreMeta {
+    /// Expiration times of keys in milliseconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    expirations: HashMap<String, u64>,
+}
 
-type SerializeFn =
+/// Rejects values that would be mistaken for a compressed value when loading.
+#[cfg(feature = "compression")]
+fn check_value(store_compresses: bool, key: &str, value: &JsonValue) -> Result<(), Error> {
+    if store_compresses && is_compressed(value) {
+        return Err(Error::ReservedValue(key.to_string()));
+    }
+    Ok(())
+}
+
+/// Rejects keys that would collide with the plugin's metadata.
+fn check_key(key: &str) -> Result<(), Error> {
+    if key == META_KEY || key == EXPIRATIONS_KEY {
+        return Err(Error::ReservedKey(key.to_string()));
+    }
+    Ok(())
+}
+
+/// Whether `err` reports that the disk or the user's quota is full.
+fn is_disk_full(err: &io::Error) -> bool {
+    // `io::ErrorKind::StorageFull` isn't stable on the supported Rust versions
+    #[cfg(unix)]
+    const CODES: &[i32] = &[28 /* ENOSPC */];
+    // ERROR_HANDLE_DISK_FULL, ERROR_DISK_FULL
+    #[cfg(windows)]
+    const CODES: &[i32] = &[39, 112];
+    #[cfg(not(any(unix, windows)))]
+    const CODES: &[i32] = &[];
+    err.raw_os_error()
+        .map_or(false, |code| CODES.contains(&code))
+}
+
+pub(crate) type SerializeFn =
     fn(&HashMap<String, JsonValue>) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
-type DeserializeFn =
+pub(crate) type DeserializeFn =
     fn(&[u8]) -> Result<HashMap<String, JsonValue>, Box<dyn std::error::Error + Send + Sync>>;
 
-fn default_serialize(
+type BeforeSaveHook<R> = Arc<dyn Fn(&Store<R>) -> bool + Send + Sync>;
+type AfterSaveHook<R> = Arc<dyn Fn(&Store<R>, &Result<(), Error>) + Send + Sync>;
+type WatchFn = Arc<dyn Fn(Option<&JsonValue>) + Send + Sync>;
+type BindFn = Arc<dyn Fn(&HashMap<String, JsonValue>) + Send + Sync>;
+
+/// A mutation recorded by a store with [`StoreBuilder::history`] enabled.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    pub key: String,
+    /// The new value, or `None` if the key was removed.
+    pub value: Option<JsonValue>,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The label of the window that caused the mutation, if it came from the frontend.
+    pub window: Option<String>,
+}
+
+/// When a store was last loaded and saved, as returned by [`Store::persistence_status`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistenceStatus {
+    /// Milliseconds since the Unix epoch at which the store was last loaded from disk successfully.
+    pub loaded_at: Option<u64>,
+    /// Milliseconds since the Unix epoch at which the store was last written to disk successfully.
+    pub saved_at: Option<u64>,
+    /// Milliseconds since the Unix epoch of the last attempt to write the store to disk.
+    pub save_attempted_at: Option<u64>,
+    /// The error of the last attempt to write the store to disk, `None` if it succeeded.
+    pub save_error: Option<String>,
+    /// How long the last attempt to write the store to disk took, in milliseconds.
+    pub save_duration_ms: Option<u64>,
+}
+
+/// Identifies a callback registered with [`Store::watch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatchId(u64);
+
+#[derive(Clone)]
+struct Watcher {
+    id: WatchId,
+    key: String,
+    callback: WatchFn,
+}
+
+pub(crate) fn default_serialize(
     cache: &HashMap<String, JsonValue>,
 ) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
     Ok(serde_json::to_vec(&cache)?)
 }
 
-fn default_deserialize(
+pub(crate) fn default_deserialize(
     bytes: &[u8],
 ) -> Result<HashMap<String, JsonValue>, Box<dyn std::error::Error + Send + Sync>> {
     serde_json::from_slice(bytes).map_err(Into::into)
 }
 
+/// A value that is kept as raw JSON until it is first accessed.
+#[derive(Clone)]
+struct LazyValue {
+    raw: Box<RawValue>,
+    parsed: OnceCell<JsonValue>,
+}
+
+impl LazyValue {
+    fn get(&self) -> &JsonValue {
+        self.parsed.get_or_init(|| {
+            // the raw value was already validated when the store was loaded
+            serde_json::from_str(self.raw.get()).unwrap_or(JsonValue::Null)
+        })
+    }
+}
+
+/// The order in which [`Store::keys_sorted`] returns keys.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeyOrder {
+    /// Plain lexicographic ordering.
+    #[default]
+    Lexicographic,
+    /// Lexicographic ordering that compares runs of digits by their numeric value,
+    /// so `item2` sorts before `item10`.
+    Numeric,
+    /// Reverse lexicographic ordering.
+    Reverse,
+}
+
+/// How [`Store::import_map`] handles keys that already exist in the store.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictStrategy {
+    /// Keep the existing value.
+    #[default]
+    Skip,
+    /// Replace the existing value with the imported one.
+    Overwrite,
+    /// Abort the import without changing anything.
+    Fail,
+}
+
+/// The format of a store file, as recognized by [`FileFormat::detect`].
+///
+/// Files opened with [`StoreCollection::open_readonly`](crate::StoreCollection::open_readonly) must be in a format
+/// with a built-in deserializer, see [`FileFormat::deserializer`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum FileFormat {
+    /// A JSON object, either flat or in the envelope written with [`StoreBuilder::envelope`].
+    #[default]
+    Json,
+    /// A TOML document. Its built-in deserializer requires the `toml` feature.
+    Toml,
+    /// A MessagePack map. Its built-in deserializer requires the `msgpack` feature.
+    MessagePack,
+    /// Gzip-compressed data of any other format. Its built-in deserializer requires the `compression` feature.
+    Gzip,
+    /// Zstandard-compressed data of any other format. Its built-in deserializer requires the `zstd` feature.
+    Zstd,
+    /// The format written by stores built with [`StoreBuilder::obfuscated`].
+    Obfuscated,
+}
+
+impl FileFormat {
+    /// Guesses the format of a file from its contents.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x1f, 0x8b, ..] => return Some(Self::Gzip),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => return Some(Self::Zstd),
+            [b'T', b'S', b'O', b'B', ..] => return Some(Self::Obfuscated),
+            // a map, which is what the top level of a store serializes to
+            [0x80..=0x8f | 0xde | 0xdf, ..] => return Some(Self::MessagePack),
+            _ => {}
+        }
+
+        let text = std::str::from_utf8(bytes).ok()?;
+        let first_line = text
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))?;
+        if first_line.starts_with('{') {
+            return Some(Self::Json);
+        }
+        // a table header like `[window]` or `[[profiles]]`, or a `key = value` pair
+        let is_key = |key: &str| {
+            let key = key.trim();
+            !key.is_empty()
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "_-. \"'".contains(c))
+        };
+        let is_table = first_line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+            .map_or(false, |header| {
+                is_key(header.trim_start_matches('[').trim_end_matches(']'))
+            });
+        let is_pair = first_line.split_once('=').map_or(false, |(key, value)| {
+            is_key(key) && !value.trim().is_empty()
+        });
+        (is_table || is_pair).then_some(Self::Toml)
+    }
+
+    /// Returns the deserializer built into the plugin for this format, if there is one.
+    ///
+    /// Compressed files are decompressed and deserialized with the built-in deserializer of the format of
+    /// their contents.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{FileFormat, StoreBuilder};
+    ///
+    /// // the store was switched from the obfuscated format to plain JSON
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .detect_format(FileFormat::Obfuscated, FileFormat::Obfuscated.deserializer().unwrap());
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn deserializer(self) -> Option<DeserializeFn> {
+        match self {
+            Self::Json => Some(default_deserialize),
+            Self::Obfuscated => Some(obfuscated_deserialize),
+            #[cfg(feature = "compression")]
+            Self::Gzip => Some(crate::formats::gzip_deserialize),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Some(crate::formats::zstd_deserialize),
+            #[cfg(feature = "toml")]
+            Self::Toml => Some(crate::formats::toml_deserialize),
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => Some(crate::formats::msgpack_deserialize),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
+/// How a [`Store`] treats its path being a symbolic link or, on Windows, a junction.
+///
+/// Only the store file itself is checked, or the directory of a store built with [`StoreBuilder::shard_by_prefix`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Fail to load and save the store.
+    Refuse,
+    /// Read and write the file the link points to.
+    #[default]
+    Follow,
+    /// Ignore the link when loading and replace it with a regular file when saving.
+    Replace,
+}
+
+/// How a [`Store`] handles failures in background operations that have no caller to return them to,
+/// e.g. autosaves, the flush on exit and loading registered stores, see [`StoreBuilder::on_error`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Panic, for apps that can't continue without their data being persisted.
+    Panic,
+    /// Log the error and carry on.
+    #[default]
+    LogAndContinue,
+    /// Log the error and emit a `store://error` event with the store's path, the failed operation and the error.
+    PropagateToFrontend,
+}
+
+/// Whether the path of a [`Store`] has to end in the extension of its format, see [`StoreBuilder::extension_policy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExtensionPolicy {
+    /// Use the path as given.
+    #[default]
+    Ignore,
+    /// Append the extension to paths that don't end in it, e.g. `settings` becomes `settings.json`.
+    Append,
+    /// Fail to load and save the store if its path doesn't end in the extension.
+    Enforce,
+}
+
+/// When a [`Store`] is saved without an explicit call to [`Store::save`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SaveStrategy {
+    /// Save after every command that modifies the store.
+    Immediate,
+    /// Save once the store hasn't been modified for the given time.
+    ///
+    /// Like [`Self::Interval`], this is checked in the background once a store with a timed strategy
+    /// is registered with [`Builder::store`](crate::Builder::store) or modified through [`with_store`](crate::with_store).
+    Debounced(Duration),
+    /// Save at most once per interval while the store has unsaved modifications.
+    Interval(Duration),
+    /// Never save automatically, not even when the app exits.
+    ExplicitOnly,
+    /// Save when the app exits, or when a flush is requested by [`Builder::save_on_window_events`](crate::Builder::save_on_window_events)
+    /// or [`flush`](crate::flush).
+    #[default]
+    OnExitOnly,
+}
+
+/// Marks `revision` as written, clearing the unsaved changes unless the store was modified since.
+fn record_written(written: &mut u64, revision: u64, unsaved: &Mutex<Option<UnsavedChanges>>) {
+    *written = revision;
+    let mut unsaved = unsaved.lock().expect("mutex poisoned");
+    if unsaved.map_or(false, |unsaved| unsaved.revision <= revision) {
+        *unsaved = None;
+    }
+}
+
+/// The serialized contents of a store, prepared with [`Store::prepare_save`] and written without the store.
+pub(crate) struct PreparedSave {
+    path: PathBuf,
+    host: Arc<dyn StoreHost>,
+    store_path: PathBuf,
+    bytes: Vec<u8>,
+    signature: Option<String>,
+    revision: u64,
+    written: Arc<Mutex<u64>>,
+    unsaved: Arc<Mutex<Option<UnsavedChanges>>>,
+}
+
+impl PreparedSave {
+    /// Writes the store file, unless a newer revision of the store was written in the meantime.
+    pub(crate) fn write(&self) -> Result<(), Error> {
+        let mut written = self.written.lock().expect("mutex poisoned");
+        if *written > self.revision {
+            return Ok(());
+        }
+        write_signed(&self.store_path, &self.bytes, self.signature.as_deref())?;
+        record_written(&mut written, self.revision, &self.unsaved);
+        Ok(())
+    }
+
+    /// Logs `err` and emits it as a `store://error` event, for when the store was unloaded during the save.
+    pub(crate) fn report_error(&self, err: Error) {
+        emit_error(&*self.host, &self.path, "save", &err);
+    }
+}
+
+/// Logs `err` and emits it as a `store://error` event.
+fn emit_error(host: &dyn StoreHost, path: &Path, operation: &str, err: &Error) {
+    warn!("Failed to {} store {:?}: {}", operation, path, err);
+    let payload = ErrorPayload {
+        schema: PAYLOAD_SCHEMA,
+        path,
+        operation,
+        error: err.to_string(),
+    };
+    let emitted = serde_json::to_value(payload)
+        .map_err(Error::from)
+        .and_then(|payload| host.emit_event(ERROR_EVENT, payload));
+    if let Err(err) = emitted {
+        warn!("Failed to emit error event for store {:?}: {}", path, err);
+    }
+}
+
+/// Times of the first and the last modification since a store was last saved.
+#[derive(Debug, Clone, Copy)]
+struct UnsavedChanges {
+    since: Instant,
+    last: Instant,
+    /// The revision of the store after the last modification.
+    revision: u64,
+}
+
+/// The JSON type of a value in a [`Store`], as returned by [`Store::type_of`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ValueType {
+    String,
+    Number,
+    Bool,
+    Object,
+    Array,
+    Null,
+    /// The key does not exist in the store.
+    Missing,
+}
+
+impl From<&JsonValue> for ValueType {
+    fn from(value: &JsonValue) -> Self {
+        match value {
+            JsonValue::String(_) => Self::String,
+            JsonValue::Number(_) => Self::Number,
+            JsonValue::Bool(_) => Self::Bool,
+            JsonValue::Object(_) => Self::Object,
+            JsonValue::Array(_) => Self::Array,
+            JsonValue::Null => Self::Null,
+        }
+    }
+}
+
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Bool => "bool",
+            Self::Object => "object",
+            Self::Array => "array",
+            Self::Null => "null",
+            Self::Missing => "missing",
+        })
+    }
+}
+
+/// Attempts a lossless conversion of `value` into the `expected` type.
+///
+/// Strings are only converted if converting the result back yields the same string, so e.g. `"1.50"`,
+/// `"1e2"`, `"007"` and `" 42"` are kept as they are.
+fn coerce(value: &JsonValue, expected: ValueType) -> Option<JsonValue> {
+    match (value, expected) {
+        (JsonValue::String(s), ValueType::Number) => serde_json::from_str::<serde_json::Number>(s)
+            .ok()
+            .filter(|number| number.to_string() == *s)
+            .map(JsonValue::Number),
+        (JsonValue::String(s), ValueType::Bool) => s.parse().ok().map(JsonValue::Bool),
+        (JsonValue::Number(n), ValueType::String) => Some(JsonValue::String(n.to_string())),
+        (JsonValue::Bool(b), ValueType::String) => Some(JsonValue::String(b.to_string())),
+        _ => None,
+    }
+}
+
+/// A change of a single value between two stores.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueChange {
+    pub old: JsonValue,
+    pub new: JsonValue,
+}
+
+/// A single JSON Patch (RFC 6902) operation on a top-level store key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum PatchOperation {
+    Add { path: String, value: JsonValue },
+    Remove { path: String },
+    Replace { path: String, value: JsonValue },
+}
+
+/// Encodes a top-level key as a JSON Pointer (RFC 6901).
+fn json_pointer(key: &str) -> String {
+    format!("/{}", key.replace('~', "~0").replace('/', "~1"))
+}
+
+impl PatchOperation {
+    /// Returns the store key the operation applies to.
+    pub fn key(&self) -> String {
+        let (Self::Add { path, .. } | Self::Remove { path } | Self::Replace { path, .. }) = self;
+        path.strip_prefix('/')
+            .unwrap_or(path)
+            .replace("~1", "/")
+            .replace("~0", "~")
+    }
+}
+
+/// The differences between two stores, as returned by [`Store::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StoreDiff {
+    /// Keys only present in the other store.
+    pub added: HashMap<String, JsonValue>,
+    /// Keys only present in this store.
+    pub removed: HashMap<String, JsonValue>,
+    /// Keys present in both stores with different values.
+    pub changed: HashMap<String, ValueChange>,
+}
+
+impl StoreDiff {
+    pub(crate) fn between(
+        old: &HashMap<String, JsonValue>,
+        new: &HashMap<String, JsonValue>,
+    ) -> Self {
+        let mut diff = Self::default();
+        for (key, old_value) in old {
+            match new.get(key) {
+                Some(new_value) if new_value != old_value => {
+                    diff.changed.insert(
+                        key.clone(),
+                        ValueChange {
+                            old: old_value.clone(),
+                            new: new_value.clone(),
+                        },
+                    );
+                }
+                Some(_) => {}
+                None => {
+                    diff.removed.insert(key.clone(), old_value.clone());
+                }
+            }
+        }
+        for (key, new_value) in new {
+            if !old.contains_key(key) {
+                diff.added.insert(key.clone(), new_value.clone());
+            }
+        }
+        diff
+    }
+
+    /// Converts the diff into a JSON Patch (RFC 6902) turning the old contents into the new ones.
+    ///
+    /// Operations are ordered by key so the patch is deterministic.
+    pub fn to_patch(&self) -> Vec<PatchOperation> {
+        let mut keys: Vec<&String> = self
+            .removed
+            .keys()
+            .chain(self.changed.keys())
+            .chain(self.added.keys())
+            .collect();
+        keys.sort_unstable();
+
+        keys.into_iter()
+            .map(|key| {
+                let path = json_pointer(key);
+                if let Some(change) = self.changed.get(key) {
+                    PatchOperation::Replace {
+                        path,
+                        value: change.new.clone(),
+                    }
+                } else if let Some(value) = self.added.get(key) {
+                    PatchOperation::Add {
+                        path,
+                        value: value.clone(),
+                    }
+                } else {
+                    PatchOperation::Remove { path }
+                }
+            })
+            .collect()
+    }
+
+    /// Returns `true` if both stores had the same contents.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// 64-bit FNV-1a hash, stable across platforms and Rust versions.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Compares two strings, treating runs of ASCII digits as numbers.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    fn split_digits(s: &[u8]) -> (&[u8], &[u8]) {
+        let n = s.iter().take_while(|c| c.is_ascii_digit()).count();
+        s.split_at(n)
+    }
+
+    fn trim_zeros(s: &[u8]) -> &[u8] {
+        let n = s.iter().take_while(|&&c| c == b'0').count();
+        &s[n..]
+    }
+
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    while let (Some(&x), Some(&y)) = (a.first(), b.first()) {
+        let ord = if x.is_ascii_digit() && y.is_ascii_digit() {
+            let (num_a, rest_a) = split_digits(a);
+            let (num_b, rest_b) = split_digits(b);
+            a = rest_a;
+            b = rest_b;
+            let (num_a, num_b) = (trim_zeros(num_a), trim_zeros(num_b));
+            num_a.len().cmp(&num_b.len()).then_with(|| num_a.cmp(num_b))
+        } else {
+            a = &a[1..];
+            b = &b[1..];
+            x.cmp(&y)
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
 /// Builds a [`Store`]
 pub struct StoreBuilder<R: Runtime> {
-    app: AppHandle<R>,
+    /// `None` until the plugin provides its app handle, see [`Self::from_path`].
+    host: Option<Arc<dyn StoreHost>>,
     path: PathBuf,
     defaults: Option<HashMap<String, JsonValue>>,
     cache: HashMap<String, JsonValue>,
     serialize: SerializeFn,
     deserialize: DeserializeFn,
+    custom_format: bool,
+    schema: HashMap<String, ValueType>,
+    coerce_types: bool,
+    before_save: Option<BeforeSaveHook<R>>,
+    after_save: Option<AfterSaveHook<R>>,
+    lazy_load: bool,
+    change_event: String,
+    quiet_keys: Option<KeyFilter>,
+    coalescing: Option<Coalescing>,
+    legacy_change_payloads: bool,
+    group: Option<String>,
+    save_strategy: SaveStrategy,
+    history_capacity: usize,
+    change_log: bool,
+    journal: bool,
+    compact_after: Option<u64>,
+    #[cfg(feature = "encryption")]
+    encryption: Option<KeyEncryption>,
+    sensitive_keys: Option<KeyFilter>,
+    prune_on_save: bool,
+    #[cfg(feature = "compression")]
+    compress_above: Option<usize>,
+    save_on_change: bool,
+    #[cfg(feature = "signing")]
+    signing_key: Option<Vec<u8>>,
+    envelope: bool,
+    shard_separator: Option<char>,
+    legacy: Option<LegacyFormat>,
+    read_only: bool,
+    frontend_owned: bool,
+    writer: Option<Writer>,
+    symlink_policy: SymlinkPolicy,
+    number_mode: NumberMode,
+    error_policy: ErrorPolicy,
+    clock: Arc<dyn Clock>,
+    extension: Option<String>,
+    extension_policy: ExtensionPolicy,
+    formats: Vec<(FileFormat, DeserializeFn)>,
+    aliases: HashMap<String, String>,
+    indexes: HashMap<String, Index>,
+    #[cfg(feature = "search")]
+    search: bool,
 }
 
 impl<R: Runtime> StoreBuilder<R> {
@@ -53,13 +732,88 @@ impl<R: Runtime> StoreBuilder<R> {
     /// # }
     /// ```
     pub fn new(app: AppHandle<R>, path: PathBuf) -> Self {
+        Self::with_host(app, path)
+    }
+
+    /// Creates a new [`StoreBuilder`] for a store that doesn't belong to a running app.
+    ///
+    /// See [`DirectoryHost`](crate::DirectoryHost).
+    pub fn with_host(host: impl StoreHost + 'static, path: PathBuf) -> Self {
+        Self {
+            host: Some(Arc::new(host)),
+            ..Self::from_path(path)
+        }
+    }
+
+    /// Creates a new [`StoreBuilder`] to be registered with [`Builder::store`](crate::Builder::store)
+    /// before the app exists.
+    ///
+    /// The plugin builds the store with its app handle during initialization, calling [`Self::build`]
+    /// on such a builder yourself panics.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use serde_json::json;
+    /// use tauri_plugin_store::{PluginBuilder, StoreBuilder};
+    ///
+    /// let plugin = PluginBuilder::new()
+    ///   .store(StoreBuilder::from_path("settings.json".parse()?).default("theme".into(), json!("light")))
+    ///   .build();
+    ///
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_path(path: PathBuf) -> Self {
         Self {
-            app,
+            host: None,
             path,
             defaults: None,
             cache: Default::default(),
             serialize: default_serialize,
             deserialize: default_deserialize,
+            custom_format: false,
+            schema: Default::default(),
+            coerce_types: false,
+            before_save: None,
+            after_save: None,
+            lazy_load: false,
+            change_event: CHANGE_EVENT.into(),
+            quiet_keys: None,
+            coalescing: None,
+            legacy_change_payloads: false,
+            group: None,
+            history_capacity: 0,
+            save_strategy: Default::default(),
+            change_log: false,
+            journal: false,
+            compact_after: None,
+            #[cfg(feature = "encryption")]
+            encryption: None,
+            sensitive_keys: None,
+            prune_on_save: false,
+            #[cfg(feature = "compression")]
+            compress_above: None,
+            save_on_change: false,
+            #[cfg(feature = "signing")]
+            signing_key: None,
+            envelope: false,
+            shard_separator: None,
+            legacy: None,
+            read_only: false,
+            frontend_owned: false,
+            writer: None,
+            symlink_policy: Default::default(),
+            number_mode: Default::default(),
+            error_policy: Default::default(),
+            clock: Arc::new(SystemClock),
+            extension: None,
+            extension_policy: Default::default(),
+            formats: Vec::new(),
+            aliases: HashMap::new(),
+            indexes: HashMap::new(),
+            #[cfg(feature = "search")]
+            search: false,
         }
     }
 
@@ -120,9 +874,32 @@ impl<R: Runtime> StoreBuilder<R> {
     /// # }
     pub fn serialize(mut self, serialize: SerializeFn) -> Self {
         self.serialize = serialize;
+        self.custom_format = true;
         self
     }
 
+    /// Writes the store file in a scrambled format, so users aren't tempted to hand-edit internal state
+    /// that would break the app.
+    ///
+    /// This is obfuscation, not encryption: it only resists casual edits. Use [`Self::encrypt_keys`] for secrets
+    /// and [`Self::sign_with`] to detect edits. Existing JSON files are still read and converted on the next save.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("state.dat".parse()?)
+    ///   .obfuscated();
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn obfuscated(self) -> Self {
+        self.serialize(obfuscated_serialize)
+            .deserialize(obfuscated_deserialize)
+            .detect_format(FileFormat::Json, default_deserialize)
+    }
+
     /// Defines a custom deserialization function
     ///
     /// # Examples
@@ -137,174 +914,2631 @@ impl<R: Runtime> StoreBuilder<R> {
     /// # }
     pub fn deserialize(mut self, deserialize: DeserializeFn) -> Self {
         self.deserialize = deserialize;
+        self.custom_format = true;
         self
     }
 
-    /// Builds the [`Store`].
+    /// Registers the expected type of the values stored under the given keys.
+    ///
+    /// Values read through [`Store::get_checked`] (and the `get` command) that don't match
+    /// their registered type are rejected with [`Error::TypeMismatch`].
     ///
     /// # Examples
     /// ```
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use tauri_plugin_store::StoreBuilder;
+    /// use tauri_plugin_store::{StoreBuilder, ValueType};
+    /// use std::collections::HashMap;
     ///
-    /// let store = StoreBuilder::new("store.bin".parse()?).build();
+    /// let mut schema = HashMap::new();
+    ///
+    /// schema.insert("volume".to_string(), ValueType::Number);
+    ///
+    /// let builder = StoreBuilder::new("store.json".parse()?)
+    ///   .schema(schema);
     ///
     /// # Ok(())
     /// # }
-    pub fn build(self) -> Store<R> {
-        Store {
-            app: self.app,
-            path: self.path,
-            defaults: self.defaults,
-            cache: self.cache,
-            serialize: self.serialize,
-            deserialize: self.deserialize,
-        }
+    pub fn schema(mut self, schema: HashMap<String, ValueType>) -> Self {
+        self.schema = schema;
+        self
     }
-}
-
-#[derive(Clone)]
-pub struct Store<R: Runtime> {
-    app: AppHandle<R>,
-    pub(crate) path: PathBuf,
-    defaults: Option<HashMap<String, JsonValue>>,
-    cache: HashMap<String, JsonValue>,
-    serialize: SerializeFn,
-    deserialize: DeserializeFn,
-}
-
-impl<R: Runtime> Store<R> {
-    /// Update the store from the on-disk state
-    pub fn load(&mut self) -> Result<(), Error> {
-        let app_dir = self
-            .app
-            .path_resolver()
-            .app_data_dir()
-            .expect("failed to resolve app dir");
-        let store_path = app_dir.join(&self.path);
-
-        let bytes = read(store_path)?;
-
-        self.cache
-            .extend((self.deserialize)(&bytes).map_err(Error::Deserialize)?);
 
-        Ok(())
+    /// Attempts to coerce values that don't match the schema before rejecting them.
+    ///
+    /// Only lossless conversions are performed: numeric or boolean strings into numbers or booleans,
+    /// and numbers or booleans into strings. Strings are only converted if they are written exactly like
+    /// the converted value, e.g. `"42"` and `"true"` but not `"042"`, `"1.50"` or `" true"`.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("store.json".parse()?)
+    ///   .coerce_types(true);
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn coerce_types(mut self, coerce_types: bool) -> Self {
+        self.coerce_types = coerce_types;
+        self
     }
 
-    /// Saves the store to disk
-    pub fn save(&self) -> Result<(), Error> {
-        let app_dir = self
-            .app
-            .path_resolver()
-            .app_data_dir()
-            .expect("failed to resolve app dir");
-        let store_path = app_dir.join(&self.path);
-
-        create_dir_all(store_path.parent().expect("invalid store path"))?;
-
-        let bytes = (self.serialize)(&self.cache).map_err(Error::Serialize)?;
-        let mut f = File::create(&store_path)?;
-        f.write_all(&bytes)?;
-
-        Ok(())
+    /// Registers a hook that runs before every save.
+    ///
+    /// Returning `false` cancels the save, which then fails with [`Error::SaveCancelled`].
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    /// use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+    ///
+    /// let busy = Arc::new(AtomicBool::new(false));
+    ///
+    /// let builder = StoreBuilder::new("store.json".parse()?)
+    ///   .before_save(move |_store| !busy.load(Ordering::SeqCst));
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn before_save<F: Fn(&Store<R>) -> bool + Send + Sync + 'static>(
+        mut self,
+        hook: F,
+    ) -> Self {
+        self.before_save = Some(Arc::new(hook));
+        self
     }
 
-    pub fn insert(&mut self, key: String, value: JsonValue) -> Result<(), Error> {
-        self.cache.insert(key.clone(), value.clone());
-        self.app.emit_all(
-            "store://change",
-            ChangePayload {
-                path: &self.path,
-                key: &key,
-                value: &value,
-            },
-        )?;
-
-        Ok(())
+    /// Registers a hook that runs after every save attempt with its result.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("store.json".parse()?)
+    ///   .after_save(|_store, result| {
+    ///     if let Err(err) = result {
+    ///       eprintln!("failed to save settings: {err}");
+    ///     }
+    ///   });
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn after_save<F: Fn(&Store<R>, &Result<(), Error>) + Send + Sync + 'static>(
+        mut self,
+        hook: F,
+    ) -> Self {
+        self.after_save = Some(Arc::new(hook));
+        self
+    }
+
+    /// Keeps loaded values as raw JSON and only parses them on first access.
+    ///
+    /// This cuts load time and memory for large stores of which only a few keys are used.
+    /// Lazy loading always reads the file as JSON, bypassing a custom [`Self::deserialize`] function.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("cache.json".parse()?)
+    ///   .lazy_load(true);
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn lazy_load(mut self, lazy_load: bool) -> Self {
+        self.lazy_load = lazy_load;
+        self
+    }
+
+    /// Overrides the name of the event emitted when a key changes. Defaults to `store://change`.
+    ///
+    /// Frontends must pass the same name to the `Store` constructor to receive the events.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .change_event("settings-changed");
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn change_event(mut self, event: impl Into<String>) -> Self {
+        self.change_event = event.into();
+        self
+    }
+
+    /// Never emits change events for the keys selected by `filter`, e.g. high-frequency scratch values like
+    /// scroll positions. The keys are still persisted, and callbacks registered with [`Store::watch`] still run.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{KeyFilter, StoreBuilder};
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .quiet_keys(KeyFilter::default().include("scroll.*"));
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn quiet_keys(mut self, filter: KeyFilter) -> Self {
+        self.quiet_keys = Some(filter);
+        self
+    }
+
+    /// Coalesces writes of the keys selected by `filter`, e.g. window geometry or a playback position, committing
+    /// at most one value per key and `interval`.
+    ///
+    /// The first write of a key is committed right away. Later writes within `interval` only replace a staged value,
+    /// which is committed to the store, with a change event, once `interval` has passed since the last commit.
+    /// Reads return the last committed value. Staged values are committed in the background for stores registered
+    /// with [`Builder::store`](crate::Builder::store), and before stores are saved on exit.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{KeyFilter, StoreBuilder};
+    /// use std::time::Duration;
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .coalesce(KeyFilter::default().include("window.*"), Duration::from_millis(250));
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn coalesce(mut self, filter: KeyFilter, interval: Duration) -> Self {
+        self.coalescing = Some(Coalescing::new(filter, interval));
+        self
+    }
+
+    /// Emits change events in the shape of version 1 of [`PAYLOAD_SCHEMA`], holding only `path`, `key` and `value`.
+    ///
+    /// A compatibility shim for apps whose windows can't all be upgraded to the current payload at once.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .legacy_change_payloads();
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn legacy_change_payloads(mut self) -> Self {
+        self.legacy_change_payloads = true;
+        self
+    }
+
+    /// Adds the store to a group of related stores that are saved and reloaded together.
+    ///
+    /// See [`StoreCollection::save_group`](crate::StoreCollection::save_group).
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("keybindings.json".parse()?)
+    ///   .group("profile");
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn group(mut self, group: impl Into<String>) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    /// Sets when the store is saved automatically. Defaults to [`SaveStrategy::OnExitOnly`].
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::time::Duration;
+    /// use tauri_plugin_store::{SaveStrategy, StoreBuilder};
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .save_strategy(SaveStrategy::Debounced(Duration::from_millis(500)));
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn save_strategy(mut self, strategy: SaveStrategy) -> Self {
+        self.save_strategy = strategy;
+        self
+    }
+
+    /// Saves the store once it hasn't been modified for `delay`, shorthand for
+    /// [`SaveStrategy::Debounced`] with [`Self::save_strategy`].
+    ///
+    /// Every modification restarts the delay, so a burst of writes results in a single save.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::time::Duration;
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?).auto_save(Duration::from_millis(500));
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn auto_save(self, delay: Duration) -> Self {
+        self.save_strategy(SaveStrategy::Debounced(delay))
+    }
+
+    /// Saves the store right after every modification, whether made from Rust or by the frontend.
+    ///
+    /// Unlike [`SaveStrategy::Immediate`], which saves after commands, this also covers calls like [`Store::insert`].
+    /// Stores managed by the plugin are written by a background worker, which logs failures and emits them as
+    /// `store://error` events. Other stores are written right away and the error is returned. A read-only store or
+    /// a save cancelled by [`Self::before_save`] is reported to the caller either way, and the modification itself
+    /// is kept.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?).save_on_change();
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn save_on_change(mut self) -> Self {
+        self.save_on_change = true;
+        self
+    }
+
+    /// Records the last `capacity` mutations of the store, queryable with [`Store::history`].
+    ///
+    /// Helps tracking down where an unexpected value came from.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .history(100);
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn history(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity;
+        self
+    }
+
+    /// Appends every change to a log file next to the store file, which is emptied when the store is saved.
+    ///
+    /// This lets a [`StoreMirror`](crate::StoreMirror) in another process see changes before they are saved.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .change_log();
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn change_log(mut self) -> Self {
+        self.change_log = true;
+        self
+    }
+
+    /// Writes every modification to a journal next to the store file before applying it, and replays the
+    /// journal over the store file when the store is loaded.
+    ///
+    /// Modifications survive a crash without the store being saved after each one. The journal is flushed to disk
+    /// on every modification and emptied when the store is saved. It uses the same file as [`Self::change_log`],
+    /// so a [`StoreMirror`](crate::StoreMirror) sees journaled changes as well.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .journal();
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn journal(mut self) -> Self {
+        self.journal = true;
+        self
+    }
+
+    /// Compacts the store once the log of [`Self::change_log`] or [`Self::journal`] grows beyond `bytes`,
+    /// rewriting the store file and emptying the log, so long-lived stores that are rarely saved don't grow
+    /// unboundedly. See [`Store::compact`].
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .journal()
+    ///   .compact_after(1024 * 1024);
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn compact_after(mut self, bytes: u64) -> Self {
+        self.compact_after = Some(bytes);
+        self
+    }
+
+    /// Encrypts the values of the keys selected by `filter` with `data_key` in the store file and its change log,
+    /// leaving the other values as plain, user-editable JSON.
+    ///
+    /// An encrypted value is stored as an object with a single `$encrypted` field. Plain values of selected keys,
+    /// e.g. secrets stored before this was enabled, are encrypted on the next save. The data key should come from
+    /// a secure place like the OS keychain rather than the app's binary.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{KeyFilter, StoreBuilder};
+    ///
+    /// # let data_key = [0; 32];
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .encrypt_keys(KeyFilter::default().include("auth.*"), data_key);
+    ///
+    /// # Ok(())
+    /// # }
+    #[cfg(feature = "encryption")]
+    pub fn encrypt_keys(mut self, filter: KeyFilter, data_key: [u8; 32]) -> Self {
+        self.encryption = Some(KeyEncryption::new(filter, data_key));
+        self
+    }
+
+    /// Marks the keys selected by `filter` as sensitive, so [`Store::export_redacted`] masks their values.
+    ///
+    /// Keys encrypted with [`Self::encrypt_keys`] are always treated as sensitive.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{KeyFilter, StoreBuilder};
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .sensitive_keys(KeyFilter::default().include("auth.*").include("email"));
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn sensitive_keys(mut self, filter: KeyFilter) -> Self {
+        self.sensitive_keys = Some(filter);
+        self
+    }
+
+    /// Leaves keys whose values are `null`, empty objects or empty arrays out of the file when saving.
+    ///
+    /// The keys stay in memory until the store is reloaded, use [`Store::prune`] to remove them right away.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?).prune_on_save();
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn prune_on_save(mut self) -> Self {
+        self.prune_on_save = true;
+        self
+    }
+
+    /// Compresses values whose JSON encoding is larger than `threshold` bytes in the store file,
+    /// leaving smaller values as plain, user-editable JSON.
+    ///
+    /// A compressed value is stored as an object with a single `$deflate` field holding the deflated JSON in base64.
+    /// Values are compressed before they are encrypted with [`Self::encrypt_keys`]. Compressed values are only
+    /// recognized while compression is enabled, so the threshold can be changed later, but stores saved with compression
+    /// keep their compressed values if it's turned off. Inserting a value shaped like a compressed one fails with
+    /// [`Error::ReservedValue`](crate::Error::ReservedValue). The change log isn't compressed.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?).compress_values_above(16 * 1024);
+    ///
+    /// # Ok(())
+    /// # }
+    #[cfg(feature = "compression")]
+    pub fn compress_values_above(mut self, threshold: usize) -> Self {
+        self.compress_above = Some(threshold);
+        self
+    }
+
+    /// Signs the store file with an HMAC-SHA256 of its contents, kept in a `.sig` file next to it, and
+    /// fails to load it with [`Error::TamperDetected`] if the file was edited outside of the app.
+    ///
+    /// Meant for stores like licenses or entitlements, where manual edits must be detected rather than honored.
+    /// A missing signature counts as tampering, so sign a store before it is first saved. Only the store
+    /// file is signed, not the log of [`Self::change_log`] or [`Self::journal`]. Can't be combined with
+    /// [`Self::shard_by_prefix`].
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// # let signing_key = b"secret".to_vec();
+    /// let builder = StoreBuilder::new("license.json".parse()?)
+    ///   .sign_with(signing_key);
+    ///
+    /// # Ok(())
+    /// # }
+    #[cfg(feature = "signing")]
+    pub fn sign_with(mut self, key: impl Into<Vec<u8>>) -> Self {
+        self.signing_key = Some(key.into());
+        self
+    }
+
+    /// Saves the store as `{ "meta": {...}, "data": {...} }`, recording when each key was last changed,
+    /// when it expires and the type of its value.
+    ///
+    /// Both this and the default flat format are read transparently, so existing files are converted on the next save.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .envelope();
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn envelope(mut self) -> Self {
+        self.envelope = true;
+        self
+    }
+
+    /// Splits the store into one file per key prefix, so large stores aren't read and written as one blob.
+    ///
+    /// The prefix of a key is the part before the first `separator`. The store's path becomes a directory
+    /// containing one file per prefix, and saving only rewrites the files of prefixes that changed.
+    /// Keys without a prefix are stored in `_.json`. Not combined with [`Self::envelope`].
+    ///
+    /// All shard files are read when the store is loaded, combine with [`Self::lazy_load`] to only parse
+    /// the values that are accessed. Shard files are named with the store's [`Self::extension`], which is
+    /// required for a custom format. Stores that are also built with [`Self::sign_with`] or have a custom format
+    /// without an extension fail to load and save with [`Error::Sharding`].
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// // `a.thumbnail` is stored in `cache/a.json`, `b.thumbnail` in `cache/b.json`
+    /// let builder = StoreBuilder::new("cache".parse()?)
+    ///   .shard_by_prefix('.');
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn shard_by_prefix(mut self, separator: char) -> Self {
+        self.shard_separator = Some(separator);
+        self
+    }
+
+    /// Keeps writing the store in its previous on-disk format as well, e.g. after switching to [`Self::envelope`]
+    /// or a new path, so users can roll back to an older version of the app without losing their settings.
+    ///
+    /// The legacy file is written for [`LegacyFormat::versions`] app versions. When it is newer than the
+    /// store file, because an older version of the app saved it, it is loaded instead.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{LegacyFormat, StoreBuilder};
+    ///
+    /// let builder = StoreBuilder::new("settings.v2.json".parse()?)
+    ///   .envelope()
+    ///   .legacy_format(LegacyFormat::new("settings.json".parse()?).versions(3));
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn legacy_format(mut self, legacy: LegacyFormat) -> Self {
+        self.legacy = Some(legacy);
+        self
+    }
+
+    /// Rejects all modifications of the store and never saves it, e.g. to view a bundled resource or a file
+    /// the user selected.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("/etc/my-app/defaults.json".parse()?)
+    ///   .read_only();
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Only lets the context holding the writer token, initially `writer`, modify the store. Modifications
+    /// by other windows or Rust code fail with [`Error::NotWriter`].
+    ///
+    /// Useful for apps that designate a main window as the single source of truth. The holder can hand the token
+    /// on with [`Store::transfer_writer`]. Expired keys are removed regardless of the holder.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{StoreBuilder, Writer};
+    ///
+    /// let builder = StoreBuilder::new("document.json".parse()?)
+    ///   .single_writer(Writer::Window("main".into()));
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn single_writer(mut self, writer: Writer) -> Self {
+        self.writer = Some(writer);
+        self
+    }
+
+    /// Marks the store as written by the frontend, so it's saved whenever a page loads in a window, e.g. on a
+    /// reload or a hot reload during development.
+    ///
+    /// Changes made by a page that is reloaded between `set` and `save` are otherwise only saved on exit,
+    /// which never happens when the dev server restarts the app.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("editor.json".parse()?)
+    ///   .frontend_owned();
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn frontend_owned(mut self) -> Self {
+        self.frontend_owned = true;
+        self
+    }
+
+    /// Sets how the store treats its path being a symbolic link or junction. Defaults to [`SymlinkPolicy::Follow`].
+    ///
+    /// A stale or malicious link in the app data directory can otherwise redirect writes to any file the app
+    /// has access to.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{StoreBuilder, SymlinkPolicy};
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .symlink_policy(SymlinkPolicy::Refuse);
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn symlink_policy(mut self, policy: SymlinkPolicy) -> Self {
+        self.symlink_policy = policy;
+        self
+    }
+
+    /// Sets how numbers are stored. Defaults to [`NumberMode::Preserve`].
+    ///
+    /// JavaScript numbers are doubles, so integers beyond `Number.MAX_SAFE_INTEGER` such as 64-bit IDs are
+    /// silently rounded when the frontend reads them, unless they are stored as strings.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{NumberMode, StoreBuilder};
+    ///
+    /// let builder = StoreBuilder::new("accounts.json".parse()?)
+    ///   .number_mode(NumberMode::StringifyUnsafe);
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn number_mode(mut self, mode: NumberMode) -> Self {
+        self.number_mode = mode;
+        self
+    }
+
+    /// Sets how failures of background operations are handled. Defaults to [`ErrorPolicy::LogAndContinue`].
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{ErrorPolicy, StoreBuilder};
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .on_error(ErrorPolicy::PropagateToFrontend);
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn on_error(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Sets the source of the current time used for TTLs and timestamps. Defaults to [`SystemClock`].
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{Clock, StoreBuilder};
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    ///
+    /// struct MockClock(AtomicU64);
+    ///
+    /// impl Clock for MockClock {
+    ///   fn now_millis(&self) -> u64 {
+    ///     self.0.load(Ordering::SeqCst)
+    ///   }
+    /// }
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?).clock(MockClock(AtomicU64::new(0)));
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sets the file extension matching the store's format, without the leading dot.
+    ///
+    /// Defaults to `json`, unless a custom [`Self::serialize`] or [`Self::deserialize`] function is set.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("settings".parse()?)
+    ///   .serialize(|cache| serde_json::to_vec_pretty(&cache).map_err(Into::into))
+    ///   .extension("jsonc");
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn extension(mut self, extension: impl Into<String>) -> Self {
+        self.extension = Some(extension.into());
+        self
+    }
+
+    /// Sets whether the store's path has to end in the extension of its format, see [`Self::extension`].
+    /// Defaults to [`ExtensionPolicy::Ignore`].
+    ///
+    /// This prevents e.g. a TOML serializer writing into `settings.json`, confusing users who edit the file.
+    /// Stores built with [`Self::shard_by_prefix`] are stored in a directory and never checked.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{ExtensionPolicy, StoreBuilder};
+    ///
+    /// // stored in `settings.json`
+    /// let builder = StoreBuilder::new("settings".parse()?)
+    ///   .extension_policy(ExtensionPolicy::Append);
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn extension_policy(mut self, policy: ExtensionPolicy) -> Self {
+        self.extension_policy = policy;
+        self
+    }
+
+    /// Loads files detected as `format` with `deserialize` instead of the store's own [`Self::deserialize`] function.
+    ///
+    /// This lets apps switch the format of a store: files in the previous format are still read, and written
+    /// in the new format on the next save. Detection is based on the first bytes of the file, see [`FileFormat::detect`].
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::{FileFormat, StoreBuilder};
+    ///
+    /// // the store's `serialize` and `deserialize` functions were switched to a binary format
+    /// let builder = StoreBuilder::new("settings.bin".parse()?)
+    ///   .detect_format(FileFormat::Json, |bytes| serde_json::from_slice(bytes).map_err(Into::into));
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn detect_format(mut self, format: FileFormat, deserialize: DeserializeFn) -> Self {
+        self.formats.push((format, deserialize));
+        self
+    }
+
+    /// Renames `old_key` to `new_key`: reads and writes of the old key use the new one, and loaded files
+    /// holding the old key are migrated to the new one.
+    ///
+    /// If a file holds both keys, the old value is only kept if the new one is missing or has its default value.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("settings.json".parse()?)
+    ///   .alias("darkMode", "appearance.darkMode");
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn alias(mut self, old_key: impl Into<String>, new_key: impl Into<String>) -> Self {
+        self.aliases.insert(old_key.into(), new_key.into());
+        self
+    }
+
+    /// Maintains an index named `name` over the field at the JSON pointer `field` of the values of keys
+    /// matching the glob pattern `keys`, to look them up with [`Store::find_by`] without a full scan.
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// // index the `tag` field of `entries.1`, `entries.2`, ...
+    /// let builder = StoreBuilder::new("bookmarks.json".parse()?)
+    ///   .index("byTag", "entries.*", "/tag");
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn index(
+        mut self,
+        name: impl Into<String>,
+        keys: impl Into<String>,
+        field: impl Into<String>,
+    ) -> Self {
+        self.indexes
+            .insert(name.into(), Index::new(keys.into(), field.into()));
+        self
+    }
+
+    /// Maintains an in-memory inverted index over the words of the store's string values, to look
+    /// them up with [`Store::search`].
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let builder = StoreBuilder::new("notes.json".parse()?)
+    ///   .full_text_search();
+    ///
+    /// # Ok(())
+    /// # }
+    #[cfg(feature = "search")]
+    pub fn full_text_search(mut self) -> Self {
+        self.search = true;
+        self
+    }
+
+    /// Provides the app handle of a builder created with [`Self::from_path`].
+    pub(crate) fn app(mut self, app: AppHandle<R>) -> Self {
+        if self.host.is_none() {
+            self.host = Some(Arc::new(app));
+        }
+        self
+    }
+
+    /// Builds the [`Store`].
+    ///
+    /// # Examples
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use tauri_plugin_store::StoreBuilder;
+    ///
+    /// let store = StoreBuilder::new("store.bin".parse()?).build();
+    ///
+    /// # Ok(())
+    /// # }
+    pub fn build(mut self) -> Store<R> {
+        let extension = self
+            .extension
+            .or_else(|| (!self.custom_format).then(|| "json".to_string()));
+        let shard_extension = extension.clone().filter(|_| self.shard_separator.is_some());
+        let extension = extension.filter(|_| self.shard_separator.is_none());
+        let mut enforced_extension = None;
+        if let Some(extension) = extension {
+            let matches = self.path.extension() == Some(OsStr::new(&extension));
+            match self.extension_policy {
+                ExtensionPolicy::Append if !matches => {
+                    let mut path = self.path.into_os_string();
+                    path.push(".");
+                    path.push(&extension);
+                    self.path = path.into();
+                }
+                ExtensionPolicy::Enforce => enforced_extension = Some(extension),
+                _ => {}
+            }
+        }
+
+        let mut store = Store {
+            host: self.host.expect(
+                "a store builder created with `from_path` must be registered with the plugin",
+            ),
+            path: self.path,
+            enforced_extension,
+            defaults: self.defaults,
+            cache: self.cache,
+            serialize: self.serialize,
+            deserialize: self.deserialize,
+            custom_format: self.custom_format,
+            schema: self.schema,
+            coerce_types: self.coerce_types,
+            before_save: self.before_save,
+            after_save: self.after_save,
+            lazy_load: self.lazy_load,
+            change_event: self.change_event,
+            quiet_keys: self.quiet_keys,
+            coalescing: self.coalescing,
+            legacy_change_payloads: self.legacy_change_payloads,
+            group: self.group,
+            silent: false,
+            origin: None,
+            txn_id: None,
+            history_capacity: self.history_capacity,
+            save_strategy: self.save_strategy,
+            unsaved: Default::default(),
+            revision: 0,
+            written: Default::default(),
+            status: Default::default(),
+            change_log: self.change_log,
+            journal: self.journal,
+            compact_after: self.compact_after,
+            #[cfg(feature = "encryption")]
+            encryption: self.encryption,
+            sensitive_keys: self.sensitive_keys,
+            prune_on_save: self.prune_on_save,
+            #[cfg(feature = "compression")]
+            compress_above: self.compress_above,
+            save_on_change: self.save_on_change,
+            save_queue: None,
+            #[cfg(feature = "signing")]
+            signing_key: self.signing_key,
+            log_len: Default::default(),
+            envelope: self.envelope,
+            shard_separator: self.shard_separator,
+            shard_extension,
+            legacy: self.legacy,
+            dirty_shards: Default::default(),
+            updated_at: Default::default(),
+            history: VecDeque::new(),
+            raw: Default::default(),
+            reader: None,
+            bindings: Vec::new(),
+            watchers: Vec::new(),
+            next_watch_id: 0,
+            expirations: Default::default(),
+            leases: Default::default(),
+            read_only: self.read_only,
+            frontend_owned: self.frontend_owned,
+            writer: self.writer,
+            replicating: false,
+            replication_outbox: Vec::new(),
+            symlink_policy: self.symlink_policy,
+            number_mode: self.number_mode,
+            error_policy: self.error_policy,
+            clock: self.clock,
+            formats: self.formats,
+            aliases: self.aliases,
+            indexes: self.indexes,
+            #[cfg(feature = "search")]
+            search: self.search.then(SearchIndex::default),
+        };
+        store.rebuild_indexes();
+        store
+    }
+}
+
+#[derive(Clone)]
+pub struct Store<R: Runtime> {
+    pub(crate) host: Arc<dyn StoreHost>,
+    pub(crate) path: PathBuf,
+    pub(crate) defaults: Option<HashMap<String, JsonValue>>,
+    cache: HashMap<String, JsonValue>,
+    pub(crate) serialize: SerializeFn,
+    deserialize: DeserializeFn,
+    pub(crate) custom_format: bool,
+    schema: HashMap<String, ValueType>,
+    coerce_types: bool,
+    before_save: Option<BeforeSaveHook<R>>,
+    after_save: Option<AfterSaveHook<R>>,
+    pub(crate) lazy_load: bool,
+    change_event: String,
+    quiet_keys: Option<KeyFilter>,
+    coalescing: Option<Coalescing>,
+    legacy_change_payloads: bool,
+    group: Option<String>,
+    silent: bool,
+    /// The label of the window whose command is currently modifying the store.
+    pub(crate) origin: Option<String>,
+    /// The transaction id supplied by the frontend for the command currently modifying the store.
+    txn_id: Option<String>,
+    save_strategy: SaveStrategy,
+    unsaved: Arc<Mutex<Option<UnsavedChanges>>>,
+    /// Incremented by every modification, so copies of the store taken for background saves can be ordered.
+    revision: u64,
+    /// The revision last written to disk, locked while the store is written.
+    written: Arc<Mutex<u64>>,
+    status: Arc<Mutex<PersistenceStatus>>,
+    history_capacity: usize,
+    history: VecDeque<HistoryEntry>,
+    pub(crate) change_log: bool,
+    pub(crate) journal: bool,
+    /// The change log size in bytes at which the store is compacted, see [`StoreBuilder::compact_after`].
+    pub(crate) compact_after: Option<u64>,
+    /// Set when the store was built with [`StoreBuilder::encrypt_keys`].
+    #[cfg(feature = "encryption")]
+    pub(crate) encryption: Option<KeyEncryption>,
+    /// Set when the store was built with [`StoreBuilder::sensitive_keys`].
+    sensitive_keys: Option<KeyFilter>,
+    /// Set when the store was built with [`StoreBuilder::prune_on_save`].
+    prune_on_save: bool,
+    /// Set when the store was built with [`StoreBuilder::compress_values_above`].
+    #[cfg(feature = "compression")]
+    compress_above: Option<usize>,
+    /// Set when the store was built with [`StoreBuilder::save_on_change`] or by [`Builder::save_on_change`](crate::Builder::save_on_change).
+    pub(crate) save_on_change: bool,
+    /// The save queue of the plugin's collection, set when the store is added to it.
+    pub(crate) save_queue: Option<SaveQueue>,
+    /// Set when the store was built with [`StoreBuilder::sign_with`].
+    #[cfg(feature = "signing")]
+    pub(crate) signing_key: Option<Vec<u8>>,
+    /// The size of the change log in bytes, as far as this store wrote it.
+    pub(crate) log_len: Arc<AtomicU64>,
+    pub(crate) envelope: bool,
+    pub(crate) shard_separator: Option<char>,
+    /// The extension of the shard files of a sharded store, `None` if its custom format has no extension.
+    pub(crate) shard_extension: Option<String>,
+    pub(crate) legacy: Option<LegacyFormat>,
+    /// Shards changed since the last save, `None` if all of them have to be written.
+    pub(crate) dirty_shards: Arc<Mutex<Option<HashSet<String>>>>,
+    /// Times of the last change of keys in milliseconds since the Unix epoch.
+    updated_at: HashMap<String, u64>,
+    /// Values loaded lazily that haven't been moved into `cache` yet. A key is never in both.
+    raw: HashMap<String, LazyValue>,
+    reader: Option<StoreReader>,
+    /// Called with the contents of the store after every change, see [`Self::bind`].
+    pub(crate) bindings: Vec<BindFn>,
+    watchers: Vec<Watcher>,
+    next_watch_id: u64,
+    /// Expiration times of keys in milliseconds since the Unix epoch.
+    expirations: HashMap<String, u64>,
+    pub(crate) leases: HashMap<String, Lease>,
+    /// Set when the store was built read-only or another instance of the app owns the store file.
+    pub(crate) read_only: bool,
+    /// Set when the store was built with [`StoreBuilder::frontend_owned`].
+    pub(crate) frontend_owned: bool,
+    /// The holder of the writer token, if the store is in single-writer mode.
+    pub(crate) writer: Option<Writer>,
+    /// Set while the store is the source of a replication rule, see [`StoreCollection::replicate`](crate::StoreCollection::replicate).
+    pub(crate) replicating: bool,
+    /// Changes recorded for replication that haven't been applied to the targets yet.
+    pub(crate) replication_outbox: Vec<(String, Option<JsonValue>)>,
+    symlink_policy: SymlinkPolicy,
+    number_mode: NumberMode,
+    error_policy: ErrorPolicy,
+    pub(crate) clock: Arc<dyn Clock>,
+    /// The extension the store's path must end in, see [`ExtensionPolicy::Enforce`].
+    enforced_extension: Option<String>,
+    /// Deserializers registered with [`StoreBuilder::detect_format`].
+    formats: Vec<(FileFormat, DeserializeFn)>,
+    /// Renamed keys, mapped to their new name.
+    aliases: HashMap<String, String>,
+    /// Indexes registered with [`StoreBuilder::index`], by name.
+    pub(crate) indexes: HashMap<String, Index>,
+    /// Set when the store was built with [`StoreBuilder::full_text_search`].
+    #[cfg(feature = "search")]
+    pub(crate) search: Option<SearchIndex>,
+}
+
+impl<R: Runtime> Store<R> {
+    /// Returns the absolute path of the store file.
+    pub(crate) fn store_path(&self) -> PathBuf {
+        self.host.resolve_store_path(&self.path)
+    }
+
+    /// Returns a store with the same configuration and file but only its defaults, sharing none of the save state,
+    /// so the store file can be loaded into it without affecting this store.
+    ///
+    /// The copy doesn't emit events, save on changes or call the watchers and bindings of this store.
+    pub(crate) fn detached(&self) -> Self {
+        let mut store = self.clone();
+        store.cache = self.defaults.clone().unwrap_or_default();
+        store.raw = Default::default();
+        store.updated_at = Default::default();
+        store.expirations = Default::default();
+        store.leases = Default::default();
+        store.history = VecDeque::new();
+        store.replication_outbox = Vec::new();
+        store.silent = true;
+        store.save_on_change = false;
+        store.save_queue = None;
+        store.reader = None;
+        store.bindings = Vec::new();
+        store.watchers = Vec::new();
+        store.unsaved = Default::default();
+        store.revision = 0;
+        store.written = Default::default();
+        store.status = Default::default();
+        store.log_len = Default::default();
+        store.dirty_shards = Default::default();
+        store
+    }
+
+    /// Gives a store returned by [`Self::detached`] the events, saving, watchers, bindings, leases and history
+    /// of `original`, e.g. to replace `original` with it, and publishes its contents to them.
+    pub(crate) fn attach(&mut self, original: &Self) {
+        self.silent = original.silent;
+        self.save_on_change = original.save_on_change;
+        self.save_queue = original.save_queue.clone();
+        self.reader = original.reader.clone();
+        self.bindings = original.bindings.clone();
+        self.watchers = original.watchers.clone();
+        self.next_watch_id = original.next_watch_id;
+        self.leases = original.leases.clone();
+        self.history = original.history.clone();
+        self.contents_changed();
+    }
+
+    /// Returns `true` if the store rejects modifications, because it was built with [`StoreBuilder::read_only`]
+    /// or another instance of the app owns it.
+    ///
+    /// See [`Builder::read_only_secondary_instances`](crate::Builder::read_only_secondary_instances).
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Applies the store's [`SymlinkPolicy`] to its path, returning `false` if the file must not be read.
+    fn check_symlink(&self, store_path: &Path, writing: bool) -> Result<bool, Error> {
+        if self.symlink_policy == SymlinkPolicy::Follow {
+            return Ok(true);
+        }
+        let is_link = symlink_metadata(store_path)
+            .map_or(false, |metadata| metadata.file_type().is_symlink());
+        if !is_link {
+            return Ok(true);
+        }
+        match self.symlink_policy {
+            SymlinkPolicy::Refuse => Err(Error::Symlink(self.path.clone())),
+            SymlinkPolicy::Replace => {
+                if writing {
+                    // links to directories have to be removed as directories on Windows
+                    remove_file(store_path).or_else(|_| remove_dir(store_path))?;
+                }
+                Ok(false)
+            }
+            SymlinkPolicy::Follow => Ok(true),
+        }
+    }
+
+    /// Rejects sharded stores built with options that shard files don't support.
+    fn check_sharding(&self) -> Result<(), Error> {
+        if self.shard_separator.is_none() {
+            return Ok(());
+        }
+        #[cfg(feature = "signing")]
+        if self.signing_key.is_some() {
+            return Err(Error::Sharding(
+                self.path.clone(),
+                "shard files can't be signed".into(),
+            ));
+        }
+        if self.shard_extension.is_none() {
+            return Err(Error::Sharding(
+                self.path.clone(),
+                "stores with a custom format need an extension for their shard files".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_extension(&self) -> Result<(), Error> {
+        match &self.enforced_extension {
+            Some(expected) if self.path.extension() != Some(OsStr::new(expected)) => {
+                Err(Error::ExtensionMismatch {
+                    path: self.path.clone(),
+                    expected: expected.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_writable(&self) -> Result<(), Error> {
+        if self.read_only {
+            return Err(Error::ReadOnly(self.path.clone()));
+        }
+        Ok(())
+    }
+
+    /// Returns the group set with [`StoreBuilder::group`].
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    /// Returns the strategy set with [`StoreBuilder::save_strategy`].
+    pub fn save_strategy(&self) -> SaveStrategy {
+        self.save_strategy
+    }
+
+    /// Returns when the store was last loaded and saved, and whether the last save succeeded.
+    pub fn persistence_status(&self) -> PersistenceStatus {
+        self.status.lock().expect("mutex poisoned").clone()
+    }
+
+    /// Returns `true` if the store was modified since it was last saved.
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.unsaved.lock().expect("mutex poisoned").is_some()
+    }
+
+    /// Saves the store if it was modified since it was last saved, returning `true` if it was saved.
+    ///
+    /// Unlike [`Self::save`], an unmodified store isn't serialized or written at all.
+    pub fn save_if_dirty(&self) -> Result<bool, Error> {
+        if !self.has_unsaved_changes() {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Saves the store after a modification if it was built with [`StoreBuilder::save_on_change`].
+    ///
+    /// Stores of the plugin's collection are handed to its save queue, so the collection isn't locked while
+    /// they are written.
+    fn save_changed(&self) -> Result<(), Error> {
+        if !self.save_on_change || self.read_only || !self.has_unsaved_changes() {
+            return Ok(());
+        }
+        match &self.save_queue {
+            // saved right away once the queue was closed because the app exits
+            Some(save_queue) if save_queue.is_open() => {
+                self.check_save()?;
+                save_queue.push(&self.path);
+            }
+            _ => self.save()?,
+        }
+        Ok(())
+    }
+
+    /// Saves the store if its [`SaveStrategy`] calls for it, returning `true` if it was saved.
+    ///
+    /// Stores managed by the plugin are checked after every command and periodically in the background.
+    pub fn save_if_due(&self) -> Result<bool, Error> {
+        if self.read_only {
+            return Ok(false);
+        }
+        let Some(unsaved) = *self.unsaved.lock().expect("mutex poisoned") else {
+            return Ok(false);
+        };
+        let due = match self.save_strategy {
+            SaveStrategy::Immediate => true,
+            SaveStrategy::Debounced(delay) => unsaved.last.elapsed() >= delay,
+            SaveStrategy::Interval(interval) => unsaved.since.elapsed() >= interval,
+            SaveStrategy::ExplicitOnly | SaveStrategy::OnExitOnly => false,
+        };
+        if due {
+            self.save()?;
+        }
+        Ok(due)
+    }
+
+    /// Update the store from the on-disk state
+    pub fn load(&mut self) -> Result<(), Error> {
+        self.load_from_disk(None)
+    }
+
+    /// Updates the store from the on-disk state, parsing only the values of `keys` right away.
+    ///
+    /// The other values are kept as raw JSON until they are first accessed, like with [`StoreBuilder::lazy_load`],
+    /// so apps with one large store can boot fast by loading just what the first screen needs. This is best-effort:
+    /// stores saved in a format other than JSON are loaded completely.
+    pub fn load_keys(&mut self, keys: &[impl AsRef<str>]) -> Result<(), Error> {
+        let keys = keys.iter().map(AsRef::as_ref).collect();
+        self.load_from_disk(Some(&keys))
+    }
+
+    fn load_from_disk(&mut self, eager_keys: Option<&HashSet<&str>>) -> Result<(), Error> {
+        self.check_extension()?;
+        self.check_sharding()?;
+        let meta = if let Some(values) = self.read_legacy_if_newer()? {
+            self.merge_loaded(values);
+            None
+        } else if !self.check_symlink(&self.store_path(), false)? {
+            None
+        } else if let Some(separator) = self.shard_separator {
+            self.load_shards(separator)?;
+            None
+        } else {
+            match read(self.store_path()) {
+                Ok(bytes) => {
+                    #[cfg(feature = "signing")]
+                    self.verify_signature(&self.store_path(), &bytes)?;
+                    self.load_bytes(&bytes, eager_keys)?
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    // a fresh store holding only its defaults still has to be written
+                    if !self.cache.is_empty() {
+                        self.mark_dirty();
+                    }
+                    // the journal may hold changes made before the store was first saved
+                    if !self.journal {
+                        return Err(err.into());
+                    }
+                    None
+                }
+                Err(err) => return Err(err.into()),
+            }
+        };
+        self.apply_loaded_meta(meta)?;
+        if self.journal {
+            self.replay_journal()?;
+        }
+        self.normalize_loaded_numbers();
+        self.rebuild_indexes();
+        self.contents_changed();
+        self.status.lock().expect("mutex poisoned").loaded_at = Some(self.clock.now_millis());
+
+        Ok(())
+    }
+
+    /// Applies the metadata of a store file that was just merged into the store.
+    pub(crate) fn apply_loaded_meta(&mut self, meta: Option<EnvelopeMeta>) -> Result<(), Error> {
+        for (key, key_meta) in meta.map(|meta| meta.keys).unwrap_or_default() {
+            if let Some(updated_at) = key_meta.updated_at {
+                self.updated_at.insert(key.clone(), updated_at);
+            }
+            if let Some(expires_at) = key_meta.expires_at {
+                self.expirations.insert(key, expires_at);
+            }
+        }
+        if let Some(meta) = self.take_loaded(META_KEY) {
+            self.expirations
+                .extend(StoreMeta::deserialize(meta)?.expirations);
+        }
+        if let Some(expirations) = self.take_loaded(EXPIRATIONS_KEY) {
+            self.expirations
+                .extend(HashMap::<String, u64>::deserialize(expirations)?);
+            // the expirations are moved to the metadata on the next save
+            self.mark_dirty();
+        }
+        #[cfg(feature = "encryption")]
+        self.decrypt_loaded()?;
+        #[cfg(feature = "compression")]
+        self.decompress_loaded();
+        self.migrate_aliases();
+        Ok(())
+    }
+
+    /// Decrypts the values of encrypted keys that were just loaded.
+    #[cfg(feature = "encryption")]
+    fn decrypt_loaded(&mut self) -> Result<(), Error> {
+        let Some(encryption) = self.encryption.clone() else {
+            return Ok(());
+        };
+        let keys: Vec<String> = self
+            .keys()
+            .filter(|key| encryption.applies(key))
+            .cloned()
+            .collect();
+        for key in keys {
+            if let Some(value) = self.take_loaded(&key) {
+                let value = encryption.decrypt(&key, value)?;
+                self.cache.insert(key, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Decompresses the values that were just loaded, see [`StoreBuilder::compress_values_above`].
+    ///
+    /// Values that fail to decompress are kept as they are, so one damaged value doesn't fail the whole load.
+    #[cfg(feature = "compression")]
+    fn decompress_loaded(&mut self) {
+        if self.compress_above.is_none() {
+            return;
+        }
+        let keys: Vec<String> = self
+            .cache
+            .iter()
+            .filter(|(_, value)| is_compressed(value))
+            .map(|(key, _)| key.clone())
+            .chain(
+                self.raw
+                    .iter()
+                    .filter(|(_, value)| may_be_compressed(value.raw.get()))
+                    .map(|(key, _)| key.clone()),
+            )
+            .collect();
+        for key in keys {
+            if let Some(value) = self.take_loaded(&key) {
+                let value = decompress(value.clone()).unwrap_or_else(|err| {
+                    warn!(
+                        "Failed to decompress the value of key {:?} in store {:?}: {}",
+                        key, self.path, err
+                    );
+                    value
+                });
+                self.cache.insert(key, value);
+            }
+        }
+    }
+
+    /// Applies [`NumberMode::StringifyUnsafe`] to the values that were just loaded.
+    ///
+    /// Values kept as raw JSON by [`StoreBuilder::lazy_load`] are only parsed if they may hold an unsafe integer.
+    fn normalize_loaded_numbers(&mut self) {
+        if self.number_mode != NumberMode::StringifyUnsafe {
+            return;
+        }
+        let mut changed: Vec<String> = self
+            .cache
+            .iter_mut()
+            .filter_map(|(key, value)| stringify_unsafe(value).then(|| key.clone()))
+            .collect();
+        let keys: Vec<String> = self
+            .raw
+            .iter()
+            .filter(|(_, value)| may_hold_unsafe_integer(value.raw.get()))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            if let Some(mut value) = self.take_loaded(&key) {
+                if stringify_unsafe(&mut value) {
+                    changed.push(key.clone());
+                }
+                self.cache.insert(key, value);
+            }
+        }
+        // the store file still holds the numbers
+        if !changed.is_empty() {
+            self.mark_dirty();
+        }
+        if let Some(separator) = self.shard_separator {
+            for key in &changed {
+                self.shard_changed(shard_of(key, separator));
+            }
+        }
+    }
+
+    /// Moves the values of renamed keys that were just loaded to their new name.
+    fn migrate_aliases(&mut self) {
+        for (old_key, new_key) in self.aliases.clone() {
+            let Some(value) = self.take_loaded(&old_key) else {
+                continue;
+            };
+            let expires_at = self.expirations.remove(&old_key);
+            let default = self
+                .defaults
+                .as_ref()
+                .and_then(|defaults| defaults.get(&new_key));
+            if self
+                .get(&new_key)
+                .map_or(true, |current| Some(current) == default)
+            {
+                self.raw.remove(&new_key);
+                self.cache.insert(new_key.clone(), value);
+                if let Some(expires_at) = expires_at {
+                    self.expirations.insert(new_key, expires_at);
+                }
+            }
+            // the old key is dropped from the store file on the next save
+            self.mark_dirty();
+            if let Some(separator) = self.shard_separator {
+                self.shard_changed(shard_of(&old_key, separator));
+                self.shard_changed(shard_of(&new_key, separator));
+            }
+        }
+    }
+
+    /// Returns the key `key` was renamed to with [`StoreBuilder::alias`], or `key` itself.
+    fn resolve_key<'a>(&'a self, key: &'a str) -> &'a str {
+        self.aliases.get(key).map_or(key, String::as_str)
+    }
+
+    /// Applies the changes journaled since the store file was last saved.
+    fn replay_journal(&mut self) -> Result<(), Error> {
+        let changes = self.read_change_log()?;
+        if !changes.is_empty() {
+            // replayed changes aren't in the store file yet
+            self.mark_dirty();
+        }
+        for change in changes {
+            let key = match change {
+                Change::Set { key, value } => {
+                    #[cfg(feature = "encryption")]
+                    let value = match &self.encryption {
+                        Some(encryption) if encryption.applies(&key) => {
+                            encryption.decrypt(&key, value)?
+                        }
+                        _ => value,
+                    };
+                    self.raw.remove(&key);
+                    self.cache.insert(key.clone(), value);
+                    key
+                }
+                Change::Remove { key } => {
+                    self.raw.remove(&key);
+                    self.cache.remove(&key);
+                    self.expirations.remove(&key);
+                    key
+                }
+            };
+            if let Some(separator) = self.shard_separator {
+                self.shard_changed(shard_of(&key, separator));
+            }
+        }
+        Ok(())
+    }
+
+    /// Journals changes before they are applied if the store was built with [`StoreBuilder::journal`].
+    fn write_ahead<'a>(
+        &self,
+        changes: impl IntoIterator<Item = (&'a str, Option<&'a JsonValue>)>,
+    ) -> Result<(), Error> {
+        if self.journal {
+            self.append_changes(changes, true)?;
+        }
+        Ok(())
+    }
+
+    /// Merges the contents of a store file into the store, returning its metadata if it is an envelope.
+    pub(crate) fn load_bytes(
+        &mut self,
+        bytes: &[u8],
+        eager_keys: Option<&HashSet<&str>>,
+    ) -> Result<Option<EnvelopeMeta>, Error> {
+        if let Some(deserialize) = self.detected_deserializer(bytes) {
+            let values = deserialize(bytes).map_err(Error::Deserialize)?;
+            let (values, meta) = envelope::unwrap(values)?;
+            self.merge_loaded(values);
+            return Ok(meta);
+        }
+
+        let raw: Option<HashMap<String, Box<RawValue>>> = if self.lazy_load {
+            Some(serde_json::from_slice(bytes).map_err(|err| Error::Deserialize(err.into()))?)
+        } else if eager_keys.is_some() {
+            serde_json::from_slice(bytes).ok()
+        } else {
+            None
+        };
+
+        let meta = if let Some(raw) = raw {
+            let (raw, meta) = envelope::unwrap_raw(raw)?;
+            for (key, raw) in raw {
+                if eager_keys.map_or(false, |eager_keys| eager_keys.contains(key.as_str())) {
+                    let value = serde_json::from_str(raw.get())?;
+                    self.raw.remove(&key);
+                    self.cache.insert(key, value);
+                } else {
+                    self.cache.remove(&key);
+                    self.raw.insert(
+                        key,
+                        LazyValue {
+                            raw,
+                            parsed: OnceCell::new(),
+                        },
+                    );
+                }
+            }
+            meta
+        } else {
+            let values = (self.deserialize)(bytes).map_err(Error::Deserialize)?;
+            let (values, meta) = envelope::unwrap(values)?;
+            self.merge_loaded(values);
+            meta
+        };
+
+        Ok(meta)
+    }
+
+    /// Deserializes a file of the store, detecting its format like when loading.
+    #[cfg(feature = "encryption")]
+    pub(crate) fn deserialize_file(
+        &self,
+        bytes: &[u8],
+    ) -> Result<HashMap<String, JsonValue>, Error> {
+        let deserialize = self
+            .detected_deserializer(bytes)
+            .unwrap_or(self.deserialize);
+        deserialize(bytes).map_err(Error::Deserialize)
+    }
+
+    /// Returns the deserializer registered with [`StoreBuilder::detect_format`] for the format of `bytes`, if any.
+    fn detected_deserializer(&self, bytes: &[u8]) -> Option<DeserializeFn> {
+        if self.formats.is_empty() {
+            return None;
+        }
+        let format = FileFormat::detect(bytes)?;
+        self.formats
+            .iter()
+            .find(|(registered, _)| *registered == format)
+            .map(|(_, deserialize)| *deserialize)
+    }
+
+    /// Merges deserialized values into the store, replacing values loaded lazily.
+    pub(crate) fn merge_loaded(&mut self, values: HashMap<String, JsonValue>) {
+        for (key, value) in values {
+            self.raw.remove(&key);
+            self.cache.insert(key, value);
+        }
+    }
+
+    /// Returns a lock-free read handle that follows the contents of this store.
+    pub fn reader(&mut self) -> StoreReader {
+        if self.reader.is_none() {
+            self.reader = Some(StoreReader::new(self.contents().into_owned()));
+        }
+        self.reader.clone().expect("reader was just created")
+    }
+
+    /// Runs `f` without emitting change events to the frontend.
+    ///
+    /// Useful for bulk operations like the initial population of a store, when windows are about to fetch
+    /// the full state anyway. Callbacks registered with [`Self::watch`] are still called.
+    pub fn with_events_suppressed<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let silent = std::mem::replace(&mut self.silent, true);
+        let result = f(self);
+        self.silent = silent;
+        result
+    }
+
+    /// Emits the change event for `key`, in the legacy shape if the store was built with
+    /// [`StoreBuilder::legacy_change_payloads`].
+    fn emit_change(&self, key: &str, value: &JsonValue) -> Result<(), Error> {
+        if self.is_quiet(key) {
+            return Ok(());
+        }
+        if self.legacy_change_payloads {
+            return self.emit(
+                &self.change_event,
+                LegacyChangePayload {
+                    path: &self.path,
+                    key,
+                    value,
+                },
+            );
+        }
+        self.emit(
+            &self.change_event,
+            ChangePayload {
+                schema: PAYLOAD_SCHEMA,
+                path: Cow::Borrowed(&self.path),
+                key: Cow::Borrowed(key),
+                value: Cow::Borrowed(value),
+                window: self.origin.as_deref().map(Cow::Borrowed),
+                txn_id: self.txn_id.as_deref().map(Cow::Borrowed),
+            },
+        )
+    }
+
+    /// Returns `true` if changes of `key` aren't emitted, see [`StoreBuilder::quiet_keys`].
+    fn is_quiet(&self, key: &str) -> bool {
+        self.quiet_keys
+            .as_ref()
+            .map_or(false, |filter| filter.matches(key))
+    }
+
+    pub(crate) fn emit<S: Serialize>(&self, event: &str, payload: S) -> Result<(), Error> {
+        if !self.silent {
+            self.host
+                .emit_event(event, serde_json::to_value(payload)?)?;
+        }
+        Ok(())
+    }
+
+    /// Handles the failure of a background `operation` according to the store's [`ErrorPolicy`].
+    ///
+    /// `operation` completes the sentence "Failed to {operation} store", e.g. `"save"`.
+    pub(crate) fn report_error(&self, operation: &str, err: Error) {
+        match self.error_policy {
+            ErrorPolicy::Panic => panic!("Failed to {} store {:?}: {}", operation, self.path, err),
+            ErrorPolicy::LogAndContinue => {
+                warn!("Failed to {} store {:?}: {}", operation, self.path, err)
+            }
+            ErrorPolicy::PropagateToFrontend => self.emit_error(operation, &err),
+        }
+    }
+
+    /// Logs `err` and emits it as a `store://error` event regardless of the store's [`ErrorPolicy`].
+    ///
+    /// Used for failures of work queued by the frontend, which only learns about them through the event.
+    pub(crate) fn emit_error(&self, operation: &str, err: &Error) {
+        emit_error(&*self.host, &self.path, operation, err);
+    }
+
+    /// Runs `f` with its mutations attributed to the window labeled `window`.
+    ///
+    /// The label and `txn_id` are included in the change events emitted by `f` and the window is recorded
+    /// in the store's history, so frontends can recognize the events caused by their own writes.
+    pub fn with_origin<T>(
+        &mut self,
+        window: impl Into<String>,
+        txn_id: Option<String>,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let origin = self.origin.replace(window.into());
+        let txn_id = std::mem::replace(&mut self.txn_id, txn_id);
+        let result = f(self);
+        self.origin = origin;
+        self.txn_id = txn_id;
+        result
+    }
+
+    /// Returns the recorded mutations, oldest first, optionally only those of `key`.
+    ///
+    /// Empty unless enabled with [`StoreBuilder::history`].
+    pub fn history(&self, key: Option<&str>) -> Vec<HistoryEntry> {
+        self.history
+            .iter()
+            .filter(|entry| key.map_or(true, |key| entry.key == key))
+            .cloned()
+            .collect()
+    }
+
+    /// Registers a callback that runs whenever the value stored under `key` changes.
+    ///
+    /// The callback receives `None` when the key is removed. It runs while the store is borrowed,
+    /// so it must not access the store collection itself.
+    pub fn watch<F: Fn(Option<&JsonValue>) + Send + Sync + 'static>(
+        &mut self,
+        key: impl Into<String>,
+        callback: F,
+    ) -> WatchId {
+        let id = WatchId(self.next_watch_id);
+        self.next_watch_id += 1;
+        self.watchers.push(Watcher {
+            id,
+            key: key.into(),
+            callback: Arc::new(callback),
+        });
+        id
+    }
+
+    /// Removes a callback registered with [`Self::watch`], returning `true` if it existed.
+    pub fn unwatch(&mut self, id: WatchId) -> bool {
+        let len = self.watchers.len();
+        self.watchers.retain(|watcher| watcher.id != id);
+        self.watchers.len() != len
+    }
+
+    /// Records that the store holds changes that aren't in the store file yet.
+    fn mark_dirty(&mut self) {
+        self.revision += 1;
+        let now = Instant::now();
+        let mut unsaved = self.unsaved.lock().expect("mutex poisoned");
+        let unsaved = unsaved.get_or_insert(UnsavedChanges {
+            since: now,
+            last: now,
+            revision: self.revision,
+        });
+        unsaved.last = now;
+        unsaved.revision = self.revision;
+    }
+
+    /// Must be called for every key whose value changed, with `None` if it was removed.
+    fn key_changed(&mut self, key: &str, value: Option<&JsonValue>) {
+        for index in self.indexes.values_mut() {
+            index.update(key, value);
+        }
+        #[cfg(feature = "search")]
+        if let Some(search) = &mut self.search {
+            search.update(key, value);
+        }
+        self.mark_dirty();
+        if let Some(separator) = self.shard_separator {
+            self.shard_changed(shard_of(key, separator));
+        }
+        match value {
+            Some(_) => {
+                self.updated_at
+                    .insert(key.to_string(), self.clock.now_millis());
+            }
+            None => {
+                self.updated_at.remove(key);
+            }
+        }
+        if self.history_capacity > 0 {
+            if self.history.len() == self.history_capacity {
+                self.history.pop_front();
+            }
+            self.history.push_back(HistoryEntry {
+                key: key.to_string(),
+                value: value.cloned(),
+                timestamp: self.clock.now_millis(),
+                window: self.origin.clone(),
+            });
+        }
+        // journaled changes were logged before they were applied
+        if self.change_log && !self.journal {
+            if let Err(err) = self.append_changes([(key, value)], false) {
+                self.report_error("append to the change log of", err);
+            }
+        }
+        if self.compaction_due() {
+            if let Err(err) = self.compact() {
+                self.report_error("compact", err);
+            }
+        }
+        if self.replicating {
+            self.replication_outbox
+                .push((key.to_string(), value.cloned()));
+        }
+        self.notify_watchers(key, value);
+    }
+
+    fn notify_watchers(&self, key: &str, value: Option<&JsonValue>) {
+        for watcher in self.watchers.iter().filter(|watcher| watcher.key == key) {
+            (watcher.callback)(value);
+        }
+    }
+
+    /// Must be called after every modification of the store's contents.
+    pub(crate) fn contents_changed(&mut self) {
+        if self.reader.is_none() && self.bindings.is_empty() {
+            return;
+        }
+        let contents = self.contents();
+        for binding in &self.bindings {
+            binding(&contents);
+        }
+        if let Some(reader) = &self.reader {
+            reader.publish(contents.into_owned());
+        }
+    }
+
+    /// Returns all key-value pairs of the store, parsing lazily loaded values if needed.
+    pub(crate) fn contents(&self) -> Cow<'_, HashMap<String, JsonValue>> {
+        if self.raw.is_empty() {
+            Cow::Borrowed(&self.cache)
+        } else {
+            Cow::Owned(
+                self.entries()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            )
+        }
+    }
+
+    /// Saves the store to disk
+    ///
+    /// If the disk is full, [`Error::DiskFull`] is returned and a `store://disk-full` event with the store's
+    /// path is emitted, so the app can ask the user to free up space. The store keeps its unsaved changes.
+    pub fn save(&self) -> Result<(), Error> {
+        self.check_save()?;
+        let started = Instant::now();
+        let result = self.write_to_disk();
+        self.finish_save(started, result)
+    }
+
+    /// Runs the checks of [`Self::save`] that come before writing, including the `before_save` hook.
+    pub(crate) fn check_save(&self) -> Result<(), Error> {
+        self.check_writable()?;
+        if let Some(before_save) = &self.before_save {
+            if !before_save(self) {
+                return Err(Error::SaveCancelled(self.path.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the `result` of writing the store in a save that `started` writing then, see [`Self::save`].
+    pub(crate) fn finish_save(
+        &self,
+        started: Instant,
+        result: Result<(), Error>,
+    ) -> Result<(), Error> {
+        let result = result.map_err(|err| match err {
+            Error::Io(err) if is_disk_full(&err) => Error::DiskFull(self.path.clone()),
+            err => err,
+        });
+        if let Err(Error::DiskFull(path)) = &result {
+            // emitted even with events suppressed, the app should prompt the user to free up space
+            let emitted = serde_json::to_value(DiskFullPayload {
+                schema: PAYLOAD_SCHEMA,
+                path,
+            })
+            .map_err(Error::from)
+            .and_then(|payload| self.host.emit_event(DISK_FULL_EVENT, payload));
+            if let Err(err) = emitted {
+                warn!(
+                    "Failed to emit disk full event for store {:?}: {}",
+                    path, err
+                );
+            }
+        }
+
+        {
+            let mut status = self.status.lock().expect("mutex poisoned");
+            let now = self.clock.now_millis();
+            status.save_attempted_at = Some(now);
+            status.save_duration_ms = Some(started.elapsed().as_millis() as u64);
+            match &result {
+                Ok(()) => {
+                    status.saved_at = Some(now);
+                    status.save_error = None;
+                }
+                Err(err) => status.save_error = Some(err.to_string()),
+            }
+        }
+
+        if let Some(after_save) = &self.after_save {
+            after_save(self, &result);
+        }
+
+        result
+    }
+
+    /// Removes a key that was just loaded from the file, whether it was parsed or not.
+    fn take_loaded(&mut self, key: &str) -> Option<JsonValue> {
+        self.cache
+            .remove(key)
+            .or_else(|| self.raw.remove(key).map(|value| value.get().clone()))
+    }
+
+    /// Whether the store can be written by [`PreparedSave::write`] without access to the store.
+    ///
+    /// The change log, the changed shards and the legacy copy are tracked by the store itself.
+    pub(crate) fn is_detachable(&self) -> bool {
+        !self.change_log && !self.journal && self.shard_separator.is_none() && self.legacy.is_none()
+    }
+
+    /// Serializes the store so it can be written without holding it, see [`Self::is_detachable`].
+    pub(crate) fn prepare_save(&self) -> Result<PreparedSave, Error> {
+        let store_path = self.store_path();
+        let contents = self.contents_to_write(&store_path)?;
+        let bytes = (self.serialize)(&contents).map_err(Error::Serialize)?;
+        Ok(PreparedSave {
+            path: self.path.clone(),
+            host: self.host.clone(),
+            signature: self.signature(&bytes),
+            store_path,
+            bytes,
+            revision: self.revision,
+            written: self.written.clone(),
+            unsaved: self.unsaved.clone(),
+        })
+    }
+
+    /// Returns the contents of the store as they are written to the file at `store_path`.
+    fn contents_to_write(
+        &self,
+        store_path: &Path,
+    ) -> Result<Cow<'_, HashMap<String, JsonValue>>, Error> {
+        self.check_extension()?;
+        self.check_sharding()?;
+        create_dir_all(store_path.parent().expect("invalid store path"))?;
+        self.check_symlink(store_path, true)?;
+
+        let mut contents = self.contents();
+        if self.prune_on_save && contents.values().any(is_prunable) {
+            contents.to_mut().retain(|_, value| !is_prunable(value));
+        }
+        #[cfg(feature = "compression")]
+        if let Some(threshold) = self.compress_above {
+            for value in contents.to_mut().values_mut() {
+                if let Some(compressed) = compress(value, threshold)? {
+                    *value = compressed;
+                }
+            }
+        }
+        #[cfg(feature = "encryption")]
+        if let Some(encryption) = &self.encryption {
+            for (key, value) in contents.to_mut().iter_mut() {
+                if encryption.applies(key) {
+                    *value = encryption.encrypt(key, value)?;
+                }
+            }
+        }
+        // written first, so the store file is the newer one
+        self.write_legacy(&contents)?;
+        if self.envelope && self.shard_separator.is_none() {
+            let keys = contents
+                .keys()
+                .map(|key| {
+                    let meta = KeyMeta {
+                        updated_at: self.updated_at.get(key).copied(),
+                        expires_at: self.expirations.get(key).copied(),
+                        // compression and encryption replaced the value, so its type is taken from the store
+                        value_type: self.type_of(key),
+                    };
+                    (key.clone(), meta)
+                })
+                .collect();
+            contents = Cow::Owned(envelope::wrap(&contents, keys)?);
+        } else if !self.expirations.is_empty() {
+            let meta = StoreMeta {
+                expirations: self.expirations.clone(),
+            };
+            contents
+                .to_mut()
+                .insert(META_KEY.to_string(), serde_json::to_value(meta)?);
+        }
+        Ok(contents)
+    }
+
+    pub(crate) fn write_to_disk(&self) -> Result<(), Error> {
+        let mut written = self.written.lock().expect("mutex poisoned");
+        if *written > self.revision {
+            // a background save of a newer revision finished first
+            return Ok(());
+        }
+        let store_path = self.store_path();
+        let contents = self.contents_to_write(&store_path)?;
+
+        if let Some(separator) = self.shard_separator {
+            self.write_shards(separator, &contents)?;
+        } else {
+            let bytes = (self.serialize)(&contents).map_err(Error::Serialize)?;
+            write_signed(&store_path, &bytes, self.signature(&bytes).as_deref())?;
+        }
+
+        if self.change_log || self.journal {
+            self.truncate_change_log()?;
+        }
+        record_written(&mut written, self.revision, &self.unsaved);
+        Ok(())
+    }
+
+    pub fn insert(&mut self, key: String, value: JsonValue) -> Result<(), Error> {
+        self.check_writable()?;
+        self.check_writer()?;
+        let key = match self.aliases.get(&key) {
+            Some(new_key) => new_key.clone(),
+            None => key,
+        };
+        check_key(&key)?;
+        #[cfg(feature = "compression")]
+        check_value(self.compress_above.is_some(), &key, &value)?;
+        let value = self.normalize_numbers(value);
+        let value = match &mut self.coalescing {
+            Some(coalescing) => match coalescing.stage(&key, value) {
+                Some(value) => value,
+                None => return Ok(()),
+            },
+            None => value,
+        };
+        self.commit_value(key, value)
+    }
+
+    /// Writes a value that passed all checks of [`Self::insert`] to the store.
+    pub(crate) fn commit_value(&mut self, key: String, value: JsonValue) -> Result<(), Error> {
+        self.write_ahead([(key.as_str(), Some(&value))])?;
+        self.raw.remove(&key);
+        self.expirations.remove(&key);
+        self.cache.insert(key.clone(), value.clone());
+        self.contents_changed();
+        self.key_changed(&key, Some(&value));
+        self.emit_change(&key, &value)?;
+
+        self.save_changed()
+    }
+
+    /// Inserts multiple key-value pairs at once.
+    ///
+    /// All pairs are inserted before any change event is emitted, so listeners never observe a partial update.
+    pub fn insert_many(&mut self, entries: HashMap<String, JsonValue>) -> Result<(), Error> {
+        self.check_writable()?;
+        self.check_writer()?;
+        let entries: HashMap<String, JsonValue> = entries
+            .into_iter()
+            .map(|(key, value)| (self.resolve_key(&key).to_string(), value))
+            .collect();
+        for (key, value) in &entries {
+            check_key(key)?;
+            #[cfg(feature = "compression")]
+            check_value(self.compress_above.is_some(), key, value)?;
+        }
+        let entries: HashMap<String, JsonValue> = entries
+            .into_iter()
+            .map(|(key, value)| (key, self.normalize_numbers(value)))
+            .collect();
+        self.write_ahead(
+            entries
+                .iter()
+                .map(|(key, value)| (key.as_str(), Some(value))),
+        )?;
+        for key in entries.keys() {
+            self.raw.remove(key);
+            self.expirations.remove(key);
+        }
+        self.cache.extend(
+            entries
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        self.contents_changed();
+
+        for (key, value) in &entries {
+            self.key_changed(key, Some(value));
+        }
+        for (key, value) in &entries {
+            self.emit_change(key, value)?;
+        }
+
+        self.save_changed()
+    }
+
+    /// Applies `changes` at once, deleting the keys whose value is `None`.
+    ///
+    /// Emits a single `store://batch` event carrying a JSON Patch of the changes instead of one change event per key.
+    pub fn apply_batch(
+        &mut self,
+        changes: BTreeMap<String, Option<JsonValue>>,
+    ) -> Result<(), Error> {
+        self.check_writable()?;
+        self.check_writer()?;
+        let changes: BTreeMap<String, Option<JsonValue>> = changes
+            .into_iter()
+            .map(|(key, value)| (self.resolve_key(&key).to_string(), value))
+            .collect();
+        for (key, value) in &changes {
+            check_key(key)?;
+            if let Some(value) = value {
+                #[cfg(feature = "compression")]
+                check_value(self.compress_above.is_some(), key, value)?;
+            }
+        }
+        let changes: BTreeMap<String, Option<JsonValue>> = changes
+            .into_iter()
+            .map(|(key, value)| (key, value.map(|value| self.normalize_numbers(value))))
+            .collect();
+
+        let mut diff = StoreDiff::default();
+        for (key, value) in &changes {
+            match (self.get(key), value) {
+                (Some(old), Some(new)) if old != new => {
+                    diff.changed.insert(
+                        key.clone(),
+                        ValueChange {
+                            old: old.clone(),
+                            new: new.clone(),
+                        },
+                    );
+                }
+                (None, Some(new)) => {
+                    diff.added.insert(key.clone(), new.clone());
+                }
+                (Some(old), None) => {
+                    diff.removed.insert(key.clone(), old.clone());
+                }
+                _ => {}
+            }
+        }
+        if diff.is_empty() {
+            return Ok(());
+        }
+        let mut changes = changes;
+        changes.retain(|key, _| {
+            diff.added.contains_key(key)
+                || diff.changed.contains_key(key)
+                || diff.removed.contains_key(key)
+        });
+
+        self.write_ahead(
+            changes
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_ref())),
+        )?;
+        for (key, value) in &changes {
+            self.raw.remove(key);
+            self.expirations.remove(key);
+            match value {
+                Some(value) => {
+                    self.cache.insert(key.clone(), value.clone());
+                }
+                None => {
+                    self.cache.remove(key);
+                }
+            }
+        }
+        self.contents_changed();
+        for (key, value) in &changes {
+            self.key_changed(key, value.as_ref());
+        }
+
+        diff.added.retain(|key, _| !self.is_quiet(key));
+        diff.removed.retain(|key, _| !self.is_quiet(key));
+        diff.changed.retain(|key, _| !self.is_quiet(key));
+        let patch = diff.to_patch();
+        if !patch.is_empty() {
+            self.emit(
+                BATCH_EVENT,
+                ResetPayload {
+                    schema: PAYLOAD_SCHEMA,
+                    path: &self.path,
+                    patch: &patch,
+                },
+            )?;
+        }
+
+        self.save_changed()
+    }
+
+    /// Inserts a key-value pair that is removed by [`Self::sweep_expired`] once `ttl` has passed.
+    ///
+    /// Inserting the key again without a TTL makes it permanent.
+    pub fn insert_with_ttl(
+        &mut self,
+        key: String,
+        value: JsonValue,
+        ttl: Duration,
+    ) -> Result<(), Error> {
+        let key = self.resolve_key(&key).to_string();
+        let expires_at = self
+            .clock
+            .now_millis()
+            .saturating_add(ttl.as_millis() as u64);
+        // inserting clears the key's expiration, so saving is deferred until the new one is set
+        let save_on_change = std::mem::replace(&mut self.save_on_change, false);
+        let inserted = self.insert(key.clone(), value);
+        self.save_on_change = save_on_change;
+        inserted?;
+        self.expirations.insert(key, expires_at);
+        self.save_changed()
+    }
+
+    /// Registers default values at runtime, inserting those whose key is missing from the store.
+    ///
+    /// The defaults are used by [`Self::reset`] like the ones set with [`StoreBuilder::defaults`].
+    pub fn add_defaults(&mut self, defaults: HashMap<String, JsonValue>) -> Result<(), Error> {
+        // checked before the defaults are registered, so a rejected call has no effect
+        self.check_writable()?;
+        self.check_writer()?;
+        let defaults: HashMap<String, JsonValue> = defaults
+            .into_iter()
+            .map(|(key, value)| (self.resolve_key(&key).to_string(), value))
+            .collect();
+        let missing: HashMap<String, JsonValue> = defaults
+            .iter()
+            .filter(|(key, _)| !self.has(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        self.defaults
+            .get_or_insert_with(HashMap::new)
+            .extend(defaults);
+        if !missing.is_empty() {
+            self.insert_many(missing)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the value of `key` with the result of `f`, which receives the current value.
+    ///
+    /// The store stays borrowed between reading and writing, so unlike a [`Self::get`] followed by
+    /// [`Self::insert`] in separate [`with_store`](crate::with_store) calls no concurrent write can be lost.
+    /// Returns the new value.
+    pub fn update(
+        &mut self,
+        key: impl Into<String>,
+        f: impl FnOnce(Option<&JsonValue>) -> JsonValue,
+    ) -> Result<JsonValue, Error> {
+        let key = key.into();
+        let value = f(self.get(&key));
+        self.insert(key, value.clone())?;
+        Ok(value)
+    }
+
+    /// Inserts `value` only if `key` currently holds `expected`, returning whether it was inserted.
+    ///
+    /// A missing key compares equal to `null`.
+    pub fn compare_and_set(
+        &mut self,
+        key: String,
+        expected: &JsonValue,
+        value: JsonValue,
+    ) -> Result<bool, Error> {
+        if self.get(&key).unwrap_or(&JsonValue::Null) != expected {
+            return Ok(false);
+        }
+        self.insert(key, value)?;
+        Ok(true)
+    }
+
+    /// Removes all expired keys and saves the store once, emitting a change event with `null` for each key
+    /// like [`Self::delete`]. Returns the removed keys.
+    pub fn sweep_expired(&mut self) -> Result<Vec<String>, Error> {
+        if self.read_only {
+            return Ok(Vec::new());
+        }
+        let now = self.clock.now_millis();
+        let expired: Vec<String> = self
+            .expirations
+            .iter()
+            .filter(|(_, expires_at)| **expires_at <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        if let Some(coalescing) = &mut self.coalescing {
+            for key in &expired {
+                coalescing.discard(key);
+            }
+        }
+        // expiry isn't a modification by any context, so it bypasses single-writer mode
+        let writer = self.writer.take();
+        // applied as one batch, so the store is saved once, with the batch event replaced by delete events
+        let result = self.with_events_suppressed(|store| {
+            store.apply_batch(expired.iter().map(|key| (key.clone(), None)).collect())
+        });
+        self.writer = writer;
+        result?;
+        for key in &expired {
+            self.expirations.remove(key);
+            self.emit_change(key, &JsonValue::Null)?;
+        }
+        Ok(expired)
     }
 
     pub fn get(&self, key: impl AsRef<str>) -> Option<&JsonValue> {
-        self.cache.get(key.as_ref())
+        let key = self.resolve_key(key.as_ref());
+        self.cache
+            .get(key)
+            .or_else(|| self.raw.get(key).map(LazyValue::get))
+    }
+
+    /// Returns the value stored under `key`, validated against the store's schema.
+    ///
+    /// Keys without a registered type are returned as-is.
+    pub fn get_checked(&self, key: impl AsRef<str>) -> Result<Option<JsonValue>, Error> {
+        let key = self.resolve_key(key.as_ref());
+        self.get(key)
+            .map(|value| self.check_type(key, value))
+            .transpose()
+    }
+
+    /// Validates `value` against the type registered for `key`, coercing it if enabled.
+    fn check_type(&self, key: &str, value: &JsonValue) -> Result<JsonValue, Error> {
+        let key = self.resolve_key(key);
+        let expected = match self.schema.get(key) {
+            Some(expected) => *expected,
+            None => return Ok(value.clone()),
+        };
+
+        let found = ValueType::from(value);
+        if found == expected {
+            return Ok(value.clone());
+        }
+        if self.coerce_types {
+            if let Some(value) = coerce(value, expected) {
+                return Ok(value);
+            }
+        }
+
+        Err(Error::TypeMismatch {
+            key: key.to_string(),
+            expected,
+            found,
+        })
+    }
+
+    /// Returns when the value of `key` was last changed, in milliseconds since the Unix epoch.
+    ///
+    /// Only known for changes made since the store was loaded, unless it is saved with [`StoreBuilder::envelope`].
+    pub fn updated_at(&self, key: impl AsRef<str>) -> Option<u64> {
+        self.updated_at.get(self.resolve_key(key.as_ref())).copied()
     }
 
     pub fn has(&self, key: impl AsRef<str>) -> bool {
-        self.cache.contains_key(key.as_ref())
+        let key = self.resolve_key(key.as_ref());
+        self.cache.contains_key(key) || self.raw.contains_key(key)
+    }
+
+    /// Returns the JSON type of the value stored under `key`.
+    pub fn type_of(&self, key: impl AsRef<str>) -> ValueType {
+        self.get(key).map_or(ValueType::Missing, Into::into)
     }
 
     pub fn delete(&mut self, key: impl AsRef<str>) -> Result<bool, Error> {
-        let flag = self.cache.remove(key.as_ref()).is_some();
+        self.check_writable()?;
+        self.check_writer()?;
+        let key = self.resolve_key(key.as_ref()).to_string();
+        if let Some(coalescing) = &mut self.coalescing {
+            coalescing.discard(&key);
+        }
+        if self.has(&key) {
+            self.write_ahead([(key.as_str(), None)])?;
+        }
+        self.expirations.remove(&key);
+        let removed = self.cache.remove(&key).is_some();
+        let flag = self.raw.remove(&key).is_some() || removed;
         if flag {
-            self.app.emit_all(
-                "store://change",
-                ChangePayload {
-                    path: &self.path,
-                    key: key.as_ref(),
-                    value: &JsonValue::Null,
-                },
-            )?;
+            self.contents_changed();
+            self.key_changed(&key, None);
+            self.emit_change(&key, &JsonValue::Null)?;
+            self.save_changed()?;
         }
         Ok(flag)
     }
 
     pub fn clear(&mut self) -> Result<(), Error> {
-        let keys: Vec<String> = self.cache.keys().cloned().collect();
+        self.check_writable()?;
+        self.check_writer()?;
+        let keys: Vec<String> = self.keys().cloned().collect();
+        if let Some(coalescing) = &mut self.coalescing {
+            coalescing.discard_all();
+        }
+        self.write_ahead(keys.iter().map(|key| (key.as_str(), None)))?;
         self.cache.clear();
+        self.raw.clear();
+        self.expirations.clear();
+        self.contents_changed();
+        for key in &keys {
+            self.key_changed(key, None);
+        }
         for key in keys {
-            self.app.emit_all(
-                "store://change",
-                ChangePayload {
+            self.emit_change(&key, &JsonValue::Null)?;
+        }
+        self.save_changed()
+    }
+
+    /// Resets the store to its defaults, or clears it if there are none.
+    ///
+    /// Emits a single `store://reset` event carrying a JSON Patch of the changes.
+    pub fn reset(&mut self) -> Result<(), Error> {
+        self.check_writable()?;
+        self.check_writer()?;
+        if let Some(coalescing) = &mut self.coalescing {
+            coalescing.discard_all();
+        }
+        let defaults = self.defaults.clone().unwrap_or_default();
+        let diff = StoreDiff::between(&self.contents(), &defaults);
+        let mut loud = diff.clone();
+        loud.added.retain(|key, _| !self.is_quiet(key));
+        loud.removed.retain(|key, _| !self.is_quiet(key));
+        loud.changed.retain(|key, _| !self.is_quiet(key));
+        let patch = loud.to_patch();
+        self.write_ahead(
+            diff.added
+                .iter()
+                .map(|(key, value)| (key.as_str(), Some(value)))
+                .chain(
+                    diff.changed
+                        .iter()
+                        .map(|(key, change)| (key.as_str(), Some(&change.new))),
+                )
+                .chain(diff.removed.keys().map(|key| (key.as_str(), None))),
+        )?;
+        self.cache = defaults;
+        self.raw.clear();
+        self.expirations.clear();
+        self.contents_changed();
+        for (key, value) in &diff.added {
+            self.key_changed(key, Some(value));
+        }
+        for (key, change) in &diff.changed {
+            self.key_changed(key, Some(&change.new));
+        }
+        for key in diff.removed.keys() {
+            self.key_changed(key, None);
+        }
+
+        if !patch.is_empty() {
+            self.emit(
+                RESET_EVENT,
+                ResetPayload {
+                    schema: PAYLOAD_SCHEMA,
                     path: &self.path,
-                    key: &key,
-                    value: &JsonValue::Null,
+                    patch: &patch,
                 },
             )?;
         }
+
+        self.save_changed()
+    }
+
+    /// Restores the default value of `key`, or removes it if it has none.
+    pub fn reset_key(&mut self, key: impl AsRef<str>) -> Result<(), Error> {
+        let key = key.as_ref();
+        match self
+            .defaults
+            .as_ref()
+            .and_then(|defaults| defaults.get(key))
+        {
+            Some(default) => self.insert(key.to_string(), default.clone()),
+            None => self.delete(key).map(|_| ()),
+        }
+    }
+
+    /// Returns the keys with a default value whose current value differs from it, sorted.
+    pub fn overridden_keys(&self) -> Vec<&String> {
+        let mut keys: Vec<&String> = self
+            .defaults
+            .iter()
+            .flatten()
+            .filter(|(key, default)| self.get(key) != Some(*default))
+            .map(|(key, _)| key)
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Returns a hex-encoded hash of the store's contents.
+    ///
+    /// The hash is computed over a canonical JSON encoding with sorted keys,
+    /// so it only changes when the contents do.
+    pub fn checksum(&self) -> Result<String, Error> {
+        let sorted: BTreeMap<&String, &JsonValue> = self.entries().collect();
+        let bytes = serde_json::to_vec(&sorted)?;
+        Ok(format!("{:016x}", fnv1a(&bytes)))
+    }
+
+    /// Imports `entries` into the store, e.g. to migrate data from the browser's `localStorage`.
+    ///
+    /// If `marker` is given, the import only runs if that key doesn't exist yet and sets it to `true` afterwards,
+    /// so a migration happens exactly once. Returns whether the import ran.
+    ///
+    /// All entries are checked before any is imported, then applied at once like [`Self::apply_batch`].
+    pub fn import_map(
+        &mut self,
+        entries: HashMap<String, JsonValue>,
+        strategy: ConflictStrategy,
+        marker: Option<&str>,
+    ) -> Result<bool, Error> {
+        if let Some(marker) = marker {
+            if self.has(marker) {
+                return Ok(false);
+            }
+        }
+
+        // checked as a whole first, so a rejected import changes nothing
+        self.check_import(&entries, strategy)?;
+        if let Some(marker) = marker {
+            check_key(self.resolve_key(marker))?;
+        }
+
+        let mut changes: BTreeMap<String, Option<JsonValue>> = entries
+            .into_iter()
+            .filter(|(key, _)| strategy != ConflictStrategy::Skip || !self.has(key))
+            .map(|(key, value)| (key, Some(value)))
+            .collect();
+        if let Some(marker) = marker {
+            changes.insert(marker.to_string(), Some(JsonValue::Bool(true)));
+        }
+        self.apply_batch(changes)?;
+
+        Ok(true)
+    }
+
+    /// Checks that [`Self::import_map`] accepts `entries` with `strategy`, without changing the store.
+    pub(crate) fn check_import(
+        &self,
+        entries: &HashMap<String, JsonValue>,
+        strategy: ConflictStrategy,
+    ) -> Result<(), Error> {
+        self.check_writable()?;
+        self.check_writer()?;
+        for (key, value) in entries {
+            let key = self.resolve_key(key);
+            check_key(key)?;
+            #[cfg(feature = "compression")]
+            check_value(self.compress_above.is_some(), key, value)?;
+            if strategy == ConflictStrategy::Fail && self.has(key) {
+                return Err(Error::ImportConflict(key.to_string()));
+            }
+        }
         Ok(())
     }
 
-    pub fn reset(&mut self) -> Result<(), Error> {
-        let has_defaults = self.defaults.is_some();
-
-        if has_defaults {
-            if let Some(defaults) = &self.defaults {
-                for (key, value) in &self.cache {
-                    if defaults.get(key) != Some(value) {
-                        let _ = self.app.emit_all(
-                            "store://change",
-                            ChangePayload {
-                                path: &self.path,
-                                key,
-                                value: defaults.get(key).unwrap_or(&JsonValue::Null),
-                            },
-                        );
-                    }
+    /// Parses and schema-checks an import payload in the store's format without applying it.
+    ///
+    /// Returns the changes [`Self::import_map`] would make with the given `strategy`,
+    /// so they can be confirmed by the user first.
+    pub fn validate_import(
+        &self,
+        bytes: &[u8],
+        strategy: ConflictStrategy,
+    ) -> Result<StoreDiff, Error> {
+        let entries = (self.deserialize)(bytes).map_err(Error::Deserialize)?;
+
+        let current = self.contents();
+        let mut next = current.clone().into_owned();
+        for (key, value) in entries {
+            let value = self.check_type(&key, &value)?;
+            if current.contains_key(&key) {
+                match strategy {
+                    ConflictStrategy::Skip => continue,
+                    ConflictStrategy::Fail => return Err(Error::ImportConflict(key)),
+                    ConflictStrategy::Overwrite => {}
                 }
-                self.cache.clone_from(defaults);
             }
-            Ok(())
-        } else {
-            self.clear()
+            next.insert(key, value);
         }
+
+        Ok(StoreDiff::between(&current, &next))
+    }
+
+    /// Returns a copy of the key-value pairs selected by `filter`, e.g. to share settings without credentials.
+    pub fn export(&self, filter: &KeyFilter) -> HashMap<String, JsonValue> {
+        self.entries()
+            .filter(|(key, _)| filter.matches(key))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    /// Returns the contents of the store as pretty-printed JSON with sorted keys, masking the values of sensitive keys,
+    /// e.g. for users to paste their settings into a support ticket.
+    ///
+    /// Keys are sensitive if they were marked with [`StoreBuilder::sensitive_keys`] or are encrypted with
+    /// [`StoreBuilder::encrypt_keys`]. Their values are replaced with [`REDACTED`].
+    pub fn export_redacted(&self) -> Result<String, Error> {
+        let sensitive = |key: &str| {
+            self.sensitive_keys
+                .as_ref()
+                .map_or(false, |filter| filter.matches(key))
+                || self.is_encrypted(key)
+        };
+        let redacted: BTreeMap<&String, Cow<'_, JsonValue>> = self
+            .entries()
+            .map(|(key, value)| {
+                let value = if sensitive(key) {
+                    Cow::Owned(JsonValue::String(REDACTED.into()))
+                } else {
+                    Cow::Borrowed(value)
+                };
+                (key, value)
+            })
+            .collect();
+        Ok(serde_json::to_string_pretty(&redacted)?)
+    }
+
+    /// Whether the value of `key` is encrypted, see [`StoreBuilder::encrypt_keys`].
+    #[cfg(feature = "encryption")]
+    fn is_encrypted(&self, key: &str) -> bool {
+        self.encryption
+            .as_ref()
+            .map_or(false, |encryption| encryption.applies(key))
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn is_encrypted(&self, _key: &str) -> bool {
+        false
+    }
+
+    /// Returns the keys that were added, removed or changed in `other` compared to this store.
+    pub fn diff(&self, other: &Self) -> StoreDiff {
+        StoreDiff::between(&self.contents(), &other.contents())
     }
 
     pub fn keys(&self) -> impl Iterator<Item = &String> {
-        self.cache.keys()
+        self.cache.keys().chain(self.raw.keys())
+    }
+
+    /// Returns all keys in the store, sorted according to `order`.
+    pub fn keys_sorted(&self, order: KeyOrder) -> Vec<&String> {
+        let mut keys: Vec<&String> = self.keys().collect();
+        match order {
+            KeyOrder::Lexicographic => keys.sort_unstable(),
+            KeyOrder::Numeric => {
+                keys.sort_unstable_by(|a, b| natural_cmp(a, b).then_with(|| a.cmp(b)))
+            }
+            KeyOrder::Reverse => keys.sort_unstable_by(|a, b| b.cmp(a)),
+        }
+        keys
     }
 
     pub fn values(&self) -> impl Iterator<Item = &JsonValue> {
-        self.cache.values()
+        self.entries().map(|(_, value)| value)
     }
 
     pub fn entries(&self) -> impl Iterator<Item = (&String, &JsonValue)> {
-        self.cache.iter()
+        self.cache
+            .iter()
+            .chain(self.raw.iter().map(|(key, value)| (key, value.get())))
     }
 
     pub fn len(&self) -> usize {
-        self.cache.len()
+        self.cache.len() + self.raw.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.cache.is_empty()
+        self.cache.is_empty() && self.raw.is_empty()
     }
 }
 
@@ -314,6 +3548,81 @@ impl<R: Runtime> std::fmt::Debug for Store<R> {
             .field("path", &self.path)
             .field("defaults", &self.defaults)
             .field("cache", &self.cache)
+            .field("raw", &self.raw.keys().collect::<Vec<_>>())
+            .field("schema", &self.schema)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn coerce_converts_losslessly() {
+        assert_eq!(coerce(&json!("42"), ValueType::Number), Some(json!(42)));
+        assert_eq!(coerce(&json!("-7"), ValueType::Number), Some(json!(-7)));
+        assert_eq!(coerce(&json!("0.5"), ValueType::Number), Some(json!(0.5)));
+        assert_eq!(coerce(&json!("true"), ValueType::Bool), Some(json!(true)));
+        assert_eq!(coerce(&json!("false"), ValueType::Bool), Some(json!(false)));
+        assert_eq!(coerce(&json!(42), ValueType::String), Some(json!("42")));
+        assert_eq!(coerce(&json!(true), ValueType::String), Some(json!("true")));
+    }
+
+    #[test]
+    fn coerce_keeps_ambiguous_strings() {
+        for s in ["007", " 42", "42 ", "+1", "", "NaN", "0x10"] {
+            assert_eq!(coerce(&json!(s), ValueType::Number), None, "{s:?}");
+        }
+        // with `arbitrary-precision` numbers keep their original digits
+        #[cfg(not(feature = "arbitrary-precision"))]
+        for s in ["1.50", "1e2"] {
+            assert_eq!(coerce(&json!(s), ValueType::Number), None, "{s:?}");
+        }
+        for s in ["True", "1", "yes", ""] {
+            assert_eq!(coerce(&json!(s), ValueType::Bool), None, "{s:?}");
+        }
+    }
+
+    #[test]
+    fn coerce_ignores_other_types() {
+        assert_eq!(coerce(&json!(null), ValueType::String), None);
+        assert_eq!(coerce(&json!([1]), ValueType::String), None);
+        assert_eq!(coerce(&json!({ "a": 1 }), ValueType::Number), None);
+        assert_eq!(coerce(&json!(1), ValueType::Bool), None);
+        assert_eq!(coerce(&json!("a"), ValueType::Object), None);
+    }
+
+    #[test]
+    fn natural_cmp_orders_numbers_by_value() {
+        assert_eq!(natural_cmp("item2", "item10"), Ordering::Less);
+        assert_eq!(natural_cmp("item10", "item9"), Ordering::Greater);
+        assert_eq!(natural_cmp("v1.10", "v1.9"), Ordering::Greater);
+        // longer than any integer type
+        assert_eq!(
+            natural_cmp("n99999999999999999999", "n100000000000000000000"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn natural_cmp_ignores_leading_zeros() {
+        assert_eq!(natural_cmp("a007", "a7"), Ordering::Equal);
+        assert_eq!(natural_cmp("a007", "a8"), Ordering::Less);
+        assert_eq!(natural_cmp("0", "00"), Ordering::Equal);
+    }
+
+    #[test]
+    fn natural_cmp_compares_text_bytewise() {
+        assert_eq!(natural_cmp("a", "b"), Ordering::Less);
+        assert_eq!(natural_cmp("B", "a"), Ordering::Less);
+        assert_eq!(natural_cmp("x", "x1"), Ordering::Less);
+        assert_eq!(natural_cmp("x1", "xa"), Ordering::Less);
+        assert_eq!(natural_cmp("", ""), Ordering::Equal);
+
+        let mut keys = vec!["a10", "a2", "b1", "a1", "a"];
+        keys.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(keys, ["a", "a1", "a2", "a10", "b1"]);
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Writing a store in its previous on-disk format as well, so apps can roll back an update that changed it.
+
+use crate::{
+    atomic::write_atomic,
+    store::{default_deserialize, default_serialize, DeserializeFn, SerializeFn},
+    Error, JsonValue, Store,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use tauri::Runtime;
+
+/// The previous on-disk format of a store, written alongside the current one by
+/// [`StoreBuilder::legacy_format`](crate::StoreBuilder::legacy_format).
+///
+/// # Examples
+/// ```
+/// use tauri_plugin_store::LegacyFormat;
+///
+/// // keep writing the flat JSON file read by the last release for the next two versions
+/// let legacy = LegacyFormat::new("settings.json".into()).versions(2);
+/// ```
+#[derive(Clone)]
+pub struct LegacyFormat {
+    path: PathBuf,
+    serialize: SerializeFn,
+    deserialize: DeserializeFn,
+    versions: usize,
+}
+
+impl LegacyFormat {
+    /// Creates a legacy format stored at `path`, resolved like store paths, as flat JSON for a single app version.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            serialize: default_serialize,
+            deserialize: default_deserialize,
+            versions: 1,
+        }
+    }
+
+    /// Sets the function the legacy file is serialized with.
+    pub fn serialize(mut self, serialize: SerializeFn) -> Self {
+        self.serialize = serialize;
+        self
+    }
+
+    /// Sets the function the legacy file is deserialized with.
+    pub fn deserialize(mut self, deserialize: DeserializeFn) -> Self {
+        self.deserialize = deserialize;
+        self
+    }
+
+    /// Sets for how many app versions, starting with the first one that saves the store, the legacy file is written.
+    pub fn versions(mut self, versions: usize) -> Self {
+        self.versions = versions;
+        self
+    }
+}
+
+/// The app versions that have written the legacy file so far.
+#[derive(Default, Serialize, Deserialize)]
+struct Transition {
+    versions: Vec<String>,
+}
+
+/// Returns the path of the file recording the versions that wrote the legacy file.
+fn transition_path(legacy_path: &Path) -> PathBuf {
+    let mut path = legacy_path.as_os_str().to_owned();
+    path.push(".transition");
+    path.into()
+}
+
+fn modified(path: &Path) -> io::Result<Option<std::time::SystemTime>> {
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(Some(metadata.modified()?)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+impl<R: Runtime> Store<R> {
+    /// Returns the absolute path of the legacy file, if the store has a legacy format.
+    pub(crate) fn legacy_path(&self) -> Option<PathBuf> {
+        self.legacy
+            .as_ref()
+            .map(|legacy| self.host.resolve_store_path(&legacy.path))
+    }
+
+    /// Reads the legacy file if it was written after the store file, e.g. by an older version of the app
+    /// the user rolled back to.
+    pub(crate) fn read_legacy_if_newer(&self) -> Result<Option<HashMap<String, JsonValue>>, Error> {
+        let Some(legacy) = &self.legacy else {
+            return Ok(None);
+        };
+        let legacy_path = self.host.resolve_store_path(&legacy.path);
+        let Some(legacy_modified) = modified(&legacy_path)? else {
+            return Ok(None);
+        };
+        // both files are written on save, the store file last
+        if modified(&self.store_path())?.map_or(false, |current| current >= legacy_modified) {
+            return Ok(None);
+        }
+        let bytes = fs::read(legacy_path)?;
+        Ok(Some(
+            (legacy.deserialize)(&bytes).map_err(Error::Deserialize)?,
+        ))
+    }
+
+    /// Writes the legacy file if the transition period hasn't ended yet.
+    pub(crate) fn write_legacy(&self, contents: &HashMap<String, JsonValue>) -> Result<(), Error> {
+        let Some(legacy) = &self.legacy else {
+            return Ok(());
+        };
+        let legacy_path = self.host.resolve_store_path(&legacy.path);
+        let transition_path = transition_path(&legacy_path);
+
+        let mut transition: Transition = match fs::read(&transition_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Transition::default(),
+            Err(err) => return Err(err.into()),
+        };
+        let version = self.host.app_version().to_string();
+        if !transition.versions.contains(&version) {
+            if transition.versions.len() >= legacy.versions {
+                return Ok(());
+            }
+            transition.versions.push(version);
+            write_atomic(&transition_path, &serde_json::to_vec(&transition)?)?;
+        }
+
+        let bytes = (legacy.serialize)(contents).map_err(Error::Serialize)?;
+        write_atomic(&legacy_path, &bytes)?;
+        Ok(())
+    }
+}
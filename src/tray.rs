@@ -0,0 +1,154 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Two-way bindings between store keys and system tray menu items.
+
+use crate::{ensure_store, Error, JsonValue, StoreCollection};
+use log::warn;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, Runtime, SystemTrayEvent};
+
+/// The state of a tray menu item that is bound to a key with [`StoreCollection::bind_tray_item`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayBinding {
+    /// Checks the item while the key is `true`. Clicking the item toggles the key.
+    Selected,
+    /// Enables the item while the key is `true`.
+    Enabled,
+    /// Shows the key's value as the item's title, e.g. an unread count. Strings are shown without quotes.
+    Title,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TrayItem {
+    id: String,
+    path: PathBuf,
+    key: String,
+    binding: TrayBinding,
+}
+
+fn apply<R: Runtime>(
+    app: &AppHandle<R>,
+    id: &str,
+    binding: TrayBinding,
+    value: Option<&JsonValue>,
+) {
+    let Some(item) = app.tray_handle().try_get_item(id) else {
+        warn!("Tray menu item {:?} bound to a store key doesn't exist", id);
+        return;
+    };
+    let flag = value.and_then(JsonValue::as_bool).unwrap_or(false);
+    let result = match binding {
+        TrayBinding::Selected => item.set_selected(flag),
+        TrayBinding::Enabled => item.set_enabled(flag),
+        TrayBinding::Title => item.set_title(match value {
+            Some(JsonValue::String(title)) => title.clone(),
+            Some(JsonValue::Null) | None => String::new(),
+            Some(value) => value.to_string(),
+        }),
+    };
+    if let Err(err) = result {
+        warn!("Failed to update tray menu item {:?}: {}", id, err);
+    }
+}
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Keeps the tray menu item `id` in sync with `key` in the store at `path`, see [`TrayBinding`].
+    ///
+    /// The item is updated right away and whenever the key changes. The store stays loaded for the
+    /// lifetime of the app. Tauri only delivers tray events to the app, so forward them to
+    /// [`Self::handle_tray_event`] for clicks to update the store.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use tauri::{CustomMenuItem, Manager, SystemTray, SystemTrayMenu};
+    /// use tauri_plugin_store::{StoreCollection, TrayBinding};
+    ///
+    /// tauri::Builder::default()
+    ///   .plugin(tauri_plugin_store::Builder::default().build())
+    ///   .system_tray(SystemTray::new().with_menu(
+    ///     SystemTrayMenu::new().add_item(CustomMenuItem::new("notifications", "Notifications")),
+    ///   ))
+    ///   .on_system_tray_event(|app, event| {
+    ///     app.state::<StoreCollection<_>>().handle_tray_event(app, &event);
+    ///   })
+    ///   .setup(|app| {
+    ///     app.state::<StoreCollection<_>>().bind_tray_item(
+    ///       &app.handle(),
+    ///       "settings.json".as_ref(),
+    ///       "notifications",
+    ///       "notifications",
+    ///       TrayBinding::Selected,
+    ///     )?;
+    ///     Ok(())
+    ///   });
+    /// ```
+    pub fn bind_tray_item(
+        &self,
+        app: &AppHandle<R>,
+        path: &Path,
+        key: &str,
+        id: &str,
+        binding: TrayBinding,
+    ) -> Result<(), Error> {
+        if app.tray_handle().try_get_item(id).is_none() {
+            return Err(Error::TrayItemNotFound(id.to_string()));
+        }
+        let store_key = {
+            let mut stores = self.stores.lock().expect("mutex poisoned");
+            let store_key = ensure_store(app, self, &mut stores, path)?;
+            let store = stores.get_mut(&store_key).expect("store was loaded above");
+            apply(app, id, binding, store.get(key));
+            let (app, item) = (app.clone(), id.to_string());
+            store.watch(key, move |value| apply(&app, &item, binding, value));
+            store_key
+        };
+        self.registered
+            .lock()
+            .expect("mutex poisoned")
+            .insert(store_key);
+        self.tray_items
+            .lock()
+            .expect("mutex poisoned")
+            .push(TrayItem {
+                id: id.to_string(),
+                path: path.to_path_buf(),
+                key: key.to_string(),
+                binding,
+            });
+        Ok(())
+    }
+
+    /// Toggles the keys bound to a clicked tray menu item with [`TrayBinding::Selected`],
+    /// returning `true` if the event was handled.
+    pub fn handle_tray_event(&self, app: &AppHandle<R>, event: &SystemTrayEvent) -> bool {
+        let SystemTrayEvent::MenuItemClick { id, .. } = event else {
+            return false;
+        };
+        let items: Vec<TrayItem> = self
+            .tray_items
+            .lock()
+            .expect("mutex poisoned")
+            .iter()
+            .filter(|item| &item.id == id && item.binding == TrayBinding::Selected)
+            .cloned()
+            .collect();
+        for item in &items {
+            let result = crate::with_store(app.clone(), app.state(), &item.path, |store| {
+                let checked = store
+                    .get(&item.key)
+                    .and_then(JsonValue::as_bool)
+                    .unwrap_or(false);
+                store.insert(item.key.clone(), JsonValue::Bool(!checked))
+            });
+            if let Err(err) = result {
+                warn!(
+                    "Failed to toggle key {:?} of store {:?} from the tray: {}",
+                    item.key, item.path, err
+                );
+            }
+        }
+        !items.is_empty()
+    }
+}
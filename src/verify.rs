@@ -0,0 +1,121 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Consistency checks of all registered stores, e.g. at startup.
+
+use crate::{Error, StoreCollection, StoreHost, PAYLOAD_SCHEMA, VERIFY_EVENT};
+use log::warn;
+use serde::Serialize;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use tauri::{AppHandle, Runtime};
+
+/// A problem found by [`StoreCollection::verify_all`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreIssue {
+    pub path: PathBuf,
+    /// Why the store file couldn't be loaded, if it couldn't.
+    pub error: Option<String>,
+    /// Where the broken store file was moved to.
+    pub quarantined: Option<PathBuf>,
+    /// The keys whose values don't match the store's schema, sorted.
+    pub invalid_keys: Vec<String>,
+}
+
+/// The outcome of [`StoreCollection::verify_all`], also emitted as the payload of the `store://verified` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+    pub schema: u32,
+    /// The number of stores that were checked.
+    pub checked: usize,
+    pub issues: Vec<StoreIssue>,
+}
+
+/// Returns the path a broken store file is moved to.
+pub(crate) fn quarantine_path(store_path: &Path) -> PathBuf {
+    let mut path = store_path.as_os_str().to_owned();
+    path.push(".quarantined");
+    path.into()
+}
+
+impl<R: Runtime> StoreCollection<R> {
+    /// Reads the file of every registered store, checks its values against the store's schema and emits a
+    /// `store://verified` event with the outcome, so broken stores surface at startup instead of on first access.
+    ///
+    /// The files are loaded into detached copies of the stores, so the stores themselves and their unsaved changes
+    /// are left untouched. Files that can't be read, e.g. because they are corrupt or fail their signature check,
+    /// are moved next to the store file with a `.quarantined` extension.
+    /// Stores without a file yet are fine. Run at startup with [`Builder::verify_on_startup`](crate::Builder::verify_on_startup).
+    pub fn verify_all(&self, app: &AppHandle<R>) -> VerifyReport {
+        let registered = self.registered.lock().expect("mutex poisoned");
+        let copies: Vec<_> = self
+            .stores
+            .lock()
+            .expect("mutex poisoned")
+            .iter()
+            .filter(|(key, _)| registered.contains(*key))
+            .map(|(_, store)| store.detached())
+            .collect();
+        drop(registered);
+
+        let mut report = VerifyReport {
+            schema: PAYLOAD_SCHEMA,
+            checked: 0,
+            issues: Vec::new(),
+        };
+        for mut store in copies {
+            report.checked += 1;
+
+            let mut issue = StoreIssue {
+                path: store.path.clone(),
+                error: None,
+                quarantined: None,
+                invalid_keys: Vec::new(),
+            };
+            match store.load() {
+                Ok(()) => {}
+                Err(Error::Io(err)) if err.kind() == io::ErrorKind::NotFound => {}
+                // the file may be fine, e.g. if it's only locked by another process
+                Err(Error::Io(err)) => issue.error = Some(err.to_string()),
+                Err(err) => {
+                    issue.error = Some(err.to_string());
+                    let store_path = store.store_path();
+                    let target = quarantine_path(&store_path);
+                    match fs::rename(&store_path, &target) {
+                        Ok(()) => issue.quarantined = Some(target),
+                        Err(err) => {
+                            warn!("Failed to quarantine store file {:?}: {}", store_path, err)
+                        }
+                    }
+                }
+            }
+
+            let keys: Vec<String> = store.keys().cloned().collect();
+            issue.invalid_keys = keys
+                .into_iter()
+                .filter(|key| store.get_checked(key).is_err())
+                .collect();
+            issue.invalid_keys.sort();
+
+            if issue.error.is_some() || !issue.invalid_keys.is_empty() {
+                report.issues.push(issue);
+            }
+        }
+
+        report.issues.sort_by(|a, b| a.path.cmp(&b.path));
+        match serde_json::to_value(&report) {
+            Ok(payload) => {
+                if let Err(err) = app.emit_event(VERIFY_EVENT, payload) {
+                    warn!("Failed to emit the store verification report: {}", err);
+                }
+            }
+            Err(err) => warn!("Failed to serialize the store verification report: {}", err),
+        }
+        report
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Persistence of window sizes, positions and maximized states, see [`Builder::window_state`](crate::Builder::window_state).
+
+use crate::{with_store, StoreExt};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, Runtime, Window};
+
+/// The state of a window persisted by [`Builder::window_state`](crate::Builder::window_state), in physical pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowState {
+    pub width: u32,
+    pub height: u32,
+    pub x: i32,
+    pub y: i32,
+    pub maximized: bool,
+}
+
+/// Applies the state saved for `window` in the store at `path`, if any.
+pub(crate) fn restore<R: Runtime>(window: &Window<R>, path: &Path) {
+    let result = window
+        .store_value::<WindowState>(path, window.label())
+        .and_then(|state| {
+            let Some(state) = state else {
+                return Ok(());
+            };
+            if state.width > 0 && state.height > 0 {
+                window.set_size(PhysicalSize::new(state.width, state.height))?;
+            }
+            window.set_position(PhysicalPosition::new(state.x, state.y))?;
+            if state.maximized {
+                window.maximize()?;
+            }
+            Ok(())
+        });
+    if let Err(err) = result {
+        warn!(
+            "Failed to restore the state of window {:?}: {}",
+            window.label(),
+            err
+        );
+    }
+}
+
+/// Saves the current state of the window labeled `label` into the store at `path`.
+///
+/// The size and position of maximized or minimized windows aren't saved, so they are restored to where they were before.
+pub(crate) fn save<R: Runtime>(app: &AppHandle<R>, path: &Path, label: &str) {
+    let Some(window) = app.get_window(label) else {
+        return;
+    };
+    let result = with_store(app.clone(), app.state(), path, |store| {
+        let mut state = store
+            .get(label)
+            .and_then(|value| serde_json::from_value::<WindowState>(value.clone()).ok())
+            .unwrap_or_default();
+        state.maximized = window.is_maximized()?;
+        if !state.maximized && !window.is_minimized()? {
+            let size = window.inner_size()?;
+            let position = window.outer_position()?;
+            state.width = size.width;
+            state.height = size.height;
+            state.x = position.x;
+            state.y = position.y;
+        }
+        let value = serde_json::to_value(state)?;
+        if store.get(label) != Some(&value) {
+            store.insert(label.to_string(), value)?;
+        }
+        Ok(())
+    });
+    if let Err(err) = result {
+        warn!("Failed to save the state of window {:?}: {}", label, err);
+    }
+}
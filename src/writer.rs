@@ -0,0 +1,59 @@
+// Copyright 2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+//! Single-writer mode, enabled with [`StoreBuilder::single_writer`](crate::StoreBuilder::single_writer).
+
+use crate::{Error, Store};
+use serde::{Deserialize, Serialize};
+use tauri::Runtime;
+
+/// The context allowed to modify a store in single-writer mode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "label", rename_all = "camelCase")]
+pub enum Writer {
+    /// Rust code, i.e. modifications not made through a window's command.
+    Backend,
+    /// The window with the given label.
+    Window(String),
+}
+
+impl<R: Runtime> Store<R> {
+    /// Returns the context holding the writer token, or `None` if the store isn't in single-writer mode.
+    pub fn writer(&self) -> Option<&Writer> {
+        self.writer.as_ref()
+    }
+
+    /// Enables single-writer mode with `writer` holding the token, or disables it with `None`.
+    ///
+    /// Unlike [`Self::transfer_writer`], this isn't restricted to the current holder, so keep it out of reach
+    /// of the frontend.
+    pub fn set_writer(&mut self, writer: Option<Writer>) {
+        self.writer = writer;
+    }
+
+    /// Hands the writer token to `writer`. Only the current holder may do so.
+    ///
+    /// Does nothing if the store isn't in single-writer mode.
+    pub fn transfer_writer(&mut self, writer: Writer) -> Result<(), Error> {
+        self.check_writer()?;
+        if self.writer.is_some() {
+            self.writer = Some(writer);
+        }
+        Ok(())
+    }
+
+    /// Fails with [`Error::NotWriter`] if the context modifying the store doesn't hold the writer token.
+    pub(crate) fn check_writer(&self) -> Result<(), Error> {
+        let allowed = match (&self.writer, &self.origin) {
+            (None, _) => true,
+            (Some(Writer::Backend), origin) => origin.is_none(),
+            (Some(Writer::Window(label)), origin) => origin.as_deref() == Some(label.as_str()),
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(Error::NotWriter(self.path.clone()))
+        }
+    }
+}